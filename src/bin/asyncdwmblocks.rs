@@ -5,13 +5,25 @@ use std::process;
 use std::sync::Arc;
 
 use clap::{App, Arg};
+use tokio::io::BufReader;
 use tokio::runtime;
 use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::{broadcast, mpsc, oneshot};
 
+#[cfg(feature = "i3bar")]
+use asyncdwmblocks::i3bar;
 #[cfg(feature = "ipc")]
 use asyncdwmblocks::ipc::{OpaqueServer, Server};
-use asyncdwmblocks::{config::Config, statusbar::StatusBar, x11};
+#[cfg(target_os = "linux")]
+use asyncdwmblocks::signals;
+use asyncdwmblocks::{
+    config::Config,
+    output,
+    statusbar::{
+        Protocol, StatusBar, StatusBarCreationError, StatusBarHandle, StatusBarMessage,
+        StatusBarResponse,
+    },
+};
 
 #[derive(Debug, PartialEq, Clone)]
 struct CliArgs {
@@ -41,19 +53,26 @@ fn parse_cli_args() -> CliArgs {
     }
 }
 
+/// Loads configuration and applies command line arguments to it.
+#[allow(unused_mut, unused_variables)]
+async fn load_config(cli_args: &CliArgs) -> Result<Config, Box<dyn Error>> {
+    let mut config = Config::get_config().await?;
+    #[cfg(feature = "uds")]
+    {
+        config.ipc.uds.force_remove_uds_file = cli_args.force_remove_uds_file;
+    }
+
+    Ok(config)
+}
+
 // Some channels are not used without some features
 #[allow(unused_variables, unused_mut, non_snake_case)]
 async fn run() -> Result<(), Box<dyn Error>> {
     let cli_args = parse_cli_args();
 
-    let x11 = x11::X11Connection::new()?;
+    let config = load_config(&cli_args).await?.arc();
 
-    let mut config = Config::get_config().await?;
-    #[cfg(feature = "uds")]
-    {
-        config.ipc.uds.force_remove_uds_file = cli_args.force_remove_uds_file;
-    }
-    let config = config.arc();
+    let mut sinks = output::sinks(&config)?;
 
     let mut statusbar = StatusBar::try_from(Arc::clone(&config))?;
 
@@ -61,10 +80,11 @@ async fn run() -> Result<(), Box<dyn Error>> {
     // about OS signals and send them to different
     // running tasks to enable them to perform a cleanup
     // before gracefully shutting down.
-    let (termination_signal_sender, termination_signal_receiver) = broadcast::channel(8);
+    let (termination_signal_sender, _) = broadcast::channel(8);
     let mut termination_signal_receiver_statusbar = termination_signal_sender.subscribe();
     let mut termination_signal_receiver_main = termination_signal_sender.subscribe();
     let termination_signal_sender_statusbar = termination_signal_sender.clone();
+    let termination_signal_sender_server = termination_signal_sender.clone();
 
     // This channel is used to tell other tasks that
     // server ended with error.
@@ -75,11 +95,17 @@ async fn run() -> Result<(), Box<dyn Error>> {
     #[cfg(feature = "ipc")]
     let (server_error_sender, mut server_error_receiver) = oneshot::channel();
 
-    // This channel is used by IPC server to send BlockRefreshMessages.
-    let (server_sender, server_receiver) = mpsc::channel(8);
+    // This channel is used to pass reloaded configuration
+    // to IPC server task, when server has to be rebinded.
+    #[cfg(feature = "ipc")]
+    let (server_config_sender, mut server_config_receiver) = mpsc::channel::<Arc<Config>>(1);
+
+    // This channel is used by IPC server (and signals listener) to send StatusBarRequests.
+    let (statusbar_handle, server_receiver) = StatusBarHandle::channel(8);
+    let server_sender = statusbar_handle.sender();
 
     // This channel is used to send computed status bar from
-    // statusbar task to update xroot name task.
+    // statusbar task to writing outputs task.
     let (statusbar_sender, mut statusbar_receiver) = mpsc::channel(8);
 
     // OS signals
@@ -89,7 +115,6 @@ async fn run() -> Result<(), Box<dyn Error>> {
     let mut SIGTERM = signal(SignalKind::terminate())?;
     tokio::spawn(async move {
         tokio::select! {
-            _ = SIGHUP.recv() => {},
             _ = SIGINT.recv() => {},
             _ = SIGQUIT.recv() => {},
             _ = SIGTERM.recv() => {},
@@ -98,19 +123,112 @@ async fn run() -> Result<(), Box<dyn Error>> {
         let _ = termination_signal_sender.send(());
     });
 
+    // Real-time signals (compatible with dwmblocks and dwm's statuscmd patch).
+    #[cfg(target_os = "linux")]
+    let signals_listener = signals::listen(&config, server_sender.clone())?;
+
+    // Click events of i3bar (and swaybar) are read from stdin.
+    #[cfg(feature = "i3bar")]
+    if config.statusbar.protocol == Protocol::I3bar {
+        let server_sender = server_sender.clone();
+        tokio::spawn(async move {
+            let stdin = BufReader::new(tokio::io::stdin());
+            if let Err(e) = i3bar::listen(stdin, server_sender).await {
+                eprintln!("Failed to read click events: {}", e);
+            }
+        });
+    }
+
+    // Reloading configuration on SIGHUP. Errors are reported,
+    // but running status bar keeps it's previous configuration.
+    {
+        let mut config = Arc::clone(&config);
+        let statusbar_handle = statusbar_handle.clone();
+        #[cfg(feature = "ipc")]
+        let server_config_sender = server_config_sender.clone();
+        tokio::spawn(async move {
+            while SIGHUP.recv().await.is_some() {
+                let new_config = match load_config(&cli_args).await {
+                    Ok(new_config) => new_config.arc(),
+                    Err(e) => {
+                        eprintln!("Failed to reload configuration: {}", e);
+                        continue;
+                    }
+                };
+
+                // Outputs are opened only once, at startup.
+                if new_config.outputs != config.outputs {
+                    let e = StatusBarCreationError::RestartRequired(String::from("outputs"));
+                    eprintln!("Failed to reload configuration: {}", e);
+                    continue;
+                }
+
+                let message = StatusBarMessage::Reload(Arc::clone(&new_config));
+                match statusbar_handle.request(message).await {
+                    Ok(StatusBarResponse::InvalidConfig(e)) => {
+                        eprintln!("Failed to reload configuration: {}", e);
+                        continue;
+                    }
+                    Ok(_) => {}
+                    // Statusbar has stopped running.
+                    Err(_) => break,
+                }
+
+                #[cfg(target_os = "linux")]
+                if let Err(e) = signals_listener.update(&new_config) {
+                    eprintln!("Failed to update signals: {}", e);
+                }
+                #[cfg(feature = "ipc")]
+                if new_config.ipc != config.ipc {
+                    let _ = server_config_sender.send(Arc::clone(&new_config)).await;
+                }
+                config = new_config;
+            }
+        });
+    }
+
     // IPC server
     #[cfg(feature = "ipc")]
     tokio::spawn(async move {
-        let mut server = OpaqueServer::new(
-            server_sender,
-            termination_signal_receiver,
-            Arc::clone(&config),
-        );
-
-        if let Err(e) = server.run().await {
-            // If sending failed that mean that we are already finishing
-            let _ = server_error_sender.send(e);
-            let _ = server_termination_error_sender.send(());
+        let mut config = config;
+        let mut rebinded = false;
+        loop {
+            let mut server = OpaqueServer::new(
+                server_sender.clone(),
+                termination_signal_sender_server.subscribe(),
+                Arc::clone(&config),
+            );
+            let mut handle = tokio::spawn(async move { server.run().await });
+
+            tokio::select! {
+                result = &mut handle => {
+                    match result {
+                        // Server failed after reloading configuration. Report it
+                        // and wait for configuration to be fixed.
+                        Ok(Err(e)) if rebinded => {
+                            eprintln!("IPC server failed: {}", e);
+                            match server_config_receiver.recv().await {
+                                Some(new_config) => config = new_config,
+                                None => break,
+                            }
+                        }
+                        Ok(Err(e)) => {
+                            // If sending failed that mean that we are already finishing
+                            let _ = server_error_sender.send(e);
+                            let _ = server_termination_error_sender.send(());
+                            break;
+                        }
+                        _ => break,
+                    }
+                }
+                Some(new_config) = server_config_receiver.recv() => {
+                    // Server is dropped together with it's task, which unbinds it.
+                    handle.abort();
+                    let _ = handle.await;
+                    config = new_config;
+                }
+            }
+            rebinded = true;
         }
     });
 
@@ -126,10 +244,14 @@ async fn run() -> Result<(), Box<dyn Error>> {
         };
     });
 
-    // Updating xroot name
+    // Writing status bar to outputs
     tokio::spawn(async move {
         while let Some(msg) = statusbar_receiver.recv().await {
-            x11.set_root_name(&msg);
+            for sink in sinks.iter_mut() {
+                if let Err(e) = sink.write(&msg).await {
+                    eprintln!("Failed to write status bar to {}: {}", sink.describe(), e);
+                }
+            }
         }
     });
 
@@ -2,14 +2,17 @@ use std::error::Error;
 use std::fmt;
 use std::process;
 
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, ArgMatches};
 use tokio::runtime;
 
 use asyncdwmblocks::{
     block::BlockRunMode,
     config::Config,
-    ipc::{Notifier, OpaqueNotifier},
-    statusbar::BlockRefreshMessage,
+    ipc::{
+        frame::{Event, Frame, Response},
+        Notifier, OpaqueNotifier,
+    },
+    statusbar::{BlockRefreshMessage, BlockUpdate},
 };
 
 #[derive(Debug, PartialEq, Clone)]
@@ -23,14 +26,138 @@ impl fmt::Display for CliArgsParseError {
 
 impl Error for CliArgsParseError {}
 
-fn parse_cli_args() -> Result<BlockRefreshMessage, CliArgsParseError> {
+/// Parses value of an argument that must be a number.
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, CliArgsParseError>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .parse::<T>()
+        .map_err(|e| CliArgsParseError(format!("{} must be a number: {}", name, e)))
+}
+
+/// Parses value of `--interval` option (`-` means no interval).
+fn parse_interval(value: &str) -> Result<Option<u64>, CliArgsParseError> {
+    match value {
+        "-" => Ok(None),
+        value => match parse_number(value, "Interval")? {
+            0 => Err(CliArgsParseError(String::from(
+                "Interval must be at least 1 second",
+            ))),
+            interval => Ok(Some(interval)),
+        },
+    }
+}
+
+/// Returns command and it's arguments given to a subcommand.
+fn command_args(matches: &ArgMatches) -> Option<(String, Vec<String>)> {
+    let mut values = matches.values_of("command")?.map(String::from);
+    let command = values.next()?;
+    Some((command, values.collect()))
+}
+
+fn parse_cli_args() -> Result<Frame, CliArgsParseError> {
     let app = App::new("asyncdwmblocks-notifier")
         .about("Send notifications to asyncdwmblocks")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .subcommand(App::new("list").about("List blocks of running asyncdwmblocks"))
+        .subcommand(
+            App::new("get")
+                .about("Print current output of a block")
+                .arg(Arg::new("block").required(true).help("Name of a block")),
+        )
+        .subcommand(App::new("status").about("Print current status bar"))
+        .subcommand(
+            App::new("subscribe")
+                .about("Print every new status bar")
+                .arg(
+                    Arg::new("blocks")
+                        .long("blocks")
+                        .help("Print also changes of single blocks (as <block>\\t<result>)"),
+                ),
+        )
+        .subcommand(
+            App::new("insert")
+                .about("Add a new block")
+                .setting(AppSettings::TrailingVarArg)
+                .arg(
+                    Arg::new("index")
+                        .required(true)
+                        .help("Position of a new block"),
+                )
+                .arg(Arg::new("block").required(true).help("Name of a new block"))
+                .arg(
+                    Arg::new("interval")
+                        .short('i')
+                        .long("interval")
+                        .takes_value(true)
+                        .help("Refresh block every <interval> seconds"),
+                )
+                .arg(
+                    Arg::new("command")
+                        .required(true)
+                        .multiple_values(true)
+                        .help("Command (with it's arguments) of a new block"),
+                ),
+        )
+        .subcommand(
+            App::new("remove")
+                .about("Remove a block")
+                .arg(Arg::new("block").required(true).help("Name of a block")),
+        )
+        .subcommand(
+            App::new("move")
+                .about("Move a block to a new position")
+                .arg(Arg::new("block").required(true).help("Name of a block"))
+                .arg(
+                    Arg::new("index")
+                        .required(true)
+                        .help("New position of a block"),
+                ),
+        )
+        .subcommand(
+            App::new("set")
+                .about("Set text of a block (without running it's command)")
+                .setting(AppSettings::TrailingVarArg)
+                .arg(Arg::new("block").required(true).help("Name of a block"))
+                .arg(
+                    Arg::new("text")
+                        .multiple_values(true)
+                        .help("Block's new text (empty if not given)"),
+                ),
+        )
+        .subcommand(
+            App::new("change")
+                .about("Change block's command or interval")
+                .setting(AppSettings::TrailingVarArg)
+                .arg(Arg::new("block").required(true).help("Name of a block"))
+                .arg(
+                    Arg::new("interval")
+                        .short('i')
+                        .long("interval")
+                        .takes_value(true)
+                        .help("Refresh block every <interval> seconds (- disables refreshing)"),
+                )
+                .arg(
+                    Arg::new("command")
+                        .multiple_values(true)
+                        .required_unless_present("interval")
+                        .help("New command (with it's arguments)"),
+                ),
+        )
         .arg(
             Arg::new("block")
-                .required(true)
+                .required_unless_present("all")
                 .help("Name of a block that you wish to reload"),
         )
+        .arg(
+            Arg::new("all")
+                .short('a')
+                .long("all")
+                .conflicts_with_all(&["block", "button"])
+                .help("Reload all blocks"),
+        )
         .arg(
             Arg::new("button")
                 .short('b')
@@ -40,6 +167,71 @@ fn parse_cli_args() -> Result<BlockRefreshMessage, CliArgsParseError> {
         );
 
     let matches = app.get_matches();
+    match matches.subcommand() {
+        Some(("list", _)) => return Ok(Frame::List),
+        Some(("get", matches)) => {
+            // Block is a required argument.
+            let block = matches.value_of("block").unwrap();
+            return Ok(Frame::Get(block.to_string()));
+        }
+        Some(("status", _)) => return Ok(Frame::Status),
+        Some(("subscribe", matches)) => {
+            let blocks = matches.is_present("blocks");
+            return Ok(Frame::Subscribe { blocks });
+        }
+        // Positional arguments of subcommands are required,
+        // unless stated otherwise.
+        Some(("insert", matches)) => {
+            let index = parse_number(matches.value_of("index").unwrap(), "Index")?;
+            let name = matches.value_of("block").unwrap().to_string();
+            let interval = match matches.value_of("interval") {
+                Some(interval) => parse_interval(interval)?,
+                None => None,
+            };
+            let (command, args) = command_args(matches).unwrap();
+            return Ok(Frame::Insert {
+                index,
+                name,
+                interval,
+                command,
+                args,
+            });
+        }
+        Some(("remove", matches)) => {
+            let block = matches.value_of("block").unwrap();
+            return Ok(Frame::Remove(block.to_string()));
+        }
+        Some(("move", matches)) => {
+            let name = matches.value_of("block").unwrap().to_string();
+            let index = parse_number(matches.value_of("index").unwrap(), "Index")?;
+            return Ok(Frame::Move { name, index });
+        }
+        Some(("set", matches)) => {
+            let name = matches.value_of("block").unwrap().to_string();
+            let text = match matches.values_of("text") {
+                Some(text) => text.collect::<Vec<_>>().join(" "),
+                None => String::new(),
+            };
+            return Ok(Frame::Set { name, text });
+        }
+        Some(("change", matches)) => {
+            let name = matches.value_of("block").unwrap().to_string();
+            let mut update = BlockUpdate::new();
+            if let Some(interval) = matches.value_of("interval") {
+                update = update.with_interval(parse_interval(interval)?);
+            }
+            if let Some((command, args)) = command_args(matches) {
+                update = update.with_command(command, args);
+            }
+            return Ok(Frame::Change { name, update });
+        }
+        _ => {}
+    }
+
+    if matches.is_present("all") {
+        return Ok(Frame::RefreshAll);
+    }
+
     let block = matches
         .value_of("block")
         .ok_or_else(|| CliArgsParseError(String::from("Specify which block should be reloaded")))?;
@@ -60,7 +252,7 @@ fn parse_cli_args() -> Result<BlockRefreshMessage, CliArgsParseError> {
         None => BlockRunMode::Normal,
     };
     let message = BlockRefreshMessage::new(name, mode);
-    Ok(message)
+    Ok(message.into())
 }
 
 async fn run() -> Result<(), Box<dyn Error>> {
@@ -69,8 +261,33 @@ async fn run() -> Result<(), Box<dyn Error>> {
 
     let mut notifier = OpaqueNotifier::new(config);
 
-    notifier.push_message(msg);
-    notifier.send_messages().await?;
+    if let Frame::Subscribe { blocks } = msg {
+        let mut events = notifier.subscribe(blocks).await?;
+        while let Some(event) = events.recv().await {
+            match event {
+                Event::Status(status) => println!("{}", status),
+                Event::Block { name, result } => println!("{}\t{}", name, result),
+            }
+        }
+        return Ok(());
+    }
+
+    notifier.push_frame(msg);
+    for response in notifier.send_messages().await? {
+        match response {
+            Response::Value(Some(value)) => println!("{}", value),
+            Response::List(blocks) => {
+                for block in blocks {
+                    let interval = match block.interval {
+                        Some(interval) => format!("{}s", interval),
+                        None => String::from("-"),
+                    };
+                    println!("{}\t{}\t{}", block.name, interval, block.command);
+                }
+            }
+            _ => {}
+        }
+    }
 
     Ok(())
 }
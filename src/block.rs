@@ -1,27 +1,48 @@
 //! This module defines [Block] type and it's errors.
 
+use std::borrow::Cow;
 use std::error::Error;
 use std::fmt;
+use std::future::Future;
+use std::process::Stdio;
 use std::sync::Arc;
 
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
+#[cfg(feature = "config-file")]
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio::task;
-use tokio::time::{interval_at, Duration, Instant, Interval, MissedTickBehavior};
+use tokio::time::{self, interval_at, Duration, Instant, Interval, MissedTickBehavior};
 
 use crate::config::Config;
+use crate::utils::{self, Template};
+
+/// Name of a placeholder replaced by Block's result in it's [format](Block::with_format).
+pub const OUTPUT_PLACEHOLDER: &str = "output";
+
+/// Delay before first restart of a persistent block's command.
+const PERSISTENT_RESTART_DELAY_MIN: Duration = Duration::from_secs(1);
+/// Maximal delay between restarts of a persistent block's command.
+const PERSISTENT_RESTART_DELAY_MAX: Duration = Duration::from_secs(60);
 
 /// Error that may occur when running (and awaiting) [Block::run].
 ///
-/// While awaiting for `Block::run()` three things could happen wrong:
+/// While awaiting for `Block::run()` five things could happen wrong:
 ///
 ///  1. Execution of provided command could fail (represented by `CommandError` variant).
-///  2. Task spawned by `tokio` failed to finish (represented by `JoinError` variant).
-///  3. Channel used to communicate stdout of running command closed before
-///  sending value (represented by `ChannelClosed` variant).
+///  2. [BlockSource] of a builtin block could fail (represented by `SourceError` variant).
+///  3. Command didn't finish in time and was killed (represented by `Timeout` variant).
+///  4. Task spawned by `tokio` failed to finish (represented by `JoinError` variant).
+///  5. Channel used to communicate stdout of running command closed before
+///     sending value (represented by `ChannelClosed` variant).
 ///
 /// Depending on which variant happened different action might be appropriate.
-/// If it is the first case then this error is probably user fault. We can then
+/// If it is one of the first three cases then this error is probably user fault. We can then
 /// choose to end program, log it, inform user or simply ignore it. If it is on
 /// the other hand the latter case, then it is probably internal bug that should
 /// be reported.
@@ -58,6 +79,10 @@ use crate::config::Config;
 pub enum BlockRunError {
     /// io error that happened when Command was executed.
     CommandError(std::io::Error),
+    /// [BlockSource] failed to produce output.
+    SourceError(Box<dyn Error + Send + Sync>),
+    /// Command was running longer than it's timeout and was killed.
+    Timeout(Duration),
     /// tokio's JoinError that happened in spawned job.
     JoinError(task::JoinError),
     /// tokio's oneshot channel was closed before it could receive computation result.
@@ -68,6 +93,8 @@ impl fmt::Display for BlockRunError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let msg = match self {
             Self::CommandError(e) => e.to_string(),
+            Self::SourceError(e) => e.to_string(),
+            Self::Timeout(t) => format!("Command timed out after {}s", t.as_secs()),
             Self::JoinError(e) => e.to_string(),
             Self::ChannelClosed => "Channel was closed".to_string(),
         };
@@ -104,19 +131,19 @@ impl BlockRunError {
     pub fn is_internal(&self) -> bool {
         match self {
             Self::JoinError(_) | BlockRunError::ChannelClosed => true,
-            Self::CommandError(_) => false,
+            Self::CommandError(_) | Self::SourceError(_) | Self::Timeout(_) => false,
         }
     }
 
     /// Returns true if error is external (failure to run a command).
     ///
     /// This error is probably user fault and can be ignored (if user wishes so).
-    /// It could be caused by user providing wrong command, not having proper
-    /// permissions to run a script, `$PATH` being wrongly set, etc.
+    /// It could be caused by user providing wrong command, builtin failing, not having proper
+    /// permissions to run a script, `$PATH` being wrongly set, script hanging, etc.
     pub fn is_io(&self) -> bool {
         match self {
             Self::JoinError(_) | BlockRunError::ChannelClosed => false,
-            Self::CommandError(_) => true,
+            Self::CommandError(_) | Self::SourceError(_) | Self::Timeout(_) => true,
         }
     }
 }
@@ -163,16 +190,440 @@ impl BlockRunMode {
     }
 }
 
-// TODO: If result is &self and run is &mut self does it mean that
-// we can't get past result while we are await current computation?
+/// This enum represents how Block's command produces it's output.
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[cfg_attr(feature = "config-file", derive(Deserialize))]
+#[cfg_attr(feature = "config-file", serde(rename_all = "lowercase"))]
+pub enum BlockMode {
+    /// Command is run every time block is refreshed and
+    /// first line of it's output becomes block's result.
+    #[default]
+    Oneshot,
+    /// Command is run once and is expected to keep running.
+    /// Every line it prints becomes block's new result.
+    /// When it exits it is restarted (with increasing delay).
+    /// Refreshing such block restarts it's command.
+    Persistent,
+    /// Block has no command. It's result is set directly (for example
+    /// by other programs over IPC) and refreshing it does nothing.
+    Push,
+}
+
+/// Policy deciding what happens when [Block] is triggered (by it's
+/// interval or by a request) while it's previous run hasn't finished yet.
+///
+/// Global policy is set in [Config](crate::config::ConfigBlock::busy_policy)
+/// and can be overridden for each block.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "config-file", derive(Deserialize))]
+#[cfg_attr(feature = "config-file", serde(rename_all = "lowercase"))]
+pub enum BusyPolicy {
+    /// Ignore new trigger.
+    Skip,
+    /// Run block once again after current run finishes.
+    /// Multiple triggers are merged into one (the last one wins).
+    Queue,
+    /// Cancel (and kill) current run and start a new one.
+    Restart,
+}
+
+/// Policy deciding what [Block] displays when it's command exits with non-zero code.
+///
+/// Global policy is set in [Config](crate::config::ConfigBlock::error_policy)
+/// and can be overridden for each block.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "config-file", derive(Deserialize))]
+#[cfg_attr(feature = "config-file", serde(rename_all = "lowercase"))]
+pub enum ErrorPolicy {
+    /// Display command's output as if it succeeded.
+    Show,
+    /// Keep displaying previous result.
+    Keep,
+    /// Display error marker (see [error_marker](crate::config::ConfigBlock::error_marker)).
+    Marker,
+    /// Display first line of command's stderr.
+    Stderr,
+}
+
+/// Settings of retrying failed runs of a [Block].
+///
+/// Run failed if it returned an error, or if Block's command exited with non-zero code.
+/// Then Block is run again after a delay, which is multiplied by `backoff` after each
+/// consecutive failure. Only [oneshot](BlockMode::Oneshot) Blocks are retried.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "config-file", derive(Deserialize))]
+#[cfg_attr(feature = "config-file", serde(default))]
+pub struct Retry {
+    /// Maximal number of retries after consecutive failed runs.
+    pub max_attempts: u32,
+    /// Delay (in seconds) before the first retry.
+    pub initial_delay: u64,
+    /// Factor by which delay is multiplied after each retry (at least 1).
+    pub backoff: f64,
+    /// Fraction of a delay (from 0 to 1) by which it is randomly
+    /// lengthened or shortened (for example `0.1` means ±10%).
+    pub jitter: f64,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: 1,
+            backoff: 2.0,
+            jitter: 0.1,
+        }
+    }
+}
+
+impl Retry {
+    /// Checks if `backoff` is a finite number not smaller than 1,
+    /// and `jitter` is a number from range `[0, 1]`.
+    ///
+    /// # Example
+    /// ```
+    /// use asyncdwmblocks::block::Retry;
+    ///
+    /// assert!(Retry::default().validate().is_ok());
+    /// assert!(Retry { backoff: 0.5, ..Default::default() }.validate().is_err());
+    /// assert!(Retry { jitter: f64::NAN, ..Default::default() }.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.backoff.is_finite() && self.backoff >= 1.0) {
+            return Err(format!("backoff must be at least 1, got {}", self.backoff));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(format!("jitter must be from 0 to 1, got {}", self.jitter));
+        }
+
+        Ok(())
+    }
+
+    /// Returns delay before retry number `attempt` (counted from 0), without jitter.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use asyncdwmblocks::block::Retry;
+    ///
+    /// let retry = Retry { initial_delay: 2, backoff: 3.0, ..Default::default() };
+    /// assert_eq!(retry.delay(0), Duration::from_secs(2));
+    /// assert_eq!(retry.delay(2), Duration::from_secs(18));
+    /// ```
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.initial_delay as f64 * self.backoff.powi(attempt as i32);
+        Duration::from_secs_f64(delay.clamp(0.0, u32::MAX as f64))
+    }
+
+    /// Returns [delay](Retry::delay) changed by jitter. **random** is a number from
+    /// range `[0, 1)`, that is mapped to the range from `1 - jitter` to `1 + jitter`
+    /// of the delay.
+    fn jittered_delay(&self, attempt: u32, random: f64) -> Duration {
+        self.delay(attempt)
+            .mul_f64(1.0 + self.jitter * (random * 2.0 - 1.0))
+    }
+}
+
+/// Output of a [Block]'s [computation](Block::computation).
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockOutput {
+    /// Text produced by Block (first line of command's stdout).
+    pub text: String,
+    /// Exit code of Block's command. It is `None` for builtin and persistent
+    /// Blocks, and when command was terminated by a signal.
+    pub exit_code: Option<i32>,
+    /// First line of command's stderr.
+    pub stderr: String,
+}
+
+impl From<String> for BlockOutput {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            exit_code: None,
+            stderr: String::new(),
+        }
+    }
+}
+
+/// Returns first line of `output`, without `\u{0}` characters.
+fn first_line(output: &[u8]) -> String {
+    String::from_utf8_lossy(output)
+        .chars()
+        .filter(|c| c != &'\u{0}')
+        .take_while(|c| c != &'\n')
+        .collect()
+}
+
+/// Rules deciding when [Block] is hidden from status bar (together with it's delimiter).
+///
+/// Rules are evaluated every time Block's result is updated.
+/// Block is hidden if any of them matches.
+#[derive(Debug, Clone, Default)]
+pub struct HideRules {
+    /// Hide Block when it's result is empty.
+    pub when_empty: bool,
+    /// Hide Block when it's command exited with this code.
+    pub when_exit_code: Option<i32>,
+    /// Hide Block when it's result matches this regular expression.
+    pub when_matches: Option<Regex>,
+}
+
+impl HideRules {
+    /// Checks if Block with `result` (produced by command that
+    /// exited with `exit_code`) should be hidden.
+    pub fn hides(&self, result: &str, exit_code: Option<i32>) -> bool {
+        (self.when_empty && result.is_empty())
+            || (self.when_exit_code.is_some() && self.when_exit_code == exit_code)
+            || matches!(&self.when_matches, Some(regex) if regex.is_match(result))
+    }
+}
+
+/// Regular expressions are compared by their patterns.
+impl PartialEq for HideRules {
+    fn eq(&self, other: &Self) -> bool {
+        self.when_empty == other.when_empty
+            && self.when_exit_code == other.when_exit_code
+            && self.when_matches.as_ref().map(Regex::as_str)
+                == other.when_matches.as_ref().map(Regex::as_str)
+    }
+}
+
+/// Wall clock to which [aligned](BlockSource::alignment) Block's interval is aligned.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Alignment {
+    /// Local time.
+    Local,
+    /// Time in a given timezone.
+    Timezone(Tz),
+}
+
+impl Alignment {
+    /// Returns offset (in seconds) of this clock from UTC at **time**.
+    fn offset(&self, time: DateTime<Utc>) -> i32 {
+        let time = time.naive_utc();
+        match self {
+            Self::Local => Local.offset_from_utc_datetime(&time).fix(),
+            Self::Timezone(timezone) => timezone.offset_from_utc_datetime(&time).fix(),
+        }
+        .local_minus_utc()
+    }
+}
+
+/// Source of a [Block]'s output.
+///
+/// Every time Block is run, it's source produces Block's new output.
+/// Commands are run by [CommandSource], while builtin blocks implement
+/// this trait to compute their output in-process (without spawning processes).
+/// Since source is kept between runs, it can also keep some state.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use async_trait::async_trait;
+/// use asyncdwmblocks::block::{Block, BlockRunError, BlockRunMode, BlockSource};
+/// use asyncdwmblocks::config::Config;
+///
+/// #[derive(Debug)]
+/// struct Counter(u64);
+///
+/// #[async_trait]
+/// impl BlockSource for Counter {
+///     async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+///         self.0 += 1;
+///         Ok(self.0.to_string())
+///     }
+/// }
+///
+/// # async fn doc() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = Config::default().arc();
+/// let mut block = Block::from_source(Box::new(Counter(0)), Some(1), config);
+/// block.run(BlockRunMode::Normal).await?;
+/// block.run(BlockRunMode::Normal).await?;
+///
+/// assert_eq!(block.result(), Some(&String::from("2")));
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait BlockSource: fmt::Debug + Send {
+    /// Produces Block's new output. [mode](BlockRunMode) tells if Block was clicked.
+    async fn produce(&mut self, mode: BlockRunMode) -> Result<String, BlockRunError>;
+
+    /// Returns description of this source, used in place of Block's command
+    /// (for example when listing blocks).
+    fn describe(&self) -> String {
+        String::from("builtin")
+    }
+
+    /// Returns wall clock to which Block's interval should be aligned
+    /// (see [get_scheduler](Block::get_scheduler)). Defaults to `None`.
+    fn alignment(&self) -> Option<Alignment> {
+        None
+    }
+}
+
+/// [BlockSource] that runs a command (with it's arguments) as a separate process.
+///
+/// This is a source of every Block created with [Block::new].
+#[derive(Debug, PartialEq, Clone)]
+pub struct CommandSource {
+    command: String,
+    args: Vec<String>,
+    timeout: Option<Duration>,
+    config: Arc<Config>,
+}
+
+impl CommandSource {
+    /// Creates new `CommandSource`. If command runs longer than `timeout`,
+    /// then it (and all processes in it's process group) is killed.
+    pub fn new(
+        command: String,
+        args: Vec<String>,
+        timeout: Option<Duration>,
+        config: Arc<Config>,
+    ) -> Self {
+        Self {
+            command,
+            args,
+            timeout,
+            config,
+        }
+    }
+}
+
+impl CommandSource {
+    /// Runs command by running tokio's **`spawn_blocking`** and returns first line of it's
+    /// output (stdout), without `\u{0}` characters, together with it's exit code and
+    /// first line of stderr. `$BUTTON` is set if Block was clicked.
+    ///
+    /// Command runs in it's own process group, which is killed on timeout
+    /// or when returned future is dropped before command exits.
+    pub async fn run(&mut self, mode: BlockRunMode) -> Result<BlockOutput, BlockRunError> {
+        let command = self.command.clone();
+        let args = self.args.clone();
+        let timeout = self.timeout;
+        let config = Arc::clone(&self.config);
+        let (sender, receiver) = oneshot::channel();
+
+        task::spawn_blocking(move || async move {
+            let mut command = Command::new(command);
+            let command = command
+                .args(args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true);
+            let command = match mode.button() {
+                Some(b) => command.env(&config.block.clicked_env_variable, b.to_string()),
+                None => command,
+            };
+            set_process_group(command);
+
+            let output = async {
+                let child = command.spawn()?;
+                // Kills command along with it's children on timeout,
+                // or when this future is dropped (for example when
+                // Block's task is aborted).
+                let mut group = ProcessGroupGuard(child.id());
+
+                let output = match timeout {
+                    Some(timeout) => match time::timeout(timeout, child.wait_with_output()).await {
+                        Ok(output) => output?,
+                        Err(_) => return Err(BlockRunError::Timeout(timeout)),
+                    },
+                    None => child.wait_with_output().await?,
+                };
+                group.disarm();
+
+                Ok(output)
+            };
+
+            // ignore sending error
+            let _ = sender.send(output.await);
+        })
+        .await?
+        .await;
+
+        let output = receiver.await??;
+
+        Ok(BlockOutput {
+            text: first_line(&output.stdout),
+            exit_code: output.status.code(),
+            stderr: first_line(&output.stderr),
+        })
+    }
+}
+
+#[async_trait]
+impl BlockSource for CommandSource {
+    /// Runs command (see [run](CommandSource::run)) and returns it's output.
+    async fn produce(&mut self, mode: BlockRunMode) -> Result<String, BlockRunError> {
+        self.run(mode).await.map(|output| output.text)
+    }
+
+    fn describe(&self) -> String {
+        let mut description = self.command.clone();
+        for arg in &self.args {
+            description.push(' ');
+            description.push_str(arg);
+        }
+        description
+    }
+}
+
+/// [BlockSource] shared by all clones of a builtin [Block].
+type SharedSource = Arc<Mutex<Box<dyn BlockSource>>>;
+
+/// Where [Block]'s output comes from.
+#[derive(Debug, Clone)]
+enum Source {
+    /// Block's command is run by a [CommandSource].
+    Command,
+    /// Block is builtin, computed in-process.
+    Builtin(SharedSource),
+}
+
+/// Builtin sources are equal only if they are the same source.
+impl PartialEq for Source {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Command, Self::Command) => true,
+            (Self::Builtin(a), Self::Builtin(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
 
 /// This struct represents single status bar block.
+///
+/// Block either runs a command, or is a builtin block computed
+/// by a [BlockSource] (see [from_source](Block::from_source)).
 #[derive(Debug, PartialEq, Clone)]
 pub struct Block {
     command: String,
     args: Vec<String>,
+    source: Source,
     interval: Option<Duration>,
+    alignment: Option<Alignment>,
+    timeout: Option<Duration>,
+    busy_policy: Option<BusyPolicy>,
+    retry: Option<Retry>,
+    retry_attempts: u32,
+    error_policy: Option<ErrorPolicy>,
+    error_marker: Option<String>,
+    mode: BlockMode,
+    signal: Option<u8>,
+    ttl: Option<Duration>,
+    format: Option<Template>,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    color: Option<String>,
+    hide_rules: HideRules,
+    hidden: bool,
     result: Option<String>,
+    last_exit_status: Option<i32>,
+    last_error: Option<String>,
     config: Arc<Config>,
 }
 
@@ -195,8 +646,8 @@ impl Block {
     ///  - `command`: command that should be executed every time this block is reloaded
     ///  - `args`: arguments to this command
     ///  - `interval`: at which rate (in seconds) this block should reload.
-    ///  If `None` then it won't be automatically reload (but still can be by sending
-    ///  proper signal to status bar)
+    ///    If `None` then it won't be automatically reload (but still can be by sending
+    ///    proper signal to status bar)
     ///  - `config`: an Arc of a global configuration
     ///
     ///  # Panics
@@ -209,18 +660,229 @@ impl Block {
         config: Arc<Config>,
     ) -> Self {
         // TODO: make new accept Cows instead of Strings.
+        Self::with_source(command, args, Source::Command, interval, config)
+    }
+
+    /// Creates a new builtin `Block`, whose output is produced by **source**.
+    /// Source's [description](BlockSource::describe) is used as Block's command.
+    ///
+    /// Block's [timeout](Block::with_timeout) is applied to producing output, but
+    /// unlike commands source is not killed, only stopped. [Persistent](BlockMode::Persistent)
+    /// builtin Blocks produce output once, every time they are (re)started.
+    ///
+    ///  # Panics
+    ///  If `interval` is `Some`, then it must be greater than 0. Interval with value
+    ///  `Some(0)` will panic.
+    pub fn from_source(
+        source: Box<dyn BlockSource>,
+        interval: Option<u64>,
+        config: Arc<Config>,
+    ) -> Self {
+        let command = source.describe();
+        Self::builtin(command, vec![], source, interval, config)
+    }
+
+    /// Creates a new builtin `Block` defined (in configuration) by `command` and `args`.
+    pub(crate) fn builtin(
+        command: String,
+        args: Vec<String>,
+        source: Box<dyn BlockSource>,
+        interval: Option<u64>,
+        config: Arc<Config>,
+    ) -> Self {
+        let alignment = source.alignment();
+        let source = Source::Builtin(Arc::new(Mutex::new(source)));
+        let mut block = Self::with_source(command, args, source, interval, config);
+        block.alignment = alignment;
+        block
+    }
+
+    fn with_source(
+        command: String,
+        args: Vec<String>,
+        source: Source,
+        interval: Option<u64>,
+        config: Arc<Config>,
+    ) -> Self {
         if interval.is_some() {
             assert!(interval > Some(0), "Interval must be at least 1 second.");
         }
         Self {
             command,
             args,
+            source,
             interval: interval.map(Duration::from_secs),
+            alignment: None,
+            timeout: None,
+            busy_policy: None,
+            retry: None,
+            retry_attempts: 0,
+            error_policy: None,
+            error_marker: None,
+            mode: BlockMode::Oneshot,
+            signal: None,
+            ttl: None,
+            format: None,
+            prefix: None,
+            suffix: None,
+            color: None,
+            hide_rules: HideRules::default(),
+            hidden: false,
             result: None,
+            last_exit_status: None,
+            last_error: None,
             config,
         }
     }
 
+    /// Sets timeout (in seconds) after which Block's command is killed.
+    ///
+    /// If `None` then global [timeout](crate::config::ConfigBlock::timeout)
+    /// from config is used.
+    ///
+    /// # Panics
+    /// If `timeout` is `Some`, then it must be greater than 0. Timeout with value
+    /// `Some(0)` will panic.
+    ///
+    /// # Example
+    /// ```
+    /// use asyncdwmblocks::block::Block;
+    /// use asyncdwmblocks::config::Config;
+    ///
+    /// let config = Config::default().arc();
+    /// let weather = Block::new("curl".to_string(), vec!["wttr.in".to_string()], Some(600), config)
+    ///     .with_timeout(Some(10));
+    /// ```
+    pub fn with_timeout(mut self, timeout: Option<u64>) -> Self {
+        if timeout.is_some() {
+            assert!(timeout > Some(0), "Timeout must be at least 1 second.");
+        }
+        self.timeout = timeout.map(Duration::from_secs);
+        self
+    }
+
+    /// Sets Block's [BlockMode].
+    ///
+    /// For [persistent](BlockMode::Persistent) blocks interval, timeout and
+    /// busy policy are ignored.
+    pub fn with_mode(mut self, mode: BlockMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets Block's real-time signal offset.
+    ///
+    /// See [signal](crate::config::ConfigStatusBarBlock::signal).
+    pub fn with_signal(mut self, signal: Option<u8>) -> Self {
+        self.signal = signal;
+        self
+    }
+
+    /// Sets time (in seconds) after which text set directly
+    /// to this Block expires. See [ttl](crate::config::ConfigStatusBarBlock::ttl).
+    ///
+    /// # Panics
+    /// If `ttl` is `Some`, then it must be greater than 0. TTL with value
+    /// `Some(0)` will panic.
+    pub fn with_ttl(mut self, ttl: Option<u64>) -> Self {
+        if ttl.is_some() {
+            assert!(ttl > Some(0), "TTL must be at least 1 second.");
+        }
+        self.ttl = ttl.map(Duration::from_secs);
+        self
+    }
+
+    /// Sets template in which Block's result is displayed in status bar.
+    /// Template can contain only [`{output}`](OUTPUT_PLACEHOLDER) placeholder,
+    /// which is replaced by Block's result.
+    ///
+    /// # Example
+    /// ```
+    /// use asyncdwmblocks::block::{Block, OUTPUT_PLACEHOLDER};
+    /// use asyncdwmblocks::config::Config;
+    /// use asyncdwmblocks::utils::Template;
+    ///
+    /// let config = Config::default().arc();
+    /// let volume = Template::parse("vol: {output}%", &[OUTPUT_PLACEHOLDER]).unwrap();
+    /// let block = Block::new("my_volume_script".to_string(), vec![], None, config)
+    ///     .with_format(Some(volume))
+    ///     .with_prefix(Some(String::from("[")))
+    ///     .with_suffix(Some(String::from("]")));
+    /// ```
+    pub fn with_format(mut self, format: Option<Template>) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets text displayed before Block's (formatted) result.
+    pub fn with_prefix(mut self, prefix: Option<String>) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// Sets text displayed after Block's (formatted) result.
+    pub fn with_suffix(mut self, suffix: Option<String>) -> Self {
+        self.suffix = suffix;
+        self
+    }
+
+    /// Sets Block's color (for example `#ff0000`). It is used only by status bar
+    /// [protocols](crate::statusbar::Protocol) that support colors.
+    pub fn with_color(mut self, color: Option<String>) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets [rules](HideRules) deciding when Block is hidden from status bar.
+    pub fn with_hide_rules(mut self, hide_rules: HideRules) -> Self {
+        self.hidden = match &self.result {
+            Some(result) => hide_rules.hides(result, None),
+            None => false,
+        };
+        self.hide_rules = hide_rules;
+        self
+    }
+
+    /// Sets Block's [BusyPolicy].
+    ///
+    /// If `None` then global [busy_policy](crate::config::ConfigBlock::busy_policy)
+    /// from config is used.
+    pub fn with_busy_policy(mut self, busy_policy: Option<BusyPolicy>) -> Self {
+        self.busy_policy = busy_policy;
+        self
+    }
+
+    /// Sets Block's [Retry] settings. If `None` failed runs are not retried.
+    ///
+    /// # Panics
+    /// If `retry` is `Some`, then it must be [valid](Retry::validate).
+    pub fn with_retry(mut self, retry: Option<Retry>) -> Self {
+        if let Some(Err(err)) = retry.as_ref().map(Retry::validate) {
+            panic!("Invalid retry: {}.", err);
+        }
+        self.retry = retry;
+        self
+    }
+
+    /// Sets Block's [ErrorPolicy].
+    ///
+    /// If `None` then global [error_policy](crate::config::ConfigBlock::error_policy)
+    /// from config is used.
+    pub fn with_error_policy(mut self, error_policy: Option<ErrorPolicy>) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    /// Sets text displayed when Block's command fails and it's
+    /// policy is [ErrorPolicy::Marker].
+    ///
+    /// If `None` then global [error_marker](crate::config::ConfigBlock::error_marker)
+    /// from config is used.
+    pub fn with_error_marker(mut self, error_marker: Option<String>) -> Self {
+        self.error_marker = error_marker;
+        self
+    }
+
     /// Executes Block's command by running tokio's **`spawn_blocking`**.
     ///
     /// This method runs Block's command (with it's args) and returns `Ok(())`
@@ -232,6 +894,16 @@ impl Block {
     /// newline character and then sets it as a inner result. It also filters out `\u{0}`
     /// characters.
     ///
+    /// Command is run once, even if Block is [persistent](BlockMode::Persistent).
+    /// [Push](BlockMode::Push) Blocks have no command, so nothing happens.
+    /// Builtin Blocks produce their output with their [BlockSource] instead.
+    ///
+    /// If command runs longer than Block's timeout, then it (and all processes
+    /// in it's process group) is killed and [BlockRunError::Timeout] is returned.
+    /// In that case inner result is either left unchanged or set to
+    /// [timeout_placeholder](crate::config::ConfigBlock::timeout_placeholder)
+    /// if it was configured.
+    ///
     /// # Example
     /// ```no_run
     /// use asyncdwmblocks::block::{Block, BlockRunMode};
@@ -248,43 +920,224 @@ impl Block {
     ///
     /// ```
     pub async fn run(&mut self, mode: BlockRunMode) -> Result<(), BlockRunError> {
-        let (sender, receiver) = oneshot::channel();
+        if self.mode == BlockMode::Push {
+            return Ok(());
+        }
+        let output = self.computation(mode).await;
+        self.update(output)
+    }
+
+    /// Returns a future that executes Block's command and computes it's output,
+    /// without borrowing the Block.
+    ///
+    /// This allows to run Block's command in a separate task (while Block itself
+    /// stays where it was) and then pass it's output to [update](Block::update).
+    /// [run](Block::run) is equivalent of calling those two methods one after another.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use asyncdwmblocks::block::{Block, BlockRunMode};
+    /// use asyncdwmblocks::config::Config;
+    ///
+    /// # async fn doc() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = Config::default().arc();
+    /// let mut block = Block::new("echo".to_string(), vec!["Hello".to_string()], None, config);
+    ///
+    /// let handle = tokio::spawn(block.computation(BlockRunMode::Normal));
+    /// block.update(handle.await?)?;
+    ///
+    /// assert_eq!(block.result(), Some(&String::from("Hello")));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn computation(
+        &self,
+        mode: BlockRunMode,
+    ) -> impl Future<Output = Result<BlockOutput, BlockRunError>> + Send + 'static {
+        let source = self.source.clone();
+        let mut command = CommandSource::new(
+            self.command.clone(),
+            self.args.clone(),
+            self.timeout(),
+            Arc::clone(&self.config),
+        );
+        let timeout = self.timeout();
+
+        async move {
+            match source {
+                Source::Command => command.run(mode).await,
+                Source::Builtin(source) => {
+                    let mut source = source.lock().await;
+                    let output = match timeout {
+                        Some(timeout) => time::timeout(timeout, source.produce(mode))
+                            .await
+                            .unwrap_or(Err(BlockRunError::Timeout(timeout))),
+                        None => source.produce(mode).await,
+                    };
+                    output.map(BlockOutput::from)
+                }
+            }
+        }
+    }
+
+    /// Updates Block's result with output of a [computation](Block::computation).
+    ///
+    /// On success output becomes Block's new result. On failure error is
+    /// returned back and result is left unchanged (unless it was a timeout
+    /// and [timeout_placeholder](crate::config::ConfigBlock::timeout_placeholder)
+    /// was configured). Block's [hide rules](HideRules) are evaluated
+    /// every time result is updated.
+    ///
+    /// If command exited with non-zero code, then Block's [ErrorPolicy]
+    /// decides what becomes it's new result.
+    pub fn update(
+        &mut self,
+        output: Result<BlockOutput, BlockRunError>,
+    ) -> Result<(), BlockRunError> {
+        if let Err(e) = &output {
+            self.last_error = Some(e.to_string());
+        }
+
+        match output {
+            Ok(BlockOutput {
+                text,
+                exit_code,
+                stderr,
+            }) => {
+                self.last_exit_status = exit_code;
+                let result = match exit_code {
+                    Some(code) if code != 0 => {
+                        let text = match self.error_policy() {
+                            ErrorPolicy::Show => Some(text),
+                            ErrorPolicy::Keep => None,
+                            ErrorPolicy::Marker => Some(self.error_marker().to_string()),
+                            ErrorPolicy::Stderr => Some(stderr.clone()),
+                        };
+                        self.last_error = if stderr.is_empty() {
+                            Some(format!("Command exited with code {}", code))
+                        } else {
+                            Some(stderr)
+                        };
+                        text
+                    }
+                    _ => {
+                        self.last_error = None;
+                        self.retry_attempts = 0;
+                        Some(text)
+                    }
+                };
 
+                if let Some(result) = result {
+                    self.result = Some(result);
+                }
+                if let Some(result) = &self.result {
+                    self.hidden = self.hide_rules.hides(result, exit_code);
+                }
+                Ok(())
+            }
+            Err(BlockRunError::Timeout(timeout)) => {
+                if let Some(placeholder) = &self.config.block.timeout_placeholder {
+                    self.hidden = self.hide_rules.hides(placeholder, None);
+                    self.result = Some(placeholder.clone());
+                }
+                Err(BlockRunError::Timeout(timeout))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns a future that runs Block's command as a long running process
+    /// (see [BlockMode::Persistent]) and sends every line it prints through **sender**.
+    ///
+    /// When command exits (or fails to start) it is restarted after a delay, which
+    /// doubles after each consecutive restart that didn't produce any output (up to a minute).
+    /// `mode` is used only for the first run of the command. This future runs until
+    /// it is dropped (which kills the running command along with it's whole process
+    /// group) or **sender** is closed.
+    /// Builtin Blocks produce (and send) their output only once.
+    pub fn persistent_computation(
+        &self,
+        mode: BlockRunMode,
+        sender: mpsc::Sender<Result<BlockOutput, BlockRunError>>,
+    ) -> impl Future<Output = ()> + Send + 'static {
         let command = self.command.clone();
         let args = self.args.clone();
-
         let config = Arc::clone(&self.config);
-        task::spawn_blocking(|| async move {
-            let mut command = Command::new(command);
-            let command = command.args(args);
-            let command = match mode.button() {
-                Some(b) => command.env(&config.block.clicked_env_variable, b.to_string()),
-                None => command,
-            };
+        // Builtin blocks don't have a long running process.
+        let builtin = match self.source {
+            Source::Builtin(_) => Some(self.computation(mode.clone())),
+            Source::Command => None,
+        };
 
-            // ignore sending error
-            let _ = sender.send(command.output().await.map(|o| o.stdout));
-        })
-        .await?
-        .await;
+        async move {
+            if let Some(computation) = builtin {
+                let _ = sender.send(computation.await).await;
+                return;
+            }
 
-        let output: Vec<u8> = receiver.await??;
+            let mut mode = mode;
+            let mut delay = PERSISTENT_RESTART_DELAY_MIN;
+            loop {
+                let mut command = Command::new(&command);
+                let command = command
+                    .args(&args)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .kill_on_drop(true);
+                let command = match mode.button() {
+                    Some(b) => command.env(&config.block.clicked_env_variable, b.to_string()),
+                    None => command,
+                };
+                set_process_group(command);
+                mode = BlockRunMode::Normal;
 
-        self.result = Some(
-            String::from_utf8_lossy(&output)
-                .chars()
-                .filter(|c| c != &'\u{0}')
-                .take_while(|c| c != &'\n')
-                .collect(),
-        );
-        Ok(())
+                let mut received_output = false;
+                match command.spawn() {
+                    Ok(mut child) => {
+                        // Kills command along with it's children (like `pactl subscribe`
+                        // in a pipeline) when this future is dropped.
+                        let mut group = ProcessGroupGuard(child.id());
+                        // stdout was set to piped, so it must be present.
+                        let stdout = child.stdout.take().unwrap();
+                        let mut lines = BufReader::new(stdout).lines();
+                        while let Ok(Some(line)) = lines.next_line().await {
+                            received_output = true;
+                            let line: String = line.chars().filter(|c| c != &'\u{0}').collect();
+                            if sender.send(Ok(line.into())).await.is_err() {
+                                return;
+                            }
+                        }
+                        let _ = child.wait().await;
+                        group.disarm();
+                    }
+                    Err(e) => {
+                        if sender.send(Err(BlockRunError::from(e))).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+
+                if received_output {
+                    delay = PERSISTENT_RESTART_DELAY_MIN;
+                }
+                time::sleep(delay).await;
+                delay = std::cmp::min(delay * 2, PERSISTENT_RESTART_DELAY_MAX);
+            }
+        }
     }
 
     /// Creates properly configured [Interval] that ticks at Block's rate.
     ///
     /// If upon creation `interval` was set to `None` (meaning no refreshment)
+    /// or block is [persistent](BlockMode::Persistent) or [push](BlockMode::Push)
     /// this method will return `None` as well.
     ///
+    /// Scheduler of an [aligned](BlockSource::alignment) Block first ticks at the next
+    /// multiple of it's interval on the wall clock (in it's [Alignment]'s timezone),
+    /// so that for example Block with interval of 60 seconds is refreshed when minute
+    /// changes and Block with interval of a day is refreshed at midnight.
+    ///
     /// # Example
     /// ```no_run
     /// use std::sync::Arc;
@@ -300,7 +1153,13 @@ impl Block {
     /// assert!(message.get_scheduler().is_none());
     /// ```
     pub fn get_scheduler(&self) -> Option<Interval> {
+        if self.mode != BlockMode::Oneshot {
+            return None;
+        }
         let interval = self.interval?;
+        if let Some(alignment) = self.alignment {
+            return Some(aligned_scheduler(interval, alignment));
+        }
         let mut scheduler = interval_at(Instant::now() + interval, interval);
         scheduler.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
@@ -312,19 +1171,332 @@ impl Block {
     pub fn result(&self) -> Option<&String> {
         self.result.as_ref()
     }
-}
 
-#[cfg(test)]
-impl Block {
+    /// Returns Block's result as it is displayed in status bar: rendered with
+    /// Block's [format](Block::with_format) and surrounded by it's prefix and suffix.
+    /// `None` means that no computation has ever been completed,
+    /// or that Block is [hidden](Block::is_hidden).
+    pub fn output(&self) -> Option<Cow<'_, str>> {
+        if self.hidden {
+            return None;
+        }
+        let result = self.result.as_ref()?;
+        if self.format.is_none() && self.prefix.is_none() && self.suffix.is_none() {
+            return Some(Cow::Borrowed(result));
+        }
+
+        let mut output = String::new();
+        output.extend(self.prefix.as_deref());
+        match &self.format {
+            Some(format) => output.push_str(&format.render(|_| result.clone())),
+            None => output.push_str(result),
+        }
+        output.extend(self.suffix.as_deref());
+
+        Some(Cow::Owned(output))
+    }
+
+    /// Returns Block's color.
+    pub fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    /// Checks if Block is hidden from status bar by it's [HideRules].
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Returns Block's command.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Returns arguments of Block's command.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Returns Block's refresh interval.
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    /// Changes Block's command and it's arguments. Result is left unchanged.
+    ///
+    /// Builtin Block becomes a Block running this command.
+    pub fn set_command(&mut self, command: String, args: Vec<String>) {
+        self.command = command;
+        self.args = args;
+        self.source = Source::Command;
+        self.alignment = None;
+    }
+
+    /// Checks if this Block is builtin (it's output is produced
+    /// by a [BlockSource] other than [CommandSource]).
+    pub fn is_builtin(&self) -> bool {
+        matches!(self.source, Source::Builtin(_))
+    }
+
+    /// Checks if Block's interval is [aligned](BlockSource::alignment) to the wall clock.
+    pub fn is_aligned(&self) -> bool {
+        self.alignment.is_some()
+    }
+
+    /// Returns wall clock to which Block's interval is [aligned](BlockSource::alignment).
+    pub fn alignment(&self) -> Option<Alignment> {
+        self.alignment
+    }
+
+    /// Changes Block's refresh interval (in seconds).
+    ///
+    /// # Panics
+    /// If `interval` is `Some`, then it must be greater than 0. Interval with value
+    /// `Some(0)` will panic.
+    pub fn set_interval(&mut self, interval: Option<u64>) {
+        if interval.is_some() {
+            assert!(interval > Some(0), "Interval must be at least 1 second.");
+        }
+        self.interval = interval.map(Duration::from_secs);
+    }
+
+    /// Returns effective timeout of this Block.
+    ///
+    /// It is either Block's own timeout, or (if not set) the global one.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+            .or_else(|| self.config.block.timeout.map(Duration::from_secs))
+    }
+
+    /// Returns effective [ErrorPolicy] of this Block.
+    ///
+    /// It is either Block's own policy, or (if not set) the global one.
+    pub fn error_policy(&self) -> ErrorPolicy {
+        self.error_policy.unwrap_or(self.config.block.error_policy)
+    }
+
+    /// Returns effective error marker of this Block.
+    ///
+    /// It is either Block's own marker, or (if not set) the global one.
+    pub fn error_marker(&self) -> &str {
+        self.error_marker
+            .as_deref()
+            .unwrap_or(&self.config.block.error_marker)
+    }
+
+    /// Returns exit code of Block's command from it's last run.
+    /// `None` means that command wasn't run yet, was terminated
+    /// by a signal, or that Block has no command.
+    pub fn last_exit_status(&self) -> Option<i32> {
+        self.last_exit_status
+    }
+
+    /// Returns description of an error that happened during Block's last run:
+    /// first line of command's stderr (if it exited with non-zero code) or
+    /// [BlockRunError]. `None` means that last run succeeded.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// Returns Block's [Retry] settings.
+    pub fn retry(&self) -> Option<&Retry> {
+        self.retry.as_ref()
+    }
+
+    /// Returns number of retries made since Block's last successful run.
+    pub fn retry_attempts(&self) -> u32 {
+        self.retry_attempts
+    }
+
+    /// Returns delay (with random jitter) after which Block's last failed run
+    /// should be retried. `None` means that last run succeeded, Block has no
+    /// [Retry] settings, or that all retry attempts were used.
+    pub fn retry_delay(&self) -> Option<Duration> {
+        let retry = self.retry.as_ref()?;
+        if self.mode != BlockMode::Oneshot
+            || self.last_error.is_none()
+            || self.retry_attempts >= retry.max_attempts
+        {
+            return None;
+        }
+
+        Some(retry.jittered_delay(self.retry_attempts, utils::random()))
+    }
+
+    /// Records that Block's failed run is going to be retried.
+    pub(crate) fn start_retry(&mut self) {
+        self.retry_attempts += 1;
+    }
+
+    /// Returns Block's [BlockMode].
+    pub fn mode(&self) -> BlockMode {
+        self.mode
+    }
+
+    /// Returns Block's real-time signal offset.
+    pub fn signal(&self) -> Option<u8> {
+        self.signal
+    }
+
+    /// Returns time after which text set directly to this Block expires.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// Returns effective [BusyPolicy] of this Block.
+    ///
+    /// It is either Block's own policy, or (if not set) the global one.
+    pub fn busy_policy(&self) -> BusyPolicy {
+        self.busy_policy.unwrap_or(self.config.block.busy_policy)
+    }
+
+    /// Checks if `other` Block is defined the same way as this one
+    /// (ignoring their results). Such Blocks behave identically.
+    ///
+    /// Builtin Blocks are compared by their command and arguments
+    /// (and not by the state of their sources).
+    pub fn same_definition(&self, other: &Block) -> bool {
+        self.is_builtin() == other.is_builtin()
+            && self.command == other.command
+            && self.args == other.args
+            && self.interval == other.interval
+            && self.timeout == other.timeout
+            && self.busy_policy == other.busy_policy
+            && self.retry == other.retry
+            && self.error_policy == other.error_policy
+            && self.error_marker == other.error_marker
+            && self.mode == other.mode
+            && self.signal == other.signal
+            && self.ttl == other.ttl
+            && self.format == other.format
+            && self.prefix == other.prefix
+            && self.suffix == other.suffix
+            && self.color == other.color
+            && self.hide_rules == other.hide_rules
+            && self.config.block == other.config.block
+    }
+
     pub(crate) fn set_result(&mut self, result: Option<String>) {
+        self.hidden = match &result {
+            Some(result) => self.hide_rules.hides(result, None),
+            None => false,
+        };
         self.result = result;
     }
 
+    /// Takes result (and source of a builtin Block) from an **old** Block with
+    /// the [same definition](Block::same_definition), so that it's state is preserved.
+    pub(crate) fn keep_state_of(&mut self, old: &Block) {
+        self.result = old.result.clone();
+        self.hidden = old.hidden;
+        self.last_exit_status = old.last_exit_status;
+        self.last_error = old.last_error.clone();
+        self.retry_attempts = old.retry_attempts;
+        if old.is_builtin() {
+            self.source = old.source.clone();
+        }
+    }
+}
+
+/// Creates [Interval] with given `period`, whose first tick happens at the next
+/// multiple of `period` on the wall clock of **alignment** (counted from midnight
+/// of 1970-01-01 in it's timezone).
+///
+/// Since [Interval] measures time with monotonic clock (which may drift from the
+/// wall clock, for example when system is suspended), and timezone's offset may
+/// change, it should be recreated after every tick to stay aligned.
+pub(crate) fn aligned_scheduler(period: Duration, alignment: Alignment) -> Interval {
+    let now = Utc::now();
+    let delay = until_aligned(period, now, alignment.offset(now));
+    let mut scheduler = interval_at(Instant::now() + delay, period);
+    scheduler.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    scheduler
+}
+
+/// Returns time left from **now** until the next wall clock multiple of `period`,
+/// on a clock that is **offset** seconds ahead of UTC.
+fn until_aligned(period: Duration, now: DateTime<Utc>, offset: i32) -> Duration {
+    let now =
+        now.timestamp_nanos_opt().unwrap_or_default() as i128 + offset as i128 * 1_000_000_000;
+    let period = period.as_nanos() as i128;
+
+    Duration::from_nanos((period - now.rem_euclid(period)) as u64)
+}
+
+/// Puts **command** in it's own process group, so that it can
+/// be killed along with it's children (see [ProcessGroupGuard]).
+fn set_process_group(command: &mut Command) {
+    // SAFETY: setpgid is async-signal-safe.
+    unsafe {
+        command.pre_exec(|| {
+            libc::setpgid(0, 0);
+            Ok(())
+        });
+    }
+}
+
+/// Kills process group (of a command started with [set_process_group])
+/// when dropped, unless it was [disarmed](ProcessGroupGuard::disarm).
+struct ProcessGroupGuard(Option<u32>);
+
+impl ProcessGroupGuard {
+    /// Leaves process group running (for example after command has exited).
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        if let Some(pgid) = self.0 {
+            kill_process_group(pgid);
+        }
+    }
+}
+
+/// Sends `SIGKILL` to every process in process group `pgid`.
+fn kill_process_group(pgid: u32) {
+    // SAFETY: kill is safe to call with any arguments. Negative pid
+    // means that signal is sent to every process in the group.
+    unsafe {
+        libc::kill(-(pgid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(test)]
+impl Block {
     pub(crate) fn get_interval(&self) -> Option<Duration> {
         self.interval
     }
 }
 
+/// Returns whether process **pid** is running (exists and isn't a zombie).
+#[cfg(test)]
+pub(crate) fn process_running(pid: &str) -> bool {
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        // State follows command name (in parentheses).
+        Ok(stat) => !stat
+            .rsplit(')')
+            .next()
+            .unwrap()
+            .trim_start()
+            .starts_with('Z'),
+        Err(_) => false,
+    }
+}
+
+/// Waits (up to a second) until process **pid** is not running.
+#[cfg(test)]
+pub(crate) async fn process_exited(pid: &str) -> bool {
+    for _ in 0..100 {
+        if !process_running(pid) {
+            return true;
+        }
+        time::sleep(Duration::from_millis(10)).await;
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -334,7 +1506,7 @@ mod tests {
     async fn block_run_error_types() {
         use BlockRunError::*;
 
-        let command_error = CommandError(std::io::Error::new(std::io::ErrorKind::Other, "testing"));
+        let command_error = CommandError(std::io::Error::other("testing"));
         let channel_closed = ChannelClosed;
         // This is the only way I know to create a JoinError
         let join_error = tokio::spawn(async { panic!() }).await.unwrap_err();
@@ -406,6 +1578,162 @@ mod tests {
         assert!(timeout.is_ok());
     }
 
+    #[tokio::test]
+    async fn run_timeout() {
+        let config = Config::default().arc();
+        let mut block =
+            Block::new("sleep".into(), vec!["5".into()], None, config).with_timeout(Some(1));
+
+        let run = timeout_at(
+            Instant::now() + Duration::from_secs(1) + Duration::from_millis(100),
+            block.run(BlockRunMode::Normal),
+        )
+        .await
+        .expect("Command wasn't killed on time.");
+        assert!(matches!(run, Err(BlockRunError::Timeout(_))));
+        assert!(run.unwrap_err().is_io());
+        assert_eq!(block.result, None);
+    }
+
+    #[tokio::test]
+    async fn run_timeout_global_and_placeholder() {
+        let config = Config {
+            block: crate::config::ConfigBlock {
+                timeout: Some(1),
+                timeout_placeholder: Some(String::from("timeout")),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+        let mut block = Block::new("sleep".into(), vec!["5".into()], None, config);
+        assert_eq!(block.timeout(), Some(Duration::from_secs(1)));
+
+        let run = block.run(BlockRunMode::Normal).await;
+        assert!(matches!(run, Err(BlockRunError::Timeout(_))));
+        assert_eq!(block.result, Some(String::from("timeout")));
+    }
+
+    #[tokio::test]
+    async fn run_timeout_kills_process_group() {
+        let config = Config::default().arc();
+        // Background child keeps stdout open, so without killing
+        // whole process group this would wait for it.
+        let mut block = Block::new(
+            "sh".into(),
+            vec!["-c".into(), "sleep 5 & sleep 5".into()],
+            None,
+            config,
+        )
+        .with_timeout(Some(1));
+
+        let run = timeout_at(
+            Instant::now() + Duration::from_secs(1) + Duration::from_millis(100),
+            block.run(BlockRunMode::Normal),
+        )
+        .await
+        .expect("Command wasn't killed on time.");
+        assert!(matches!(run, Err(BlockRunError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn run_abort_kills_process_group() {
+        let config = Config::default().arc();
+        let pid_file =
+            std::env::temp_dir().join(format!("asyncdwmblocks-abort-{}", std::process::id()));
+        let _ = std::fs::remove_file(&pid_file);
+        let script = format!("sleep 5 & echo $! > {}; wait", pid_file.display());
+        let mut block = Block::new("sh".into(), vec!["-c".into(), script], None, config);
+
+        // Abort run like BusyPolicy::Restart does.
+        let run = tokio::spawn(async move { block.run(BlockRunMode::Normal).await });
+        let mut pid = String::new();
+        for _ in 0..100 {
+            pid = std::fs::read_to_string(&pid_file).unwrap_or_default();
+            if pid.ends_with('\n') {
+                break;
+            }
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        let pid = pid.trim().to_string();
+        assert!(process_running(&pid));
+        run.abort();
+        assert!(run.await.unwrap_err().is_cancelled());
+
+        assert!(
+            process_exited(&pid).await,
+            "Child of aborted command is running."
+        );
+        let _ = std::fs::remove_file(&pid_file);
+    }
+
+    #[derive(Debug)]
+    struct Clicks(u32);
+
+    #[async_trait]
+    impl BlockSource for Clicks {
+        async fn produce(&mut self, mode: BlockRunMode) -> Result<String, BlockRunError> {
+            match mode {
+                BlockRunMode::Normal => {}
+                BlockRunMode::Button(_) => self.0 += 1,
+            }
+            Ok(format!("clicks: {}", self.0))
+        }
+
+        fn describe(&self) -> String {
+            String::from("clicks")
+        }
+    }
+
+    #[derive(Debug)]
+    struct Sleep;
+
+    #[async_trait]
+    impl BlockSource for Sleep {
+        async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+            time::sleep(Duration::from_secs(5)).await;
+            Ok(String::from("awake"))
+        }
+    }
+
+    #[tokio::test]
+    async fn run_builtin_keeps_state() {
+        let config = Config::default().arc();
+        let mut block = Block::from_source(Box::new(Clicks(0)), None, config);
+        assert!(block.is_builtin());
+        assert_eq!(block.to_string(), "clicks");
+
+        block.run(BlockRunMode::Button(1)).await.unwrap();
+        block.run(BlockRunMode::Button(3)).await.unwrap();
+        // Clones share the same source.
+        let mut clone = block.clone();
+        clone.run(BlockRunMode::Normal).await.unwrap();
+        assert_eq!(clone.result(), Some(&String::from("clicks: 2")));
+        assert_eq!(block, clone.clone());
+
+        // Changing command turns block into an ordinary one.
+        clone.set_command(String::from("echo"), vec![String::from("hi")]);
+        assert!(!clone.is_builtin());
+        clone.run(BlockRunMode::Normal).await.unwrap();
+        assert_eq!(clone.result(), Some(&String::from("hi")));
+    }
+
+    #[tokio::test]
+    async fn run_builtin_timeout() {
+        let config = Config::default().arc();
+        let mut block = Block::from_source(Box::new(Sleep), None, config).with_timeout(Some(1));
+        assert_eq!(block.to_string(), "builtin");
+
+        let run = timeout_at(
+            Instant::now() + Duration::from_secs(1) + Duration::from_millis(100),
+            block.run(BlockRunMode::Normal),
+        )
+        .await
+        .expect("Builtin wasn't stopped on time.");
+        assert!(matches!(run, Err(BlockRunError::Timeout(_))));
+        assert_eq!(block.result, None);
+    }
+
     #[tokio::test]
     async fn block_get_scheduler() {
         let config = Config::default().arc();
@@ -421,4 +1749,254 @@ mod tests {
 
         assert!(timeout.is_ok());
     }
+
+    #[derive(Debug)]
+    struct Aligned;
+
+    #[async_trait]
+    impl BlockSource for Aligned {
+        async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+            Ok(String::new())
+        }
+
+        fn alignment(&self) -> Option<Alignment> {
+            Some(Alignment::Timezone(chrono_tz::Asia::Kolkata))
+        }
+    }
+
+    #[tokio::test]
+    async fn block_get_aligned_scheduler() {
+        let config = Config::default().arc();
+        let mut block = Block::from_source(Box::new(Aligned), Some(60), config);
+        assert!(block.is_aligned());
+        assert_eq!(
+            block.alignment(),
+            Some(Alignment::Timezone(chrono_tz::Asia::Kolkata))
+        );
+        let scheduler = block.get_scheduler().unwrap();
+        assert_eq!(scheduler.period(), Duration::from_secs(60));
+
+        block.set_command("date".into(), vec![]);
+        assert!(!block.is_aligned());
+    }
+
+    #[test]
+    fn block_until_aligned() {
+        let time = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        let minute = Duration::from_secs(60);
+        let hour = Duration::from_secs(3600);
+        let day = Duration::from_secs(86400);
+        // Asia/Kolkata is 5 and a half hours ahead of UTC.
+        let kolkata = Alignment::Timezone(chrono_tz::Asia::Kolkata);
+        let now = time("2022-03-01T00:10:30.250Z");
+        let offset = kolkata.offset(now);
+        assert_eq!(offset, 19800);
+
+        assert_eq!(until_aligned(minute, now, 0), Duration::from_millis(29750));
+        assert_eq!(until_aligned(hour, now, 0), Duration::from_millis(2969750));
+        assert_eq!(
+            until_aligned(minute, now, offset),
+            Duration::from_millis(29750)
+        );
+        // It is 05:40:30 in Kolkata, so the hour changes in 19 and a half minutes...
+        assert_eq!(
+            until_aligned(hour, now, offset),
+            Duration::from_millis(1169750)
+        );
+        // ... and the date changes at 18:30 UTC.
+        assert_eq!(
+            until_aligned(day, now, offset),
+            (time("2022-03-01T18:30:00Z") - now).to_std().unwrap()
+        );
+        // Timezones behind UTC.
+        assert_eq!(
+            until_aligned(day, now, -3600),
+            (time("2022-03-01T01:00:00Z") - now).to_std().unwrap()
+        );
+        // At the exact multiple of period, the next one is awaited.
+        assert_eq!(
+            until_aligned(hour, time("2022-03-01T00:30:00Z"), offset),
+            hour
+        );
+    }
+
+    #[tokio::test]
+    async fn block_output() {
+        let config = Config::default().arc();
+        let mut block = Block::new("echo".into(), vec!["50".into()], None, config);
+        assert_eq!(block.output(), None);
+
+        block.run(BlockRunMode::Normal).await.unwrap();
+        assert_eq!(block.output().unwrap(), "50");
+
+        let format = Template::parse("{{vol}} {output}%", &[OUTPUT_PLACEHOLDER]).unwrap();
+        let mut block = block
+            .with_format(Some(format))
+            .with_prefix(Some(String::from("<")));
+        assert_eq!(block.output().unwrap(), "<{vol} 50%");
+        assert_eq!(block.result(), Some(&String::from("50")));
+
+        block = block.with_format(None).with_suffix(Some(String::from(">")));
+        assert_eq!(block.output().unwrap(), "<50>");
+    }
+
+    #[tokio::test]
+    async fn block_hide_rules() {
+        let config = Config::default().arc();
+        let script = |code: &str| vec!["-c".into(), format!("echo vpn: off; exit {}", code)];
+        let mut block = Block::new("sh".into(), script("3"), None, Arc::clone(&config))
+            .with_hide_rules(HideRules {
+                when_exit_code: Some(3),
+                ..Default::default()
+            });
+
+        block.run(BlockRunMode::Normal).await.unwrap();
+        assert!(block.is_hidden());
+        assert_eq!(block.output(), None);
+        assert_eq!(block.result(), Some(&String::from("vpn: off")));
+
+        block.set_command("sh".into(), script("0"));
+        block.run(BlockRunMode::Normal).await.unwrap();
+        assert!(!block.is_hidden());
+
+        let mut block = block.with_hide_rules(HideRules {
+            when_matches: Some(Regex::new("off$").unwrap()),
+            ..Default::default()
+        });
+        assert!(block.is_hidden());
+        block.set_result(Some(String::new()));
+        assert!(!block.is_hidden());
+
+        let block = block.with_hide_rules(HideRules {
+            when_empty: true,
+            ..Default::default()
+        });
+        assert!(block.is_hidden());
+    }
+
+    #[tokio::test]
+    async fn block_error_policy() {
+        let config = Config::default().arc();
+        let script = |code: &str| {
+            let script = format!("echo ok; echo no data >&2; echo more >&2; exit {}", code);
+            vec!["-c".into(), script]
+        };
+        let mut block = Block::new("sh".into(), script("0"), None, config);
+        block.run(BlockRunMode::Normal).await.unwrap();
+        assert_eq!(block.last_exit_status(), Some(0));
+        assert_eq!(block.last_error(), None);
+
+        let policies = [
+            (ErrorPolicy::Show, "ok"),
+            (ErrorPolicy::Keep, "previous"),
+            (ErrorPolicy::Marker, "⚠ battery"),
+            (ErrorPolicy::Stderr, "no data"),
+        ];
+        for (policy, result) in policies {
+            block.set_result(Some(String::from("previous")));
+            block = block
+                .with_error_policy(Some(policy))
+                .with_error_marker(Some(String::from("⚠ battery")));
+            block.set_command("sh".into(), script("2"));
+            block.run(BlockRunMode::Normal).await.unwrap();
+
+            assert_eq!(block.result(), Some(&String::from(result)));
+            assert_eq!(block.last_exit_status(), Some(2));
+            assert_eq!(block.last_error(), Some("no data"));
+        }
+
+        block.set_command("sh".into(), vec!["-c".into(), "exit 1".into()]);
+        block.run(BlockRunMode::Normal).await.unwrap();
+        assert_eq!(block.last_error(), Some("Command exited with code 1"));
+
+        block.set_command("xfewxj1287rxn31xm31rx798321x".into(), vec![]);
+        assert!(block.run(BlockRunMode::Normal).await.is_err());
+        assert!(block.last_error().is_some());
+    }
+
+    #[tokio::test]
+    async fn block_retry() {
+        let config = Config::default().arc();
+        let retry = Retry {
+            max_attempts: 2,
+            initial_delay: 4,
+            backoff: 1.5,
+            jitter: 0.25,
+        };
+        assert_eq!(retry.delay(1), Duration::from_secs(6));
+        let failing = vec!["-c".into(), "exit 1".into()];
+        let mut block = Block::new("sh".into(), failing.clone(), None, config);
+        block.run(BlockRunMode::Normal).await.unwrap();
+        // Block without retry settings is not retried.
+        assert_eq!(block.retry_delay(), None);
+
+        block = block.with_retry(Some(retry));
+        for attempt in 0..2 {
+            let delay = block.retry_delay().unwrap();
+            let expected = retry.delay(attempt);
+            assert!(delay >= expected.mul_f64(0.75) && delay <= expected.mul_f64(1.25));
+            block.start_retry();
+            block.run(BlockRunMode::Normal).await.unwrap();
+        }
+        // All attempts were used.
+        assert_eq!(block.retry_attempts(), 2);
+        assert_eq!(block.retry_delay(), None);
+
+        // Successful run resets attempts.
+        block.set_command("true".into(), vec![]);
+        block.run(BlockRunMode::Normal).await.unwrap();
+        assert_eq!(block.retry_attempts(), 0);
+        assert_eq!(block.retry_delay(), None);
+        block.set_command("sh".into(), failing);
+        block.run(BlockRunMode::Normal).await.unwrap();
+        assert!(block.retry_delay().is_some());
+    }
+
+    #[test]
+    fn block_retry_jitter() {
+        let retry = Retry {
+            initial_delay: 10,
+            jitter: 0.2,
+            ..Default::default()
+        };
+        assert_eq!(retry.jittered_delay(0, 0.0), Duration::from_secs(8));
+        assert_eq!(retry.jittered_delay(0, 0.5), Duration::from_secs(10));
+        assert_eq!(retry.jittered_delay(1, 0.75), Duration::from_secs(22));
+
+        // Delays are spread evenly over the whole range.
+        let mut generator = crate::utils::SplitMix64::new(7);
+        let delays: Vec<Duration> = (0..1000)
+            .map(|_| retry.jittered_delay(0, generator.next_f64()))
+            .collect();
+        let shorter = delays
+            .iter()
+            .filter(|d| **d < Duration::from_secs(10))
+            .count();
+        assert!(delays
+            .iter()
+            .all(|d| *d >= Duration::from_secs(8) && *d < Duration::from_secs(12)));
+        assert!((450..550).contains(&shorter));
+    }
+
+    #[test]
+    fn block_retry_validate() {
+        let retry = |backoff: f64, jitter: f64| Retry {
+            backoff,
+            jitter,
+            ..Default::default()
+        };
+        assert!(retry(1.0, 0.0).validate().is_ok());
+        assert!(retry(3.5, 1.0).validate().is_ok());
+
+        for invalid in [
+            retry(0.5, 0.1),
+            retry(f64::NAN, 0.1),
+            retry(f64::INFINITY, 0.1),
+            retry(2.0, -0.1),
+            retry(2.0, 1.5),
+            retry(2.0, f64::NAN),
+        ] {
+            assert!(invalid.validate().is_err(), "{:?}", invalid);
+        }
+    }
 }
@@ -0,0 +1,89 @@
+//! This module defines `backlight` builtin, that displays screen's brightness
+//! read from `/sys/class/backlight/<device>`.
+//!
+//! Device is chosen with `device` argument (for example `device=intel_backlight`).
+//! If it is not set, then the first device is used.
+//!
+//! Placeholders:
+//!  - `{percent}`: brightness (in percent of maximal brightness)
+//!
+//! # Example
+//! ```yaml
+//! - name: backlight
+//!   type: builtin
+//!   command: backlight
+//!   args: ["format=☀ {percent}%"]
+//!   signal: 2
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+
+use super::{invalid_data, parse_value, read_dir, read_file, Options};
+use crate::block::{BlockRunError, BlockRunMode, BlockSource};
+use crate::config::ConfigStatusBarBlock;
+use crate::utils::Template;
+
+/// Format used when `format` argument is not set.
+pub const DEFAULT_FORMAT: &str = "{percent}%";
+
+const BACKLIGHT: &str = "sys/class/backlight";
+
+/// [BlockSource] of a `backlight` builtin.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Backlight {
+    template: Template,
+    root: PathBuf,
+    device: Option<String>,
+}
+
+impl Backlight {
+    /// Creates new `Backlight` from block's configuration.
+    pub(crate) fn from_config(block: &ConfigStatusBarBlock) -> Result<Self, String> {
+        let options = Options::parse(block, &["device"])?;
+
+        Ok(Self {
+            template: options.template(DEFAULT_FORMAT, &["percent"])?,
+            root: options.root(),
+            device: options.get("device").map(String::from),
+        })
+    }
+
+    /// Returns configured device or the first one.
+    async fn device(&self) -> Result<String, BlockRunError> {
+        match &self.device {
+            Some(device) => Ok(device.clone()),
+            None => read_dir(&self.root, BACKLIGHT)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| invalid_data(String::from("no backlight device found"))),
+        }
+    }
+}
+
+#[async_trait]
+impl BlockSource for Backlight {
+    async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+        let device = Path::new(BACKLIGHT).join(self.device().await?);
+        let brightness: u64 = parse_value(
+            "brightness",
+            &read_file(&self.root, device.join("brightness")).await?,
+        )?;
+        let max: u64 = parse_value(
+            "max_brightness",
+            &read_file(&self.root, device.join("max_brightness")).await?,
+        )?;
+        let percent = match max {
+            0 => 0,
+            max => (100 * brightness + max / 2) / max,
+        };
+
+        Ok(self.template.render(|_| percent.to_string()))
+    }
+
+    fn describe(&self) -> String {
+        String::from("backlight")
+    }
+}
@@ -0,0 +1,97 @@
+//! This module defines `battery` builtin, that displays battery's capacity
+//! and status read from `/sys/class/power_supply/<device>`.
+//!
+//! Battery is chosen with `device` argument (for example `device=BAT1`).
+//! If it is not set, then the first power supply of type `Battery` is used.
+//!
+//! Placeholders:
+//!  - `{capacity}`: battery's charge (in percent)
+//!  - `{status}`: battery's status (like `Charging`, `Discharging` or `Full`)
+//!
+//! # Example
+//! ```yaml
+//! - name: battery
+//!   type: builtin
+//!   command: battery
+//!   args: ["format={status} {capacity}%", "device=BAT0"]
+//!   interval: 60
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+
+use super::{invalid_data, parse_value, read_dir, read_file, Options};
+use crate::block::{BlockRunError, BlockRunMode, BlockSource};
+use crate::config::ConfigStatusBarBlock;
+use crate::utils::Template;
+
+/// Format used when `format` argument is not set.
+pub const DEFAULT_FORMAT: &str = "{capacity}%";
+
+const POWER_SUPPLY: &str = "sys/class/power_supply";
+
+/// [BlockSource] of a `battery` builtin.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Battery {
+    template: Template,
+    root: PathBuf,
+    device: Option<String>,
+}
+
+impl Battery {
+    /// Creates new `Battery` from block's configuration.
+    pub(crate) fn from_config(block: &ConfigStatusBarBlock) -> Result<Self, String> {
+        let options = Options::parse(block, &["device"])?;
+
+        Ok(Self {
+            template: options.template(DEFAULT_FORMAT, &["capacity", "status"])?,
+            root: options.root(),
+            device: options.get("device").map(String::from),
+        })
+    }
+
+    /// Returns configured device or the first battery.
+    async fn device(&self) -> Result<String, BlockRunError> {
+        if let Some(device) = &self.device {
+            return Ok(device.clone());
+        }
+
+        for device in read_dir(&self.root, POWER_SUPPLY).await? {
+            let kind = read_file(
+                &self.root,
+                Path::new(POWER_SUPPLY).join(&device).join("type"),
+            )
+            .await;
+            if matches!(kind, Ok(kind) if kind.trim() == "Battery") {
+                return Ok(device);
+            }
+        }
+        Err(invalid_data(String::from("no battery found")))
+    }
+}
+
+#[async_trait]
+impl BlockSource for Battery {
+    async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+        let device = Path::new(POWER_SUPPLY).join(self.device().await?);
+        let capacity: u8 = parse_value(
+            "capacity",
+            &read_file(&self.root, device.join("capacity")).await?,
+        )?;
+        let status = if self.template.contains("status") {
+            read_file(&self.root, device.join("status")).await?
+        } else {
+            String::new()
+        };
+
+        Ok(self.template.render(|name| match name {
+            "capacity" => capacity.to_string(),
+            _ => status.trim().to_string(),
+        }))
+    }
+
+    fn describe(&self) -> String {
+        String::from("battery")
+    }
+}
@@ -0,0 +1,133 @@
+//! This module defines `cpu` builtin, that displays CPU usage.
+//!
+//! Usage is computed from the difference of times read from `/proc/stat`
+//! between two runs (the first run displays usage since boot).
+//!
+//! Placeholders:
+//!  - `{usage}`: percent of time CPU was busy
+//!
+//! # Example
+//! ```yaml
+//! - name: cpu
+//!   type: builtin
+//!   command: cpu
+//!   args: ["format=CPU {usage}%"]
+//!   interval: 2
+//! ```
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use super::{invalid_data, read_file, Options};
+use crate::block::{BlockRunError, BlockRunMode, BlockSource};
+use crate::config::ConfigStatusBarBlock;
+use crate::utils::Template;
+
+/// Format used when `format` argument is not set.
+pub const DEFAULT_FORMAT: &str = "{usage}%";
+
+/// Times (in ticks) spent by CPU, read from the first line of `/proc/stat`.
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+struct CpuTimes {
+    idle: u64,
+    total: u64,
+}
+
+impl CpuTimes {
+    fn parse(stat: &str) -> Option<Self> {
+        let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+        let times = line
+            .split_whitespace()
+            .skip(1)
+            .map(|time| time.parse::<u64>().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        if times.len() < 4 {
+            return None;
+        }
+
+        // idle + iowait
+        let idle = times[3] + times.get(4).unwrap_or(&0);
+        // guest times are already included in user times
+        let total = times.iter().take(8).sum();
+
+        Some(Self { idle, total })
+    }
+
+    /// Percent of time CPU was busy since `previous` times.
+    fn usage_since(&self, previous: &Self) -> u64 {
+        let total = self.total.saturating_sub(previous.total);
+        let idle = self.idle.saturating_sub(previous.idle);
+        match total {
+            0 => 0,
+            total => (100 * total.saturating_sub(idle) + total / 2) / total,
+        }
+    }
+}
+
+/// [BlockSource] of a `cpu` builtin.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cpu {
+    template: Template,
+    root: PathBuf,
+    previous: CpuTimes,
+}
+
+impl Cpu {
+    /// Creates new `Cpu` from block's configuration.
+    pub(crate) fn from_config(block: &ConfigStatusBarBlock) -> Result<Self, String> {
+        let options = Options::parse(block, &[])?;
+
+        Ok(Self {
+            template: options.template(DEFAULT_FORMAT, &["usage"])?,
+            root: options.root(),
+            previous: CpuTimes::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl BlockSource for Cpu {
+    async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+        let stat = read_file(&self.root, "proc/stat").await?;
+        let times = CpuTimes::parse(&stat)
+            .ok_or_else(|| invalid_data(String::from("invalid format of /proc/stat")))?;
+        let usage = times.usage_since(&self.previous);
+        self.previous = times;
+
+        Ok(self.template.render(|_| usage.to_string()))
+    }
+
+    fn describe(&self) -> String {
+        String::from("cpu")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_times() {
+        let stat = "cpu  100 0 100 700 100 0 0 0 50 0\ncpu0 100 0 100 700 100 0 0 0 50 0\n";
+        let times = CpuTimes::parse(stat).unwrap();
+        assert_eq!(
+            times,
+            CpuTimes {
+                idle: 800,
+                total: 1000
+            }
+        );
+        assert_eq!(times.usage_since(&CpuTimes::default()), 20);
+
+        let next = CpuTimes {
+            idle: 830,
+            total: 1040,
+        };
+        assert_eq!(next.usage_since(&times), 25);
+        assert_eq!(next.usage_since(&next), 0);
+
+        assert_eq!(CpuTimes::parse("cpu 1 2"), None);
+        assert_eq!(CpuTimes::parse("intr 1 2 3 4"), None);
+    }
+}
@@ -0,0 +1,152 @@
+//! This module defines `datetime` builtin, that displays current date and time.
+//!
+//! Time is formatted with `format` argument, a strftime-like format string (see
+//! [chrono's documentation](chrono::format::strftime)). If it is not set, then
+//! [DEFAULT_FORMAT] is used. `timezone` argument is a name of a timezone from IANA
+//! database (like `timezone=Europe/Warsaw`). If it is not set, then local time is displayed.
+//!
+//! Block is refreshed at the wall clock multiples of it's interval (in it's timezone),
+//! so for example with interval of 60 seconds minute changes exactly on time, and with
+//! interval of 86400 seconds date changes at midnight.
+//!
+//! # Example
+//! ```yaml
+//! - name: clock
+//!   type: builtin
+//!   command: datetime
+//!   args: ["format=%a %d %b %H:%M", "timezone=Europe/Warsaw"]
+//!   interval: 60
+//! ```
+
+use async_trait::async_trait;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, Utc};
+use chrono_tz::Tz;
+
+use crate::block::{Alignment, BlockRunError, BlockRunMode, BlockSource};
+use crate::config::ConfigStatusBarBlock;
+
+use super::Options;
+
+/// Format used when `datetime` builtin has no `format` argument.
+pub const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// [BlockSource] of a `datetime` builtin.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DateTime {
+    format: String,
+    timezone: Option<Tz>,
+}
+
+impl DateTime {
+    /// Creates new `DateTime` displaying time in `timezone`
+    /// (or local time if `None`) with given `format`.
+    ///
+    /// Error is returned if `format` is not a valid format string.
+    pub fn new(format: String, timezone: Option<Tz>) -> Result<Self, String> {
+        if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+            return Err(format!("invalid format string `{}`", format));
+        }
+
+        Ok(Self { format, timezone })
+    }
+
+    /// Creates new `DateTime` from block's configuration.
+    pub(crate) fn from_config(block: &ConfigStatusBarBlock) -> Result<Self, String> {
+        let options = Options::parse(block, &["timezone"])?;
+        let format = options.get("format").unwrap_or(DEFAULT_FORMAT);
+        let timezone = match options.get("timezone") {
+            Some(timezone) => Some(
+                timezone
+                    .parse::<Tz>()
+                    .map_err(|_| format!("unknown timezone `{}`", timezone))?,
+            ),
+            None => None,
+        };
+
+        Self::new(format.to_string(), timezone)
+    }
+
+    /// Formats current time.
+    pub fn now(&self) -> String {
+        match self.timezone {
+            Some(timezone) => Utc::now()
+                .with_timezone(&timezone)
+                .format(&self.format)
+                .to_string(),
+            None => Local::now().format(&self.format).to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl BlockSource for DateTime {
+    async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+        Ok(self.now())
+    }
+
+    fn describe(&self) -> String {
+        String::from("datetime")
+    }
+
+    fn alignment(&self) -> Option<Alignment> {
+        Some(match self.timezone {
+            Some(timezone) => Alignment::Timezone(timezone),
+            None => Alignment::Local,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(args: &[&str]) -> ConfigStatusBarBlock {
+        ConfigStatusBarBlock {
+            command: String::from("datetime"),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn datetime_from_config() {
+        assert_eq!(
+            DateTime::from_config(&block(&[])).unwrap(),
+            DateTime::new(String::from(DEFAULT_FORMAT), None).unwrap()
+        );
+        assert_eq!(
+            DateTime::from_config(&block(&["format=%H:%M", "timezone=Europe/Warsaw"])).unwrap(),
+            DateTime::new(String::from("%H:%M"), Some(chrono_tz::Europe::Warsaw)).unwrap()
+        );
+        assert_eq!(
+            DateTime::from_config(&block(&["timezone=UTC"])).unwrap(),
+            DateTime::new(String::from(DEFAULT_FORMAT), Some(Tz::UTC)).unwrap()
+        );
+
+        assert!(DateTime::from_config(&block(&["format=%H:%Q"])).is_err());
+        assert!(DateTime::from_config(&block(&["timezone=Mars/Olympus_Mons"])).is_err());
+        // Positional arguments are not accepted.
+        assert!(DateTime::from_config(&block(&["%H:%M"])).is_err());
+        assert!(DateTime::from_config(&block(&["zone=UTC"])).is_err());
+    }
+
+    #[test]
+    fn datetime_timezone() {
+        let datetime = DateTime::new(String::from("%Y %H:%M %Z"), Some(Tz::UTC)).unwrap();
+        let year = Utc::now().format("%Y").to_string();
+        assert!(datetime.now().starts_with(&year));
+        assert!(datetime.now().ends_with("UTC"));
+    }
+
+    #[test]
+    fn datetime_alignment() {
+        let local = DateTime::new(String::from(DEFAULT_FORMAT), None).unwrap();
+        assert_eq!(local.alignment(), Some(Alignment::Local));
+        let kolkata = DateTime::new(String::from(DEFAULT_FORMAT), Some(Tz::Asia__Kolkata)).unwrap();
+        assert_eq!(
+            kolkata.alignment(),
+            Some(Alignment::Timezone(Tz::Asia__Kolkata))
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! This module defines `load` builtin, that displays load average read from `/proc/loadavg`.
+//!
+//! Placeholders:
+//!  - `{load1}`: load average over the last minute
+//!  - `{load5}`: load average over the last 5 minutes
+//!  - `{load15}`: load average over the last 15 minutes
+//!
+//! # Example
+//! ```yaml
+//! - name: load
+//!   type: builtin
+//!   command: load
+//!   args: ["format={load1} {load5} {load15}"]
+//!   interval: 5
+//! ```
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use super::{invalid_data, read_file, Options};
+use crate::block::{BlockRunError, BlockRunMode, BlockSource};
+use crate::config::ConfigStatusBarBlock;
+use crate::utils::Template;
+
+/// Format used when `format` argument is not set.
+pub const DEFAULT_FORMAT: &str = "{load1}";
+
+/// [BlockSource] of a `load` builtin.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Load {
+    template: Template,
+    root: PathBuf,
+}
+
+impl Load {
+    /// Creates new `Load` from block's configuration.
+    pub(crate) fn from_config(block: &ConfigStatusBarBlock) -> Result<Self, String> {
+        let options = Options::parse(block, &[])?;
+
+        Ok(Self {
+            template: options.template(DEFAULT_FORMAT, &["load1", "load5", "load15"])?,
+            root: options.root(),
+        })
+    }
+}
+
+#[async_trait]
+impl BlockSource for Load {
+    async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+        let loadavg = read_file(&self.root, "proc/loadavg").await?;
+        let loads: Vec<&str> = loadavg.split_whitespace().take(3).collect();
+        if loads.len() < 3 {
+            return Err(invalid_data(String::from(
+                "invalid format of /proc/loadavg",
+            )));
+        }
+
+        Ok(self.template.render(|name| match name {
+            "load1" => loads[0].to_string(),
+            "load5" => loads[1].to_string(),
+            _ => loads[2].to_string(),
+        }))
+    }
+
+    fn describe(&self) -> String {
+        String::from("load")
+    }
+}
@@ -0,0 +1,110 @@
+//! This module defines `memory` builtin, that displays memory usage read from `/proc/meminfo`.
+//!
+//! Placeholders:
+//!  - `{used}`: used memory (total minus available)
+//!  - `{available}`: memory available for starting new applications
+//!  - `{total}`: total usable memory
+//!  - `{percent}`: percent of used memory
+//!
+//! Sizes are displayed in human readable form (for example `512M` or `3.2G`).
+//!
+//! # Example
+//! ```yaml
+//! - name: memory
+//!   type: builtin
+//!   command: memory
+//!   args: ["format={used}/{total}"]
+//!   interval: 10
+//! ```
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use super::{invalid_data, parse_value, read_file, Options};
+use crate::block::{BlockRunError, BlockRunMode, BlockSource};
+use crate::config::ConfigStatusBarBlock;
+use crate::utils::Template;
+
+/// Format used when `format` argument is not set.
+pub const DEFAULT_FORMAT: &str = "{used}/{total}";
+
+/// [BlockSource] of a `memory` builtin.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Memory {
+    template: Template,
+    root: PathBuf,
+}
+
+impl Memory {
+    /// Creates new `Memory` from block's configuration.
+    pub(crate) fn from_config(block: &ConfigStatusBarBlock) -> Result<Self, String> {
+        let options = Options::parse(block, &[])?;
+
+        Ok(Self {
+            template: options
+                .template(DEFAULT_FORMAT, &["used", "available", "total", "percent"])?,
+            root: options.root(),
+        })
+    }
+}
+
+/// Returns value (in kB) of `field` in `/proc/meminfo`.
+fn meminfo_field(meminfo: &str, field: &str) -> Result<u64, BlockRunError> {
+    let value = meminfo
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| *name == field)
+        .map(|(_, value)| value.trim().trim_end_matches("kB"))
+        .ok_or_else(|| invalid_data(format!("missing {} in /proc/meminfo", field)))?;
+
+    parse_value("/proc/meminfo", value)
+}
+
+/// Formats size given in kB in human readable form.
+fn human_size(kb: u64) -> String {
+    const MB: u64 = 1024;
+    const GB: u64 = 1024 * 1024;
+    match kb {
+        kb if kb >= GB => format!("{:.1}G", kb as f64 / GB as f64),
+        kb if kb >= MB => format!("{}M", kb / MB),
+        kb => format!("{}K", kb),
+    }
+}
+
+#[async_trait]
+impl BlockSource for Memory {
+    async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+        let meminfo = read_file(&self.root, "proc/meminfo").await?;
+        let total = meminfo_field(&meminfo, "MemTotal")?;
+        let available = meminfo_field(&meminfo, "MemAvailable")?;
+        let used = total.saturating_sub(available);
+
+        Ok(self.template.render(|name| match name {
+            "used" => human_size(used),
+            "available" => human_size(available),
+            "total" => human_size(total),
+            _ => match total {
+                0 => String::from("0"),
+                total => (100 * used / total).to_string(),
+            },
+        }))
+    }
+
+    fn describe(&self) -> String {
+        String::from("memory")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_human_size() {
+        assert_eq!(human_size(512), "512K");
+        assert_eq!(human_size(1024), "1M");
+        assert_eq!(human_size(1536 * 1024), "1.5G");
+        assert_eq!(human_size(16 * 1024 * 1024), "16.0G");
+    }
+}
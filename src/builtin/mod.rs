@@ -0,0 +1,378 @@
+//! This module defines builtin blocks, that are computed in-process
+//! (without spawning a process every time they are run).
+//!
+//! Builtin blocks are configured with `type: builtin`, where block's `command`
+//! is the name of a builtin and `args` are passed to it. Every builtin is created
+//! by a factory registered in [Builtins] under it's name. Library users can
+//! [register](Builtins::register) their own builtins, which are implemented
+//! as [BlockSource]s.
+//!
+//! Builtins provided by this crate:
+//!  - `datetime`: current date and time (see [datetime] module)
+//!  - `cpu`: CPU usage (see [cpu] module)
+//!  - `memory`: memory usage (see [memory] module)
+//!  - `load`: load average (see [load] module)
+//!  - `battery`: battery capacity and status (see [battery] module)
+//!  - `backlight`: screen brightness (see [backlight] module)
+//!  - `temperature`: temperature of a thermal zone or hwmon sensor
+//!    (see [temperature] module)
+//!  - `network`: network throughput and interface state (see [network] module)
+//!
+//! Builtins are configured with `key=value` arguments. All of them accept `format`
+//! (a strftime string for `datetime` and a [Template] of their output for others).
+//! Builtins reading `/proc` and `/sys` also accept `root` (directory in which `proc`
+//! and `sys` are looked for, defaults to `/`), which allows to run them against
+//! fixture directories.
+
+pub mod backlight;
+pub mod battery;
+pub mod cpu;
+pub mod datetime;
+pub mod load;
+pub mod memory;
+pub mod network;
+pub mod temperature;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::fs;
+
+use crate::block::{BlockRunError, BlockSource};
+use crate::config::ConfigStatusBarBlock;
+use crate::utils::Template;
+
+/// Creates [BlockSource] of a builtin from block's configuration.
+///
+/// Error message is returned if configuration is not valid for this builtin.
+pub type BuiltinFactory =
+    Arc<dyn Fn(&ConfigStatusBarBlock) -> Result<Box<dyn BlockSource>, String> + Send + Sync>;
+
+/// Error returned when builtin block can't be created.
+#[derive(Debug, PartialEq, Clone)]
+pub enum BuiltinError {
+    /// There is no builtin with this name.
+    Unknown(String),
+    /// Builtin was configured incorrectly.
+    InvalidConfig {
+        /// Name of a builtin.
+        builtin: String,
+        /// Why configuration is invalid.
+        reason: String,
+    },
+}
+
+impl fmt::Display for BuiltinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Self::Unknown(builtin) => format!("Unknown builtin: `{}`", builtin),
+            Self::InvalidConfig { builtin, reason } => {
+                format!("Invalid configuration of builtin `{}`: {}", builtin, reason)
+            }
+        };
+
+        write!(f, "{}", msg)
+    }
+}
+
+impl Error for BuiltinError {}
+
+/// Registry of builtin blocks.
+///
+/// [new](Builtins::new) registry contains all builtins provided by this crate.
+///
+/// # Example
+/// ```
+/// use async_trait::async_trait;
+/// use asyncdwmblocks::block::{BlockRunError, BlockRunMode, BlockSource};
+/// use asyncdwmblocks::builtin::Builtins;
+///
+/// #[derive(Debug)]
+/// struct Hello(String);
+///
+/// #[async_trait]
+/// impl BlockSource for Hello {
+///     async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+///         Ok(format!("Hello {}", self.0))
+///     }
+/// }
+///
+/// let mut builtins = Builtins::new();
+/// builtins.register("hello", |block| match block.args.first() {
+///     Some(name) => Ok(Box::new(Hello(name.clone()))),
+///     None => Err(String::from("expected name to greet")),
+/// });
+/// assert!(builtins.contains("hello"));
+/// ```
+#[derive(Clone)]
+pub struct Builtins {
+    factories: HashMap<String, BuiltinFactory>,
+}
+
+impl Builtins {
+    /// Creates registry of builtins provided by this crate.
+    pub fn new() -> Self {
+        let mut builtins = Self::empty();
+        builtins.register("datetime", |block| {
+            Ok(Box::new(datetime::DateTime::from_config(block)?))
+        });
+        builtins.register("cpu", |block| Ok(Box::new(cpu::Cpu::from_config(block)?)));
+        builtins.register("memory", |block| {
+            Ok(Box::new(memory::Memory::from_config(block)?))
+        });
+        builtins.register("load", |block| {
+            Ok(Box::new(load::Load::from_config(block)?))
+        });
+        builtins.register("battery", |block| {
+            Ok(Box::new(battery::Battery::from_config(block)?))
+        });
+        builtins.register("backlight", |block| {
+            Ok(Box::new(backlight::Backlight::from_config(block)?))
+        });
+        builtins.register("temperature", |block| {
+            Ok(Box::new(temperature::Temperature::from_config(block)?))
+        });
+        builtins.register("network", |block| {
+            Ok(Box::new(network::Network::from_config(block)?))
+        });
+        builtins
+    }
+
+    /// Creates registry without any builtins.
+    pub fn empty() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Registers builtin **name** created by **factory**.
+    /// If there already was a builtin with this name, then it is replaced.
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&ConfigStatusBarBlock) -> Result<Box<dyn BlockSource>, String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.factories.insert(name.to_string(), Arc::new(factory));
+    }
+
+    /// Checks if there is a builtin with this name.
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// Returns sorted names of all builtins.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Creates source of a builtin block (whose name is block's `command`).
+    pub fn create(
+        &self,
+        block: &ConfigStatusBarBlock,
+    ) -> Result<Box<dyn BlockSource>, BuiltinError> {
+        let factory = self
+            .factories
+            .get(&block.command)
+            .ok_or_else(|| BuiltinError::Unknown(block.command.clone()))?;
+        factory(block).map_err(|reason| BuiltinError::InvalidConfig {
+            builtin: block.command.clone(),
+            reason,
+        })
+    }
+}
+
+impl Default for Builtins {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Builtins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Builtins")
+            .field("names", &self.names())
+            .finish()
+    }
+}
+
+/// Registries are equal if they have builtins with the same names.
+impl PartialEq for Builtins {
+    fn eq(&self, other: &Self) -> bool {
+        self.names() == other.names()
+    }
+}
+
+/// Arguments of a builtin given as `key=value` pairs.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Options {
+    options: HashMap<String, String>,
+}
+
+impl Options {
+    /// Parses arguments of a builtin **block**. Apart from `format` and `root`
+    /// only keys from `allowed` are accepted.
+    pub(crate) fn parse(block: &ConfigStatusBarBlock, allowed: &[&str]) -> Result<Self, String> {
+        let mut options = HashMap::new();
+        for arg in &block.args {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("expected `key=value` argument, got `{}`", arg))?;
+            if !["format", "root"].contains(&key) && !allowed.contains(&key) {
+                return Err(format!("unknown argument `{}`", key));
+            }
+            options.insert(key.to_string(), value.to_string());
+        }
+
+        Ok(Self { options })
+    }
+
+    /// Returns value of option `key`.
+    pub(crate) fn get(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    /// Returns directory in which `proc` and `sys` are looked for.
+    pub(crate) fn root(&self) -> PathBuf {
+        PathBuf::from(self.get("root").unwrap_or("/"))
+    }
+
+    /// Parses `format` option (or `default` if it is not set) as a [Template]
+    /// that can contain given `placeholders`.
+    pub(crate) fn template(
+        &self,
+        default: &str,
+        placeholders: &[&str],
+    ) -> Result<Template, String> {
+        Template::parse(self.get("format").unwrap_or(default), placeholders)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Reads file `path` located in `root` directory.
+///
+/// Reading some files in `/sys` can block for a while,
+/// so they are read without blocking tokio's worker thread.
+pub(crate) async fn read_file<P: AsRef<Path>>(
+    root: &Path,
+    path: P,
+) -> Result<String, BlockRunError> {
+    let path = root.join(path);
+    fs::read_to_string(&path)
+        .await
+        .map_err(|e| source_error(&path, e))
+}
+
+/// Returns sorted names of entries of directory `path` located in `root` directory.
+pub(crate) async fn read_dir<P: AsRef<Path>>(
+    root: &Path,
+    path: P,
+) -> Result<Vec<String>, BlockRunError> {
+    let path = root.join(path);
+    let mut names = Vec::new();
+    let mut entries = fs::read_dir(&path)
+        .await
+        .map_err(|e| source_error(&path, e))?;
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| source_error(&path, e))?
+    {
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    names.sort_unstable();
+
+    Ok(names)
+}
+
+/// Parses `value` (trimmed) read from file `name`.
+pub(crate) fn parse_value<T: std::str::FromStr>(
+    name: &str,
+    value: &str,
+) -> Result<T, BlockRunError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid value in {}: `{}`", name, value.trim())))
+}
+
+/// Creates [BlockRunError::SourceError] describing invalid data read by a builtin.
+pub(crate) fn invalid_data(msg: String) -> BlockRunError {
+    BlockRunError::SourceError(Box::new(io::Error::new(io::ErrorKind::InvalidData, msg)))
+}
+
+fn source_error(path: &Path, err: io::Error) -> BlockRunError {
+    let msg = format!("{}: {}", path.display(), err);
+    BlockRunError::SourceError(Box::new(io::Error::new(err.kind(), msg)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{BlockRunError, BlockRunMode};
+    use async_trait::async_trait;
+
+    #[derive(Debug)]
+    struct Echo(String);
+
+    #[async_trait]
+    impl BlockSource for Echo {
+        async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn echo_builtins() -> Builtins {
+        let mut builtins = Builtins::empty();
+        builtins.register("echo", |block| match block.args.first() {
+            Some(text) => Ok(Box::new(Echo(text.clone()))),
+            None => Err(String::from("missing text")),
+        });
+        builtins
+    }
+
+    #[tokio::test]
+    async fn create_builtin() {
+        let builtins = echo_builtins();
+        let block = ConfigStatusBarBlock {
+            command: String::from("echo"),
+            args: vec![String::from("hi")],
+            ..Default::default()
+        };
+
+        let mut source = builtins.create(&block).unwrap();
+        assert_eq!(source.produce(BlockRunMode::Normal).await.unwrap(), "hi");
+    }
+
+    #[test]
+    fn create_builtin_errors() {
+        let builtins = echo_builtins();
+        let unknown = ConfigStatusBarBlock {
+            command: String::from("unknown"),
+            ..Default::default()
+        };
+        let invalid = ConfigStatusBarBlock {
+            command: String::from("echo"),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            builtins.create(&unknown).unwrap_err(),
+            BuiltinError::Unknown(String::from("unknown"))
+        );
+        assert_eq!(
+            builtins.create(&invalid).unwrap_err(),
+            BuiltinError::InvalidConfig {
+                builtin: String::from("echo"),
+                reason: String::from("missing text"),
+            }
+        );
+    }
+}
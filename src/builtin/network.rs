@@ -0,0 +1,226 @@
+//! This module defines `network` builtin, that displays download and upload
+//! rates of a network interface (read from `/proc/net/dev`) and it's state
+//! (read from `/sys/class/net/<interface>/operstate`).
+//!
+//! Interface is chosen with `interface` argument (for example `interface=wlan0`).
+//! If it is not set, then interface of the default route (read from `/proc/net/route`)
+//! is used. Rates are computed from the difference of transferred bytes between
+//! two runs (the first run displays rates equal to 0).
+//!
+//! `unit` argument sets units of rates: `bytes` (default, `B`, `K`, `M` and `G`
+//! counted in powers of 1024) or `bits` (`b`, `Kb`, `Mb` and `Gb` counted in powers of 1000).
+//!
+//! Placeholders:
+//!  - `{interface}`: name of the interface
+//!  - `{state}`: state of the interface (like `up`, `down` or `unknown`)
+//!  - `{down}`: download rate (per second)
+//!  - `{up}`: upload rate (per second)
+//!
+//! # Example
+//! ```yaml
+//! - name: network
+//!   type: builtin
+//!   command: network
+//!   args: ["format={interface} {down}/s {up}/s", "unit=bits"]
+//!   interval: 2
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use async_trait::async_trait;
+
+use super::{invalid_data, read_file, Options};
+use crate::block::{BlockRunError, BlockRunMode, BlockSource};
+use crate::config::ConfigStatusBarBlock;
+use crate::utils::Template;
+
+/// Format used when `format` argument is not set.
+pub const DEFAULT_FORMAT: &str = "{interface} ↓{down} ↑{up}";
+
+/// Units in which rates are displayed.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Unit {
+    Bytes,
+    Bits,
+}
+
+impl Unit {
+    /// Formats rate given in bytes per second.
+    fn format(&self, bytes: f64) -> String {
+        let (rate, base, units) = match self {
+            Self::Bytes => (bytes, 1024.0, ["B", "K", "M", "G"]),
+            Self::Bits => (bytes * 8.0, 1000.0, ["b", "Kb", "Mb", "Gb"]),
+        };
+
+        let mut rate = rate;
+        let mut unit = 0;
+        while rate >= base && unit < units.len() - 1 {
+            rate /= base;
+            unit += 1;
+        }
+        match unit {
+            0 => format!("{:.0}{}", rate, units[unit]),
+            _ => format!("{:.1}{}", rate, units[unit]),
+        }
+    }
+}
+
+/// Bytes transferred by an interface, read at some point in time.
+#[derive(Debug, PartialEq, Clone)]
+struct Transferred {
+    interface: String,
+    at: Instant,
+    received: u64,
+    transmitted: u64,
+}
+
+/// Returns received and transmitted bytes of `interface` read from `/proc/net/dev`.
+fn parse_dev(dev: &str, interface: &str) -> Option<(u64, u64)> {
+    let counters: Vec<u64> = dev
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim() == interface)?
+        .1
+        .split_whitespace()
+        .map(|counter| counter.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+
+    Some((*counters.first()?, *counters.get(8)?))
+}
+
+/// Returns interface of the default route read from `/proc/net/route`.
+fn parse_route(route: &str) -> Option<String> {
+    route.lines().skip(1).find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let interface = fields.next()?;
+        match fields.next()? {
+            "00000000" => Some(interface.to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// [BlockSource] of a `network` builtin.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Network {
+    template: Template,
+    root: PathBuf,
+    interface: Option<String>,
+    unit: Unit,
+    previous: Option<Transferred>,
+}
+
+impl Network {
+    /// Creates new `Network` from block's configuration.
+    pub(crate) fn from_config(block: &ConfigStatusBarBlock) -> Result<Self, String> {
+        let options = Options::parse(block, &["interface", "unit"])?;
+        let unit = match options.get("unit") {
+            None | Some("bytes") => Unit::Bytes,
+            Some("bits") => Unit::Bits,
+            Some(unit) => return Err(format!("unknown unit `{}`", unit)),
+        };
+
+        Ok(Self {
+            template: options.template(DEFAULT_FORMAT, &["interface", "state", "down", "up"])?,
+            root: options.root(),
+            interface: options.get("interface").map(String::from),
+            unit,
+            previous: None,
+        })
+    }
+
+    /// Returns configured interface or the one of the default route.
+    async fn interface(&self) -> Result<String, BlockRunError> {
+        match &self.interface {
+            Some(interface) => Ok(interface.clone()),
+            None => parse_route(&read_file(&self.root, "proc/net/route").await?)
+                .ok_or_else(|| invalid_data(String::from("no default route found"))),
+        }
+    }
+}
+
+#[async_trait]
+impl BlockSource for Network {
+    async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+        let interface = self.interface().await?;
+        let (received, transmitted) =
+            parse_dev(&read_file(&self.root, "proc/net/dev").await?, &interface)
+                .ok_or_else(|| invalid_data(format!("interface {} not found", interface)))?;
+        let current = Transferred {
+            interface,
+            at: Instant::now(),
+            received,
+            transmitted,
+        };
+
+        // Rates are known only if the same interface was read before.
+        let (down, up) = match self.previous.take() {
+            Some(previous) if previous.interface == current.interface => {
+                let elapsed = current.at.duration_since(previous.at).as_secs_f64();
+                let rate = |now: u64, before: u64| match elapsed {
+                    elapsed if elapsed > 0.0 => now.saturating_sub(before) as f64 / elapsed,
+                    _ => 0.0,
+                };
+                (
+                    rate(current.received, previous.received),
+                    rate(current.transmitted, previous.transmitted),
+                )
+            }
+            _ => (0.0, 0.0),
+        };
+        let state = if self.template.contains("state") {
+            let operstate = Path::new("sys/class/net")
+                .join(&current.interface)
+                .join("operstate");
+            read_file(&self.root, operstate).await?.trim().to_string()
+        } else {
+            String::new()
+        };
+
+        let output = self.template.render(|name| match name {
+            "interface" => current.interface.clone(),
+            "state" => state.clone(),
+            "down" => self.unit.format(down),
+            _ => self.unit.format(up),
+        });
+        self.previous = Some(current);
+
+        Ok(output)
+    }
+
+    fn describe(&self) -> String {
+        String::from("network")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_units() {
+        assert_eq!(Unit::Bytes.format(0.0), "0B");
+        assert_eq!(Unit::Bytes.format(1023.0), "1023B");
+        assert_eq!(Unit::Bytes.format(1536.0), "1.5K");
+        assert_eq!(Unit::Bytes.format(3.0 * 1024.0 * 1024.0), "3.0M");
+        assert_eq!(Unit::Bits.format(125.0), "1.0Kb");
+        assert_eq!(Unit::Bits.format(12.0), "96b");
+    }
+
+    #[test]
+    fn network_parse() {
+        let dev = "Inter-|   Receive                            |  Transmit\n \
+            face |bytes packets errs drop fifo frame compressed multicast|bytes packets\n    \
+            lo:  100 1 0 0 0 0 0 0 100 1 0 0 0 0 0 0\n  \
+            eth0: 2048 20 0 0 0 0 0 0 512 5 0 0 0 0 0 0\n";
+        assert_eq!(parse_dev(dev, "eth0"), Some((2048, 512)));
+        assert_eq!(parse_dev(dev, "wlan0"), None);
+
+        let route = "Iface\tDestination\tGateway\tFlags\n\
+            eth0\t0000A8C0\t00000000\t0001\n\
+            wlan0\t00000000\t0100A8C0\t0003\n";
+        assert_eq!(parse_route(route), Some(String::from("wlan0")));
+        assert_eq!(parse_route("Iface\tDestination\n"), None);
+    }
+}
@@ -0,0 +1,77 @@
+//! This module defines `temperature` builtin, that displays temperature
+//! of a thermal zone (`/sys/class/thermal`) or of a hwmon sensor (`/sys/class/hwmon`).
+//!
+//! Sensor is chosen with `sensor` argument. It is either a name of a thermal zone
+//! (for example `sensor=thermal_zone1`), or hwmon device and it's sensor
+//! (for example `sensor=hwmon2/temp1`). Defaults to `thermal_zone0`.
+//!
+//! Placeholders:
+//!  - `{temp}`: temperature (in degrees Celsius)
+//!
+//! # Example
+//! ```yaml
+//! - name: temperature
+//!   type: builtin
+//!   command: temperature
+//!   args: ["format={temp}°C", "sensor=hwmon1/temp1"]
+//!   interval: 10
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+
+use super::{parse_value, read_file, Options};
+use crate::block::{BlockRunError, BlockRunMode, BlockSource};
+use crate::config::ConfigStatusBarBlock;
+use crate::utils::Template;
+
+/// Format used when `format` argument is not set.
+pub const DEFAULT_FORMAT: &str = "{temp}°C";
+
+/// Sensor used when `sensor` argument is not set.
+pub const DEFAULT_SENSOR: &str = "thermal_zone0";
+
+/// [BlockSource] of a `temperature` builtin.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Temperature {
+    template: Template,
+    root: PathBuf,
+    /// Path (relative to root) of a file with temperature in millidegrees Celsius.
+    path: PathBuf,
+}
+
+impl Temperature {
+    /// Creates new `Temperature` from block's configuration.
+    pub(crate) fn from_config(block: &ConfigStatusBarBlock) -> Result<Self, String> {
+        let options = Options::parse(block, &["sensor"])?;
+        let sensor = options.get("sensor").unwrap_or(DEFAULT_SENSOR);
+        let path = match sensor.split_once('/') {
+            Some((device, sensor)) if device.starts_with("hwmon") => Path::new("sys/class/hwmon")
+                .join(device)
+                .join(format!("{}_input", sensor)),
+            Some(_) => return Err(format!("invalid sensor `{}`", sensor)),
+            None => Path::new("sys/class/thermal").join(sensor).join("temp"),
+        };
+
+        Ok(Self {
+            template: options.template(DEFAULT_FORMAT, &["temp"])?,
+            root: options.root(),
+            path,
+        })
+    }
+}
+
+#[async_trait]
+impl BlockSource for Temperature {
+    async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+        let millidegrees: i64 = parse_value("temp", &read_file(&self.root, &self.path).await?)?;
+        let degrees = millidegrees / 1000;
+
+        Ok(self.template.render(|_| degrees.to_string()))
+    }
+
+    fn describe(&self) -> String {
+        String::from("temperature")
+    }
+}
@@ -11,12 +11,14 @@ fn default_statusbar_blocks() -> Vec<ConfigStatusBarBlock> {
     //     command: "battery.sh".to_string(),
     //     args: vec![],
     //     interval: Some(60),
+    //     ..Default::default()
     //   },
     //   ConfigStatusBarBlock {
     //     name: "backlight".to_string(),
     //     command: "backlight.sh".to_string(),
     //     args: vec![],
     //     interval: None,
+    //     ..Default::default()
     //    },
     // ]
     vec![]
@@ -27,6 +29,9 @@ impl Default for ConfigStatusBar {
         Self {
             delimiter: String::from(" "),
             blocks: default_statusbar_blocks(),
+            statuscmd: false,
+            protocol: Protocol::Plain,
+            coalesce: 0,
         }
     }
 }
@@ -35,6 +40,11 @@ impl Default for ConfigBlock {
     fn default() -> Self {
         Self {
             clicked_env_variable: String::from("BUTTON"),
+            timeout: None,
+            timeout_placeholder: None,
+            busy_policy: BusyPolicy::Queue,
+            error_policy: ErrorPolicy::Show,
+            error_marker: String::from("⚠"),
         }
     }
 }
@@ -78,6 +88,8 @@ impl Default for ConfigIpc {
             tcp: Default::default(),
             #[cfg(feature = "uds")]
             uds: Default::default(),
+            max_frame_size: 1024,
+            idle_timeout: Some(5),
         }
     }
 }
@@ -89,6 +101,7 @@ impl Default for Config {
         Self {
             statusbar: Default::default(),
             block: Default::default(),
+            outputs: vec![ConfigOutput::X11],
             #[cfg(feature = "ipc")]
             ipc: Default::default(),
         }
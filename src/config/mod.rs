@@ -39,8 +39,10 @@ use serde::Deserialize;
 #[cfg(feature = "config-file")]
 use tokio::fs;
 
+use crate::block::{BlockMode, BusyPolicy, ErrorPolicy, Retry};
 #[cfg(feature = "ipc")]
 use crate::ipc::ServerType;
+use crate::statusbar::Protocol;
 
 /// Error returned when loading Config from file failed.
 #[cfg(feature = "config-file")]
@@ -81,20 +83,91 @@ impl fmt::Display for ConfigLoadError {
 #[cfg(feature = "config-file")]
 impl Error for ConfigLoadError {}
 
+/// Type of a [block](ConfigStatusBarBlock).
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[cfg_attr(feature = "config-file", derive(Deserialize))]
+#[cfg_attr(feature = "config-file", serde(rename_all = "lowercase"))]
+pub enum BlockType {
+    /// Block runs it's command as a separate process.
+    #[default]
+    Command,
+    /// Block is computed in-process by a [builtin](crate::builtin::Builtins),
+    /// whose name is block's command.
+    Builtin,
+}
+
 /// StatusBar's block representation.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 #[cfg_attr(feature = "config-file", derive(Deserialize))]
 pub struct ConfigStatusBarBlock {
     /// Block's name (id)
     pub name: String,
-    /// Command to run
+    /// Block's type (defaults to command).
+    #[cfg_attr(feature = "config-file", serde(default, rename = "type"))]
+    pub kind: BlockType,
+    /// Command to run (not needed for [push](BlockMode::Push) blocks)
+    /// or name of a builtin
+    #[cfg_attr(feature = "config-file", serde(default))]
     pub command: String,
-    /// Command's args
+    /// Command's (or builtin's) args
     #[cfg_attr(feature = "config-file", serde(default))]
     pub args: Vec<String>,
-    /// Refresh interval
+    /// Refresh interval (in seconds, at least 1)
     #[cfg_attr(feature = "config-file", serde(default))]
     pub interval: Option<u64>,
+    /// Command's timeout (in seconds, at least 1). Overrides [ConfigBlock::timeout].
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub timeout: Option<u64>,
+    /// Block's busy policy. Overrides [ConfigBlock::busy_policy].
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub busy_policy: Option<BusyPolicy>,
+    /// Retrying of block's failed runs. If `None` they are not retried.
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub retry: Option<Retry>,
+    /// Block's error policy. Overrides [ConfigBlock::error_policy].
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub error_policy: Option<ErrorPolicy>,
+    /// Block's error marker (for example `⚠ battery`). Overrides [ConfigBlock::error_marker].
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub error_marker: Option<String>,
+    /// How block's command produces output (defaults to oneshot).
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub mode: BlockMode,
+    /// Real-time signal offset (as in dwmblocks). Sending `SIGRTMIN+signal`
+    /// to asyncdwmblocks refreshes this block.
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub signal: Option<u8>,
+    /// Time (in seconds, at least 1) after which text set directly to this block
+    /// (for example with `SET` over IPC) expires. Then it is cleared
+    /// ([push](BlockMode::Push) blocks) or replaced by command's output
+    /// (which is not shown while text is set). If `None` then text is kept
+    /// until it is replaced (by new text or command's output).
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub ttl: Option<u64>,
+    /// Template in which block's result is displayed (for example `" {output}%"`).
+    /// `{output}` is replaced by block's result. Literal braces
+    /// are written as `{{` and `}}`. If `None` result is displayed as it is.
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub format: Option<String>,
+    /// Text displayed before block's (formatted) result, like an icon or a label.
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub prefix: Option<String>,
+    /// Text displayed after block's (formatted) result.
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub suffix: Option<String>,
+    /// Block's color (for example `#ff0000`). Used only by status bar
+    /// [protocols](crate::statusbar::Protocol) that support colors.
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub color: Option<String>,
+    /// Hide block (together with it's delimiter) when it's result is empty.
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub hide_when_empty: bool,
+    /// Hide block when it's command exits with this code.
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub hide_when_exit_code: Option<i32>,
+    /// Hide block when it's result matches this regular expression.
+    #[cfg_attr(feature = "config-file", serde(default))]
+    pub hide_when_matches: Option<String>,
 }
 
 /// Configuration for [StatusBar](crate::statusbar::StatusBar).
@@ -106,6 +179,22 @@ pub struct ConfigStatusBar {
     pub delimiter: String,
     /// List of StatusBar Blocks.
     pub blocks: Vec<ConfigStatusBarBlock>,
+    /// Enables support for dwm's *statuscmd* patch. When enabled every
+    /// block with a [signal](ConfigStatusBarBlock::signal) smaller than 32
+    /// is prefixed with this signal's byte, so that dwm can send it back
+    /// (together with clicked button) when block is clicked.
+    pub statuscmd: bool,
+    /// Protocol in which status bar is rendered. When it is
+    /// [i3bar](Protocol::I3bar), then delimiter is replaced by
+    /// separators (drawn if delimiter is not empty) and
+    /// [outputs](Config::outputs) are ignored. Changing protocol
+    /// requires restarting asyncdwmblocks (reloading configuration
+    /// with different protocol fails).
+    pub protocol: Protocol,
+    /// Time (in milliseconds) during which changes of blocks are collected
+    /// before status bar is rendered, so that bursts of changes (like refreshing
+    /// all blocks) are rendered once. `0` renders every change immediately.
+    pub coalesce: u64,
 }
 
 /// Configuration for [Blocks](crate::block::Block).
@@ -115,6 +204,44 @@ pub struct ConfigStatusBar {
 pub struct ConfigBlock {
     /// Environment variable used to comunicate that block was clicked.
     pub clicked_env_variable: String,
+    /// Default timeout (in seconds, at least 1) after which block's command is killed.
+    /// `None` means that commands can run forever.
+    pub timeout: Option<u64>,
+    /// Text displayed by a block whose command timed out.
+    /// If `None` block keeps it's previous result.
+    pub timeout_placeholder: Option<String>,
+    /// What should happen when block is triggered while it is still running.
+    pub busy_policy: BusyPolicy,
+    /// What block displays when it's command exits with non-zero code.
+    pub error_policy: ErrorPolicy,
+    /// Text displayed by a failed block, when it's error policy is
+    /// [marker](ErrorPolicy::Marker).
+    pub error_marker: String,
+}
+
+/// Output to which rendered status bar is written (see [output](crate::output) module).
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "config-file", derive(Deserialize))]
+#[cfg_attr(feature = "config-file", serde(tag = "type", rename_all = "lowercase"))]
+pub enum ConfigOutput {
+    /// Name of X11 root window (read by dwm).
+    X11,
+    /// Standard output (one line per status bar).
+    Stdout,
+    /// File, that is overwritten with status bar, or to which
+    /// status bars are appended (one per line).
+    File {
+        /// Path of the file.
+        path: PathBuf,
+        /// Append status bars instead of overwriting file.
+        #[cfg_attr(feature = "config-file", serde(default))]
+        append: bool,
+    },
+    /// Named pipe (one line per status bar). It is created if it doesn't exist.
+    Fifo {
+        /// Path of the pipe.
+        path: PathBuf,
+    },
 }
 
 /// Configuration of Tcp Server/Notifier.
@@ -197,6 +324,11 @@ pub struct ConfigIpc {
     /// Configuration of Unix domain socket Server/Notifier.
     #[cfg(feature = "uds")]
     pub uds: ConfigIpcUnixDomainSocket,
+    /// Maximum size (in bytes) of a single frame accepted by Server.
+    pub max_frame_size: usize,
+    /// Time (in seconds) after which Server closes connection on which
+    /// nothing was received. `None` means that connections are never timed out.
+    pub idle_timeout: Option<u64>,
 }
 
 /// Main configuration struct.
@@ -208,6 +340,10 @@ pub struct Config {
     pub statusbar: ConfigStatusBar,
     /// Configuration of [`Blocks`](crate::block::Block).
     pub block: ConfigBlock,
+    /// Outputs to which status bar is written. Changing them requires
+    /// restarting asyncdwmblocks (reloading configuration with different
+    /// outputs fails).
+    pub outputs: Vec<ConfigOutput>,
     /// Configuration of IPC (inter process comunication).
     #[cfg(feature = "ipc")]
     pub ipc: ConfigIpc,
@@ -0,0 +1,233 @@
+//! This module implements [i3bar protocol](https://i3wm.org/docs/i3bar-protocol.html),
+//! that is used by i3bar and swaybar.
+//!
+//! When [protocol](crate::config::ConfigStatusBar::protocol) is set to
+//! [i3bar](crate::statusbar::Protocol::I3bar), every status bar is [rendered](render)
+//! as a JSON array of blocks (with their `name`, `full_text`, `color` and `separator`).
+//! It is then written to the standard output by [I3bar] sink, which first writes protocol's
+//! header and then continues an infinite array of status bars. Click events are read
+//! from the standard input and [translated](listen) into [BlockRefreshMessage]s
+//! with [BlockRunMode::Button].
+//!
+//! # Example
+//! ```yaml
+//! statusbar:
+//!   protocol: i3bar
+//!   blocks:
+//!     - name: battery
+//!       command: my_battery_script.sh
+//!       interval: 60
+//!       color: "#ffff00"
+//! ```
+
+use std::io;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use crate::block::{Block, BlockRunMode};
+use crate::output::Sink;
+use crate::statusbar::{BlockRefreshMessage, StatusBarRequest};
+
+/// Header of the protocol (sent once, before status bars).
+pub const HEADER: &str = r#"{"version":1,"click_events":true}"#;
+
+/// Block, as it is sent to i3bar.
+#[derive(Debug, PartialEq, Serialize)]
+struct I3barBlock<'a> {
+    name: &'a str,
+    full_text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<&'a str>,
+    separator: bool,
+}
+
+/// Click event sent by i3bar (other fields are ignored).
+#[derive(Debug, PartialEq, Deserialize)]
+struct ClickEvent {
+    name: String,
+    button: u8,
+}
+
+/// Renders **blocks** (with their names) as a JSON array. Blocks without
+/// [output](Block::output) are skipped. **separator** sets whether i3bar
+/// should draw separator after each block.
+pub fn render<'a, I>(blocks: I, separator: bool) -> String
+where
+    I: Iterator<Item = (&'a str, &'a Block)>,
+{
+    let outputs: Vec<_> = blocks
+        .filter_map(|(name, block)| block.output().map(|output| (name, block, output)))
+        .collect();
+    let blocks: Vec<I3barBlock> = outputs
+        .iter()
+        .map(|(name, block, output)| I3barBlock {
+            name,
+            full_text: output,
+            color: block.color(),
+            separator,
+        })
+        .collect();
+
+    // Serializing strings and booleans never fails.
+    serde_json::to_string(&blocks).unwrap_or_default()
+}
+
+/// [Sink] that writes status bars to the standard output
+/// using i3bar protocol (status bars must be [rendered](render) by it).
+#[derive(Debug)]
+pub struct I3bar {
+    stdout: tokio::io::Stdout,
+    started: bool,
+}
+
+impl I3bar {
+    /// Creates new `I3bar`.
+    pub fn new() -> Self {
+        Self {
+            stdout: tokio::io::stdout(),
+            started: false,
+        }
+    }
+}
+
+impl Default for I3bar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Sink for I3bar {
+    async fn write(&mut self, status: &str) -> io::Result<()> {
+        let message = frame(status, self.started);
+        self.stdout.write_all(message.as_bytes()).await?;
+        self.stdout.flush().await?;
+        self.started = true;
+
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        String::from("i3bar")
+    }
+}
+
+/// Returns message with a status bar. First one is preceded by header
+/// and opening of infinite array, the next ones by a comma.
+fn frame(status: &str, started: bool) -> String {
+    match started {
+        false => format!("{}\n[\n{}\n", HEADER, status),
+        true => format!(",{}\n", status),
+    }
+}
+
+/// Translates line with a click event into [BlockRefreshMessage].
+/// Returns `None` if line doesn't contain a click event.
+fn parse_click(line: &str) -> Option<BlockRefreshMessage> {
+    // Events are elements of an infinite array.
+    let event = line.trim().trim_start_matches(['[', ',']);
+    if event.is_empty() {
+        return None;
+    }
+    let event: ClickEvent = serde_json::from_str(event).ok()?;
+
+    Some(BlockRefreshMessage::new(
+        event.name,
+        BlockRunMode::Button(event.button),
+    ))
+}
+
+/// Reads click events from **reader** and sends refresh requests
+/// of clicked blocks through **sender**.
+///
+/// Returns when **reader** is closed or when receiving half
+/// of **sender** is closed. Invalid events are ignored.
+pub async fn listen<R>(reader: R, sender: mpsc::Sender<StatusBarRequest>) -> io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some(message) = parse_click(&line) {
+            if sender.send(message.into()).await.is_err() {
+                // Statusbar has stopped running.
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn i3bar_render() {
+        let config = Config::default().arc();
+        let mut battery = Block::new("battery".into(), vec![], None, config.clone())
+            .with_color(Some(String::from("#ff0000")));
+        battery.set_result(Some(String::from("50% \"low\"")));
+        let mut date = Block::new("date".into(), vec![], None, config.clone());
+        date.set_result(Some(String::from("12:00")));
+        let never_run = Block::new("date".into(), vec![], None, config);
+        let blocks = [
+            ("battery", &battery),
+            ("unknown", &never_run),
+            ("date", &date),
+        ];
+
+        assert_eq!(
+            render(blocks.into_iter(), true),
+            r##"[{"name":"battery","full_text":"50% \"low\"","color":"#ff0000","separator":true},{"name":"date","full_text":"12:00","separator":true}]"##
+        );
+        assert_eq!(render(std::iter::empty(), false), "[]");
+    }
+
+    #[test]
+    fn i3bar_frame() {
+        assert_eq!(frame("[]", false), format!("{}\n[\n[]\n", HEADER));
+        assert_eq!(frame("[]", true), ",[]\n");
+    }
+
+    #[test]
+    fn i3bar_parse_click() {
+        let message = BlockRefreshMessage::new(String::from("date"), BlockRunMode::Button(3));
+        let event = r#"{"name":"date","instance":"","button":3,"x":1320,"y":1400}"#;
+        assert_eq!(parse_click(event), Some(message.clone()));
+        assert_eq!(parse_click(&format!(",{}", event)), Some(message.clone()));
+        assert_eq!(parse_click(&format!("[{}", event)), Some(message));
+        assert_eq!(parse_click("["), None);
+        assert_eq!(parse_click(r#"{"button":1}"#), None);
+    }
+
+    #[tokio::test]
+    async fn i3bar_listen() {
+        let input: &[u8] =
+            b"[\n{\"name\":\"a\",\"button\":1}\n,invalid\n,{\"name\":\"b\",\"button\":2}\n";
+        let (sender, mut receiver) = mpsc::channel(8);
+        listen(input, sender).await.unwrap();
+
+        let mut names = Vec::new();
+        while let Some(request) = receiver.recv().await {
+            match request.message() {
+                crate::statusbar::StatusBarMessage::Refresh(message) => {
+                    names.push((message.name.clone(), message.mode.clone()))
+                }
+                message => panic!("unexpected message {:?}", message),
+            }
+        }
+        assert_eq!(
+            names,
+            vec![
+                (String::from("a"), BlockRunMode::Button(1)),
+                (String::from("b"), BlockRunMode::Button(2)),
+            ]
+        );
+    }
+}
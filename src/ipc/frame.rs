@@ -4,9 +4,13 @@
 //! This module defines two types: [Frame] and [Frames].
 //!
 //! `Frame` is a single unit of translation. It represents
-//! either a message ([`BlockRefreshMessage`]) or an `Error`
-//! (which means that decoding failed). It is more useful
-//! when used in context of `Frames`.
+//! either a message ([`BlockRefreshMessage`]), a request to refresh
+//! all blocks, a query (`LIST`, `GET` or `STATUS`), a change of blocks (`INSERT`,
+//! `REMOVE`, `MOVE`, `CHANGE` or `SET`) or an `Error` (which means that decoding failed).
+//! It is more useful when used in context of `Frames`.
+//!
+//! Every frame sent to a server is answered with a [Response].
+//! Notifiers start every connection with a [`Frame::Version`] frame.
 //!
 //! `Frames` is a collection of `Frame`s. It implements both
 //! [FromIterator] and [IntoIterator]. It can create collection
@@ -14,6 +18,9 @@
 //! and can produce byte stream back from list of `Frame`s. See
 //! examples for exemplary usage of them.
 //!
+//! [FrameDecoder] decodes `Frame`s incrementally, from data that arrives
+//! in chunks. It is used by [`Server`](super::Server)s.
+//!
 //! # Decoding
 //!
 //! This example shows how byte stream could be decoded and interpreted
@@ -29,6 +36,22 @@
 //!         Frame::Message(msg) => {
 //!             // send interpreted message somewhere
 //!         }
+//!         Frame::RefreshAll => {
+//!             // refresh all blocks
+//!         }
+//!         Frame::Version(version) => {
+//!             // check if protocol version is supported
+//!         }
+//!         Frame::List | Frame::Get(_) | Frame::Status | Frame::Subscribe { .. } => {
+//!             // answer query with a Response
+//!         }
+//!         Frame::Insert { .. }
+//!         | Frame::Remove(_)
+//!         | Frame::Move { .. }
+//!         | Frame::Change { .. }
+//!         | Frame::Set { .. } => {
+//!             // change blocks of a status bar
+//!         }
 //!         Frame::Error => {
 //!             // stream contained error, handle it or ignore
 //!         }
@@ -54,10 +77,55 @@
 //! let stream: Vec<u8> = frames.encode(); // Send this stream somewhere
 //! ```
 
+use std::error::Error;
+use std::fmt;
+
 use crate::block::BlockRunMode;
-use crate::statusbar::BlockRefreshMessage;
+use crate::statusbar::{
+    BlockInfo, BlockRefreshMessage, BlockUpdate, StatusBarEvent, StatusBarMessage,
+    StatusBarResponse,
+};
 use crate::utils::SplitAtRN;
 
+/// Version of the protocol spoken by this library.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Error returned when decoding [Frame] or [Response] failed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FrameParseError {
+    /// Frame is not a valid UTF-8.
+    InvalidUtf8,
+    /// Frame is empty.
+    Empty,
+    /// Frame starts with unknown command.
+    UnknownCommand(String),
+    /// Command was given invalid number of arguments.
+    InvalidArguments(String),
+    /// Argument of a command is invalid.
+    InvalidArgument(String),
+    /// Frame is longer than allowed maximum (in bytes).
+    TooLong(usize),
+}
+
+impl fmt::Display for FrameParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Self::InvalidUtf8 => String::from("invalid utf-8"),
+            Self::Empty => String::from("empty frame"),
+            Self::UnknownCommand(command) => format!("unknown command {}", command),
+            Self::InvalidArguments(command) => {
+                format!("invalid number of arguments for {}", command)
+            }
+            Self::InvalidArgument(argument) => format!("invalid argument {}", argument),
+            Self::TooLong(max) => format!("frame exceeds maximum size of {} bytes", max),
+        };
+
+        write!(f, "{}", msg)
+    }
+}
+
+impl Error for FrameParseError {}
+
 /// This enum defines single unit of translation.
 ///
 /// `Frame` can either hold a message, or (when decoding)
@@ -69,6 +137,62 @@ use crate::utils::SplitAtRN;
 pub enum Frame {
     /// This variant holds decoded/passed message.
     Message(BlockRefreshMessage),
+    /// This variant requests refreshing all blocks.
+    RefreshAll,
+    /// This variant announces protocol version used by the notifier.
+    Version(u32),
+    /// This variant asks for a list of blocks.
+    List,
+    /// This variant asks for a current result of a block.
+    Get(String),
+    /// This variant asks for a current status bar.
+    Status,
+    /// This variant subscribes to status bar's [Event]s (and optionally
+    /// to changes of blocks). Server keeps connection open and streams them.
+    Subscribe {
+        /// Whether to receive [Event::Block] events.
+        blocks: bool,
+    },
+    /// This variant inserts a new block at position `index`.
+    ///
+    /// Since frame's arguments are separated by whitespaces,
+    /// neither `command` nor `args` can contain them.
+    Insert {
+        /// Position of a new block.
+        index: usize,
+        /// Name of a new block.
+        name: String,
+        /// Refresh interval (in seconds) of a new block.
+        interval: Option<u64>,
+        /// Command of a new block.
+        command: String,
+        /// Arguments of a command.
+        args: Vec<String>,
+    },
+    /// This variant removes a block.
+    Remove(String),
+    /// This variant moves a block to position `index`.
+    Move {
+        /// Name of a moved block.
+        name: String,
+        /// New position of a block.
+        index: usize,
+    },
+    /// This variant changes definition of a block.
+    Change {
+        /// Name of a changed block.
+        name: String,
+        /// Changes to apply.
+        update: BlockUpdate,
+    },
+    /// This variant sets text of a block (without running it's command).
+    Set {
+        /// Name of a block.
+        name: String,
+        /// Block's new text. It is the rest of a frame,
+        /// so it can contain whitespaces (but not newlines).
+        text: String,
+    },
     /// This variant indicates error while decoding.
     Error,
 }
@@ -94,56 +218,517 @@ impl Frame {
                 };
                 Vec::from(s.as_bytes())
             }
+            Self::RefreshAll => Vec::from("REFRESH_ALL\r\n".as_bytes()),
+            Self::Version(version) => Vec::from(format!("VERSION {}\r\n", version).as_bytes()),
+            Self::List => Vec::from("LIST\r\n".as_bytes()),
+            Self::Get(name) => Vec::from(format!("GET {}\r\n", name).as_bytes()),
+            Self::Status => Vec::from("STATUS\r\n".as_bytes()),
+            Self::Subscribe { blocks: false } => Vec::from("SUBSCRIBE\r\n".as_bytes()),
+            Self::Subscribe { blocks: true } => Vec::from("SUBSCRIBE BLOCKS\r\n".as_bytes()),
+            Self::Insert {
+                index,
+                name,
+                interval,
+                command,
+                args,
+            } => {
+                let mut s = format!(
+                    "INSERT {} {} {} {}",
+                    index,
+                    name,
+                    encode_interval(*interval),
+                    command
+                );
+                for arg in args {
+                    s.push(' ');
+                    s.push_str(arg);
+                }
+                s.push_str("\r\n");
+                Vec::from(s.as_bytes())
+            }
+            Self::Remove(name) => Vec::from(format!("REMOVE {}\r\n", name).as_bytes()),
+            Self::Move { name, index } => {
+                Vec::from(format!("MOVE {} {}\r\n", name, index).as_bytes())
+            }
+            Self::Change { name, update } => {
+                let mut s = format!("CHANGE {}", name);
+                if let Some(interval) = update.interval {
+                    s.push_str(&format!(" INTERVAL {}", encode_interval(interval)));
+                }
+                // Command takes all remaining arguments, so it must be the last one.
+                if let Some((command, args)) = &update.command {
+                    s.push_str(&format!(" COMMAND {}", command));
+                    for arg in args {
+                        s.push(' ');
+                        s.push_str(arg);
+                    }
+                }
+                s.push_str("\r\n");
+                Vec::from(s.as_bytes())
+            }
+            // Text must fit in a single line.
+            Self::Set { name, text } => Vec::from(
+                format!("SET {} {}\r\n", name, text.replace(['\r', '\n'], " ")).as_bytes(),
+            ),
             Self::Error => Vec::new(),
         }
     }
+
+    /// Decodes `Frame` from bytes (without ending `b"\r\n"`).
+    ///
+    /// Unlike `From<&[u8]>` it reports why decoding failed.
+    pub fn decode(data: &[u8]) -> Result<Self, FrameParseError> {
+        let line = std::str::from_utf8(data).map_err(|_| FrameParseError::InvalidUtf8)?;
+        let data = line.split_whitespace().collect::<Vec<_>>();
+        let (command, args) = data.split_first().ok_or(FrameParseError::Empty)?;
+        let command = command.to_uppercase();
+
+        match (command.as_str(), args) {
+            ("REFRESH", [name]) => Ok(Frame::Message(BlockRefreshMessage::new(
+                String::from(*name),
+                BlockRunMode::Normal,
+            ))),
+            ("BUTTON", [button, name]) => {
+                let button = button
+                    .parse::<u8>()
+                    .map_err(|_| FrameParseError::InvalidArgument(String::from(*button)))?;
+                Ok(Frame::Message(BlockRefreshMessage::new(
+                    String::from(*name),
+                    BlockRunMode::Button(button),
+                )))
+            }
+            ("REFRESH_ALL", []) => Ok(Frame::RefreshAll),
+            ("LIST", []) => Ok(Frame::List),
+            ("GET", [name]) => Ok(Frame::Get(String::from(*name))),
+            ("STATUS", []) => Ok(Frame::Status),
+            ("SUBSCRIBE", []) => Ok(Frame::Subscribe { blocks: false }),
+            ("SUBSCRIBE", [blocks]) if blocks.eq_ignore_ascii_case("blocks") => {
+                Ok(Frame::Subscribe { blocks: true })
+            }
+            ("SUBSCRIBE", [argument]) => {
+                Err(FrameParseError::InvalidArgument(String::from(*argument)))
+            }
+            ("VERSION", [version]) => version
+                .parse::<u32>()
+                .map(Frame::Version)
+                .map_err(|_| FrameParseError::InvalidArgument(String::from(*version))),
+            ("INSERT", [index, name, interval, command, args @ ..]) => Ok(Frame::Insert {
+                index: decode_index(index)?,
+                name: String::from(*name),
+                interval: decode_interval(interval)?,
+                command: String::from(*command),
+                args: args.iter().map(|arg| String::from(*arg)).collect(),
+            }),
+            ("REMOVE", [name]) => Ok(Frame::Remove(String::from(*name))),
+            ("SET", [name, ..]) => Ok(Frame::Set {
+                name: String::from(*name),
+                text: String::from(skip_words(line, 2)),
+            }),
+            ("MOVE", [name, index]) => Ok(Frame::Move {
+                name: String::from(*name),
+                index: decode_index(index)?,
+            }),
+            ("CHANGE", [name, changes @ ..]) if !changes.is_empty() => {
+                let mut update = BlockUpdate::new();
+                let mut changes = changes;
+                while let Some((change, rest)) = changes.split_first() {
+                    match (change.to_uppercase().as_str(), rest) {
+                        ("INTERVAL", [interval, rest @ ..]) => {
+                            update.interval = Some(decode_interval(interval)?);
+                            changes = rest;
+                        }
+                        ("COMMAND", [command, args @ ..]) => {
+                            let args = args.iter().map(|arg| String::from(*arg)).collect();
+                            update.command = Some((String::from(*command), args));
+                            changes = &[];
+                        }
+                        ("INTERVAL" | "COMMAND", []) => {
+                            return Err(FrameParseError::InvalidArguments(command))
+                        }
+                        _ => return Err(FrameParseError::InvalidArgument(String::from(*change))),
+                    }
+                }
+                Ok(Frame::Change {
+                    name: String::from(*name),
+                    update,
+                })
+            }
+            (
+                "REFRESH" | "BUTTON" | "REFRESH_ALL" | "VERSION" | "LIST" | "GET" | "STATUS"
+                | "SUBSCRIBE" | "INSERT" | "REMOVE" | "MOVE" | "CHANGE" | "SET",
+                _,
+            ) => Err(FrameParseError::InvalidArguments(command)),
+            _ => Err(FrameParseError::UnknownCommand(command)),
+        }
+    }
+}
+
+/// Returns **line** without it's first `n` whitespace separated words
+/// (and surrounding whitespaces).
+fn skip_words(line: &str, n: usize) -> &str {
+    let mut rest = line.trim();
+    for _ in 0..n {
+        rest = rest.trim_start_matches(|c: char| !c.is_whitespace());
+        rest = rest.trim_start();
+    }
+    rest
+}
+
+/// Encodes block's refresh interval (`-` means no interval).
+fn encode_interval(interval: Option<u64>) -> String {
+    match interval {
+        Some(interval) => interval.to_string(),
+        None => String::from("-"),
+    }
+}
+
+/// Decodes block's refresh interval (which must be greater than 0).
+fn decode_interval(interval: &str) -> Result<Option<u64>, FrameParseError> {
+    match interval {
+        "-" => Ok(None),
+        interval => match interval.parse::<u64>() {
+            Ok(interval) if interval > 0 => Ok(Some(interval)),
+            _ => Err(FrameParseError::InvalidArgument(String::from(interval))),
+        },
+    }
+}
+
+/// Decodes position of a block.
+fn decode_index(index: &str) -> Result<usize, FrameParseError> {
+    index
+        .parse::<usize>()
+        .map_err(|_| FrameParseError::InvalidArgument(String::from(index)))
 }
 
 /// Creates `Frame` from byte stream. Used in decoding.
 impl From<&[u8]> for Frame {
     fn from(data: &[u8]) -> Self {
-        let data = match String::from_utf8(Vec::from(data)) {
-            Ok(data) => data,
-            Err(_) => return Frame::Error,
+        Self::decode(data).unwrap_or(Frame::Error)
+    }
+}
+
+/// Creates `Frame` from `BlockRefreshMessage`. Used in encoding.
+impl From<BlockRefreshMessage> for Frame {
+    fn from(msg: BlockRefreshMessage) -> Self {
+        Self::Message(msg)
+    }
+}
+
+/// Creates `Frame` from `StatusBarMessage`. Used in encoding.
+impl From<StatusBarMessage> for Frame {
+    fn from(msg: StatusBarMessage) -> Self {
+        match msg {
+            StatusBarMessage::Refresh(msg) => Self::Message(msg),
+            StatusBarMessage::RefreshAll => Self::RefreshAll,
+            StatusBarMessage::List => Self::List,
+            StatusBarMessage::Get(name) => Self::Get(name),
+            StatusBarMessage::Status => Self::Status,
+            StatusBarMessage::Subscribe { blocks } => Self::Subscribe { blocks },
+            // Configuration can't be reloaded over IPC.
+            StatusBarMessage::Reload(_) => Self::Error,
+            // Builtin blocks can't be sent over IPC.
+            StatusBarMessage::Insert { block, .. } if block.is_builtin() => Self::Error,
+            // Only block's command, it's arguments and interval are sent.
+            StatusBarMessage::Insert { index, name, block } => Self::Insert {
+                index,
+                name,
+                interval: block.interval().map(|i| i.as_secs()),
+                command: String::from(block.command()),
+                args: block.args().to_vec(),
+            },
+            StatusBarMessage::Remove(name) => Self::Remove(name),
+            StatusBarMessage::Move { name, index } => Self::Move { name, index },
+            StatusBarMessage::Update { name, update } => Self::Change { name, update },
+            StatusBarMessage::Set { name, text } => Self::Set { name, text },
+        }
+    }
+}
+
+/// Error sent back by a server in a [Response].
+#[derive(Debug, PartialEq, Clone)]
+pub enum ResponseError {
+    /// There is no block with given name.
+    UnknownBlock(String),
+    /// Block with given name already exists.
+    BlockExists(String),
+    /// Server failed to decode frame (holds a reason).
+    Parse(String),
+    /// Server doesn't support given protocol version.
+    UnsupportedVersion(u32),
+    /// Configuration is invalid (holds a reason).
+    InvalidConfig(String),
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Self::UnknownBlock(name) => format!("unknown block `{}`", name),
+            Self::BlockExists(name) => format!("block `{}` already exists", name),
+            Self::Parse(reason) => format!("server failed to parse request: {}", reason),
+            Self::UnsupportedVersion(version) => {
+                format!("unsupported protocol version {}", version)
+            }
+            Self::InvalidConfig(reason) => format!("invalid configuration: {}", reason),
         };
 
-        let data = data.split_whitespace().collect::<Vec<_>>();
-
-        match data.len() {
-            2 => {
-                if data[0].to_uppercase() == "REFRESH" {
-                    Frame::Message(BlockRefreshMessage::new(
-                        String::from(data[1]),
-                        BlockRunMode::Normal,
-                    ))
-                } else {
-                    Frame::Error
+        write!(f, "{}", msg)
+    }
+}
+
+impl Error for ResponseError {}
+
+/// Server's response to a single [Frame].
+#[derive(Debug, PartialEq, Clone)]
+pub enum Response {
+    /// Request was accepted.
+    Ok,
+    /// Requested value (block's result or status bar).
+    /// `None` means that block has not computed it's result yet.
+    Value(Option<String>),
+    /// List of blocks.
+    List(Vec<BlockInfo>),
+    /// Request failed.
+    Error(ResponseError),
+}
+
+impl Response {
+    /// Encodes `Response` into `Vec<u8>`.
+    ///
+    /// Most responses take a single line. `List` response starts with
+    /// a line containing number of blocks, followed by a line for each of them.
+    pub fn encode(&self) -> Vec<u8> {
+        let s = match self {
+            Self::Ok => String::from("OK\r\n"),
+            // Values must fit in a single line.
+            Self::Value(Some(value)) => format!("VALUE {}\r\n", value.replace(['\r', '\n'], " ")),
+            Self::Value(None) => String::from("NONE\r\n"),
+            Self::List(blocks) => {
+                let mut s = format!("LIST {}\r\n", blocks.len());
+                for block in blocks {
+                    let interval = match block.interval {
+                        Some(interval) => interval.to_string(),
+                        None => String::from("-"),
+                    };
+                    s.push_str(&format!(
+                        "BLOCK {} {} {}\r\n",
+                        block.name, interval, block.command
+                    ));
                 }
+                s
             }
-            3 => {
-                let num = data[1].parse::<u8>();
-                if data[0].to_uppercase() == "BUTTON" {
-                    if let Ok(num) = num {
-                        Frame::Message(BlockRefreshMessage::new(
-                            String::from(data[2]),
-                            BlockRunMode::Button(num),
-                        ))
-                    } else {
-                        Frame::Error
-                    }
-                } else {
-                    Frame::Error
+            Self::Error(ResponseError::UnknownBlock(name)) => {
+                format!("ERR unknown-block {}\r\n", name)
+            }
+            Self::Error(ResponseError::BlockExists(name)) => {
+                format!("ERR block-exists {}\r\n", name)
+            }
+            Self::Error(ResponseError::Parse(reason)) => format!("ERR parse {}\r\n", reason),
+            Self::Error(ResponseError::UnsupportedVersion(version)) => {
+                format!("ERR unsupported-version {}\r\n", version)
+            }
+            // Reason must fit in a single line.
+            Self::Error(ResponseError::InvalidConfig(reason)) => {
+                format!(
+                    "ERR invalid-config {}\r\n",
+                    reason.replace(['\r', '\n'], " ")
+                )
+            }
+        };
+        Vec::from(s.as_bytes())
+    }
+
+    /// Decodes single line `Response` from bytes (without ending `b"\r\n"`).
+    ///
+    /// `List` responses span multiple lines and must be decoded
+    /// with [decode_all](Response::decode_all).
+    pub fn decode(data: &[u8]) -> Result<Self, FrameParseError> {
+        let data = std::str::from_utf8(data).map_err(|_| FrameParseError::InvalidUtf8)?;
+        if let Some(value) = data.strip_prefix("VALUE ") {
+            return Ok(Self::Value(Some(String::from(value))));
+        }
+
+        let mut parts = data.trim().splitn(3, ' ');
+        let status = parts
+            .next()
+            .filter(|s| !s.is_empty())
+            .ok_or(FrameParseError::Empty)?;
+
+        match (status, parts.next(), parts.next()) {
+            ("OK", None, None) => Ok(Self::Ok),
+            ("NONE", None, None) => Ok(Self::Value(None)),
+            ("ERR", Some("unknown-block"), Some(name)) => {
+                Ok(Self::Error(ResponseError::UnknownBlock(String::from(name))))
+            }
+            ("ERR", Some("block-exists"), Some(name)) => {
+                Ok(Self::Error(ResponseError::BlockExists(String::from(name))))
+            }
+            ("ERR", Some("parse"), Some(reason)) => {
+                Ok(Self::Error(ResponseError::Parse(String::from(reason))))
+            }
+            ("ERR", Some("invalid-config"), Some(reason)) => Ok(Self::Error(
+                ResponseError::InvalidConfig(String::from(reason)),
+            )),
+            ("ERR", Some("unsupported-version"), Some(version)) => version
+                .parse::<u32>()
+                .map(|v| Self::Error(ResponseError::UnsupportedVersion(v)))
+                .map_err(|_| FrameParseError::InvalidArgument(String::from(version))),
+            ("OK" | "NONE" | "ERR" | "LIST", _, _) => {
+                Err(FrameParseError::InvalidArguments(String::from(status)))
+            }
+            _ => Err(FrameParseError::UnknownCommand(String::from(status))),
+        }
+    }
+
+    /// Decodes all `Response`s from byte stream.
+    pub fn decode_all(data: &[u8]) -> Result<Vec<Self>, FrameParseError> {
+        let mut responses = Vec::new();
+        let mut lines = SplitAtRN::new(data);
+        while let Some(line) = lines.next() {
+            let count = match line.strip_prefix(b"LIST ") {
+                Some(count) => std::str::from_utf8(count)
+                    .ok()
+                    .and_then(|c| c.parse::<usize>().ok())
+                    .ok_or_else(|| {
+                        FrameParseError::InvalidArgument(String::from_utf8_lossy(count).into())
+                    })?,
+                None => {
+                    responses.push(Self::decode(line)?);
+                    continue;
                 }
+            };
+
+            let blocks = lines
+                .by_ref()
+                .take(count)
+                .map(decode_block_info)
+                .collect::<Result<Vec<_>, _>>()?;
+            if blocks.len() != count {
+                return Err(FrameParseError::InvalidArguments(String::from("LIST")));
             }
-            _ => Frame::Error,
+            responses.push(Self::List(blocks));
+        }
+
+        Ok(responses)
+    }
+
+    /// Returns error held by this response (if any).
+    pub fn into_error(self) -> Option<ResponseError> {
+        match self {
+            Self::Error(err) => Some(err),
+            _ => None,
         }
     }
 }
 
-/// Creates `Frame` from `BlockRefreshMessage`. Used in encoding.
-impl From<BlockRefreshMessage> for Frame {
-    fn from(msg: BlockRefreshMessage) -> Self {
-        Self::Message(msg)
+/// Decodes a single line of `List` response.
+fn decode_block_info(data: &[u8]) -> Result<BlockInfo, FrameParseError> {
+    let data = std::str::from_utf8(data).map_err(|_| FrameParseError::InvalidUtf8)?;
+    let mut parts = data.splitn(4, ' ');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("BLOCK"), Some(name), Some(interval)) => {
+            let interval = match interval {
+                "-" => None,
+                interval => Some(
+                    interval
+                        .parse::<u64>()
+                        .map_err(|_| FrameParseError::InvalidArgument(String::from(interval)))?,
+                ),
+            };
+            Ok(BlockInfo {
+                name: String::from(name),
+                command: String::from(parts.next().unwrap_or_default()),
+                interval,
+            })
+        }
+        (Some("BLOCK"), _, _) => Err(FrameParseError::InvalidArguments(String::from("BLOCK"))),
+        (Some(command), _, _) if !command.is_empty() => {
+            Err(FrameParseError::UnknownCommand(String::from(command)))
+        }
+        _ => Err(FrameParseError::Empty),
+    }
+}
+
+/// Event streamed by a server to subscribed notifiers
+/// (after responding to [Frame::Subscribe]).
+#[derive(Debug, PartialEq, Clone)]
+pub enum Event {
+    /// New status bar was rendered.
+    Status(String),
+    /// Result of a block has changed.
+    Block {
+        /// Block's name.
+        name: String,
+        /// Block's new result (empty if block has no result).
+        result: String,
+    },
+}
+
+impl Event {
+    /// Encodes `Event` into `Vec<u8>`.
+    ///
+    /// Status bar and block's results must fit in a single line.
+    pub fn encode(&self) -> Vec<u8> {
+        let s = match self {
+            Self::Status(status) => format!("STATUS {}\r\n", status.replace(['\r', '\n'], " ")),
+            Self::Block { name, result } => {
+                format!("UPDATE {} {}\r\n", name, result.replace(['\r', '\n'], " "))
+            }
+        };
+        Vec::from(s.as_bytes())
+    }
+
+    /// Decodes `Event` from bytes (without ending `b"\r\n"`).
+    pub fn decode(data: &[u8]) -> Result<Self, FrameParseError> {
+        let data = std::str::from_utf8(data).map_err(|_| FrameParseError::InvalidUtf8)?;
+        if let Some(status) = data.strip_prefix("STATUS ") {
+            return Ok(Self::Status(String::from(status)));
+        }
+
+        let mut parts = data.splitn(3, ' ');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("UPDATE"), Some(name), result) if !name.is_empty() => Ok(Self::Block {
+                name: String::from(name),
+                result: String::from(result.unwrap_or_default()),
+            }),
+            (Some("STATUS"), None, None) => Ok(Self::Status(String::new())),
+            (Some("UPDATE"), _, _) => {
+                Err(FrameParseError::InvalidArguments(String::from("UPDATE")))
+            }
+            (Some(command), _, _) if !command.is_empty() => {
+                Err(FrameParseError::UnknownCommand(String::from(command)))
+            }
+            _ => Err(FrameParseError::Empty),
+        }
+    }
+}
+
+/// Creates `Event` from [StatusBar](crate::statusbar::StatusBar)'s event.
+impl From<StatusBarEvent> for Event {
+    fn from(event: StatusBarEvent) -> Self {
+        match event {
+            StatusBarEvent::Status(status) => Self::Status(status),
+            StatusBarEvent::Block { name, result } => Self::Block { name, result },
+        }
+    }
+}
+
+/// Creates `Response` from [StatusBar](crate::statusbar::StatusBar)'s response.
+impl From<StatusBarResponse> for Response {
+    fn from(response: StatusBarResponse) -> Self {
+        match response {
+            StatusBarResponse::Ok => Self::Ok,
+            StatusBarResponse::UnknownBlock(name) => Self::Error(ResponseError::UnknownBlock(name)),
+            StatusBarResponse::BlockExists(name) => Self::Error(ResponseError::BlockExists(name)),
+            StatusBarResponse::List(blocks) => Self::List(blocks),
+            StatusBarResponse::Result(result) => Self::Value(result),
+            StatusBarResponse::Status(status) => Self::Value(Some(status)),
+            // Events of subscription are streamed after this response.
+            StatusBarResponse::Subscribed(_) => Self::Ok,
+            StatusBarResponse::InvalidConfig(err) => {
+                Self::Error(ResponseError::InvalidConfig(err.to_string()))
+            }
+        }
     }
 }
 
@@ -194,6 +779,101 @@ impl From<&[u8]> for Frames {
     }
 }
 
+/// Incremental decoder of [Frame]s.
+///
+/// Unlike [Frames] it can be fed with data as it arrives (for example
+/// in chunks read from a socket). Partial frames are buffered until their
+/// ending `b"\r\n"` is received. Frames longer than `max_frame_size` are
+/// rejected (and the rest of them is skipped) without buffering them.
+///
+/// # Example
+/// ```
+/// use asyncdwmblocks::ipc::frame::{Frame, FrameDecoder};
+///
+/// let mut decoder = FrameDecoder::new(1024);
+/// decoder.extend(b"REFRESH_A");
+/// assert_eq!(decoder.next_frame(), None);
+///
+/// decoder.extend(b"LL\r\nLIST");
+/// assert_eq!(decoder.next_frame(), Some(Ok(Frame::RefreshAll)));
+/// assert_eq!(decoder.next_frame(), None);
+///
+/// // Stream has ended, so buffered data is the last frame.
+/// decoder.finish();
+/// assert_eq!(decoder.next_frame(), Some(Ok(Frame::List)));
+/// assert_eq!(decoder.next_frame(), None);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    max_frame_size: usize,
+    skipping: bool,
+    finished: bool,
+}
+
+impl FrameDecoder {
+    /// Creates new decoder accepting frames of at most **max_frame_size** bytes.
+    pub fn new(max_frame_size: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            max_frame_size,
+            skipping: false,
+            finished: false,
+        }
+    }
+
+    /// Adds received data to decoder.
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Marks end of stream. Buffered data (not ended with `b"\r\n"`)
+    /// will be decoded as the last frame.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Returns next decoded frame, or `None` if there is no complete frame yet.
+    pub fn next_frame(&mut self) -> Option<Result<Frame, FrameParseError>> {
+        loop {
+            let end = self.buffer.windows(2).position(|w| w == b"\r\n");
+            let line: Vec<u8> = match end {
+                Some(end) => {
+                    let line = self.buffer.drain(..end).collect();
+                    self.buffer.drain(..2);
+                    line
+                }
+                None if self.skipping => {
+                    // Keep last byte, it might be b'\r' of frame's ending.
+                    let keep = self.buffer.len().min(1);
+                    self.buffer.drain(..self.buffer.len() - keep);
+                    if self.finished {
+                        self.buffer.clear();
+                    }
+                    return None;
+                }
+                None if self.buffer.len() > self.max_frame_size => {
+                    self.buffer.clear();
+                    self.skipping = !self.finished;
+                    return Some(Err(FrameParseError::TooLong(self.max_frame_size)));
+                }
+                None if self.finished && !self.buffer.is_empty() => self.buffer.drain(..).collect(),
+                None => return None,
+            };
+
+            if self.skipping {
+                // This is an ending of a frame that was too long.
+                self.skipping = false;
+                continue;
+            }
+            if line.len() > self.max_frame_size {
+                return Some(Err(FrameParseError::TooLong(self.max_frame_size)));
+            }
+            return Some(Frame::decode(&line));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -311,6 +991,354 @@ mod tests {
         );
     }
 
+    #[test]
+    fn frame_decode_refresh_all() {
+        let frame1 = Frame::from(b"REFRESH_ALL".as_slice());
+        let frame2 = Frame::from(b" refresh_all\t".as_slice());
+        let frame3 = Frame::from(b"REFRESH_ALL block1".as_slice());
+        let frame4 = Frame::from(b"REFRESH".as_slice());
+
+        assert_eq!(frame1, Frame::RefreshAll);
+        assert_eq!(frame2, Frame::RefreshAll);
+        assert_eq!(frame3, Frame::Error);
+        assert_eq!(frame4, Frame::Error);
+    }
+
+    #[test]
+    fn frame_decode_version() {
+        let frame1 = Frame::from(b"VERSION 1".as_slice());
+        let frame2 = Frame::from(b"version 2".as_slice());
+        let frame3 = Frame::from(b"VERSION one".as_slice());
+
+        assert_eq!(frame1, Frame::Version(1));
+        assert_eq!(frame2, Frame::Version(2));
+        assert_eq!(frame3, Frame::Error);
+        assert_eq!(Frame::Version(1).encode(), b"VERSION 1\r\n".to_vec());
+    }
+
+    #[test]
+    fn frame_decode_queries() {
+        assert_eq!(Frame::from(b"LIST".as_slice()), Frame::List);
+        assert_eq!(Frame::from(b" status ".as_slice()), Frame::Status);
+        assert_eq!(
+            Frame::from(b"GET battery".as_slice()),
+            Frame::Get(String::from("battery"))
+        );
+        assert_eq!(Frame::from(b"GET".as_slice()), Frame::Error);
+        assert_eq!(Frame::from(b"LIST battery".as_slice()), Frame::Error);
+
+        assert_eq!(Frame::List.encode(), b"LIST\r\n".to_vec());
+        assert_eq!(Frame::Status.encode(), b"STATUS\r\n".to_vec());
+        assert_eq!(
+            Frame::Get(String::from("date")).encode(),
+            b"GET date\r\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn frame_decode_subscribe() {
+        assert_eq!(
+            Frame::from(b"SUBSCRIBE".as_slice()),
+            Frame::Subscribe { blocks: false }
+        );
+        assert_eq!(
+            Frame::from(b"subscribe blocks".as_slice()),
+            Frame::Subscribe { blocks: true }
+        );
+        assert_eq!(
+            Frame::decode(b"SUBSCRIBE everything".as_slice()),
+            Err(FrameParseError::InvalidArgument(String::from("everything")))
+        );
+        assert_eq!(
+            Frame::decode(b"SUBSCRIBE blocks now".as_slice()),
+            Err(FrameParseError::InvalidArguments(String::from("SUBSCRIBE")))
+        );
+
+        assert_eq!(
+            Frame::Subscribe { blocks: false }.encode(),
+            b"SUBSCRIBE\r\n".to_vec()
+        );
+        assert_eq!(
+            Frame::Subscribe { blocks: true }.encode(),
+            b"SUBSCRIBE BLOCKS\r\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn frame_decode_block_changes() {
+        let frames = vec![
+            Frame::Insert {
+                index: 0,
+                name: String::from("recording"),
+                interval: None,
+                command: String::from("echo"),
+                args: vec![String::from("REC")],
+            },
+            Frame::Insert {
+                index: 2,
+                name: String::from("date"),
+                interval: Some(60),
+                command: String::from("date"),
+                args: Vec::new(),
+            },
+            Frame::Remove(String::from("recording")),
+            Frame::Move {
+                name: String::from("date"),
+                index: 1,
+            },
+            Frame::Change {
+                name: String::from("date"),
+                update: BlockUpdate::new().with_interval(None),
+            },
+            Frame::Change {
+                name: String::from("date"),
+                update: BlockUpdate::new()
+                    .with_interval(Some(1))
+                    .with_command(String::from("date"), vec![String::from("+%T")]),
+            },
+        ];
+
+        for frame in frames {
+            let data = frame.encode();
+            let data = &data[..data.len() - 2];
+            assert_eq!(Frame::decode(data), Ok(frame));
+        }
+
+        assert_eq!(
+            Frame::from(b"change date command date -u interval 5".as_slice()),
+            Frame::Change {
+                name: String::from("date"),
+                update: BlockUpdate::new().with_command(
+                    String::from("date"),
+                    vec![
+                        String::from("-u"),
+                        String::from("interval"),
+                        String::from("5")
+                    ]
+                ),
+            }
+        );
+        assert_eq!(
+            Frame::decode(b"INSERT 0 date 0 date".as_slice()),
+            Err(FrameParseError::InvalidArgument(String::from("0")))
+        );
+        assert_eq!(
+            Frame::decode(b"INSERT first date - date".as_slice()),
+            Err(FrameParseError::InvalidArgument(String::from("first")))
+        );
+        assert_eq!(
+            Frame::decode(b"INSERT 0 date -".as_slice()),
+            Err(FrameParseError::InvalidArguments(String::from("INSERT")))
+        );
+        assert_eq!(
+            Frame::decode(b"CHANGE date".as_slice()),
+            Err(FrameParseError::InvalidArguments(String::from("CHANGE")))
+        );
+        assert_eq!(
+            Frame::decode(b"CHANGE date INTERVAL".as_slice()),
+            Err(FrameParseError::InvalidArguments(String::from("CHANGE")))
+        );
+        assert_eq!(
+            Frame::decode(b"CHANGE date TIMEOUT 5".as_slice()),
+            Err(FrameParseError::InvalidArgument(String::from("TIMEOUT")))
+        );
+    }
+
+    #[test]
+    fn frame_decode_set() {
+        assert_eq!(
+            Frame::from(b"SET mail  3 new \t".as_slice()),
+            Frame::Set {
+                name: String::from("mail"),
+                text: String::from("3 new"),
+            }
+        );
+        assert_eq!(
+            Frame::from(b"set mail".as_slice()),
+            Frame::Set {
+                name: String::from("mail"),
+                text: String::new(),
+            }
+        );
+        assert_eq!(
+            Frame::decode(b"SET".as_slice()),
+            Err(FrameParseError::InvalidArguments(String::from("SET")))
+        );
+
+        let frame = Frame::Set {
+            name: String::from("mail"),
+            text: String::from("3 new\n(1 important)"),
+        };
+        assert_eq!(frame.encode(), b"SET mail 3 new (1 important)\r\n".to_vec());
+    }
+
+    #[test]
+    fn event_encode_decode() {
+        let events = vec![
+            Event::Status(String::from("85% | 12:00")),
+            Event::Status(String::new()),
+            Event::Block {
+                name: String::from("date"),
+                result: String::from("12:00 Mon"),
+            },
+            Event::Block {
+                name: String::from("battery"),
+                result: String::new(),
+            },
+        ];
+
+        for event in events {
+            let data = event.encode();
+            let data = &data[..data.len() - 2];
+            assert_eq!(Event::decode(data), Ok(event));
+        }
+
+        assert_eq!(
+            Event::Status(String::from("a\nb")).encode(),
+            b"STATUS a b\r\n".to_vec()
+        );
+        assert!(Event::decode(b"UPDATE".as_slice()).is_err());
+        assert!(Event::decode(b"OK".as_slice()).is_err());
+    }
+
+    #[test]
+    fn frame_decoder_partial_frames() {
+        let mut decoder = FrameDecoder::new(64);
+        decoder.extend(b"REFRESH da");
+        assert_eq!(decoder.next_frame(), None);
+        decoder.extend(b"te\r");
+        assert_eq!(decoder.next_frame(), None);
+        decoder.extend(b"\nBUTTON 1 battery\r\nRELOAD\r\nSTA");
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Ok(Frame::Message(BlockRefreshMessage::new(
+                String::from("date"),
+                BlockRunMode::Normal
+            ))))
+        );
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Ok(Frame::Message(BlockRefreshMessage::new(
+                String::from("battery"),
+                BlockRunMode::Button(1)
+            ))))
+        );
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Err(FrameParseError::UnknownCommand(String::from("RELOAD"))))
+        );
+        assert_eq!(decoder.next_frame(), None);
+
+        decoder.extend(b"TUS");
+        decoder.finish();
+        assert_eq!(decoder.next_frame(), Some(Ok(Frame::Status)));
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn frame_decoder_too_long() {
+        let mut decoder = FrameDecoder::new(16);
+        decoder.extend(b"REFRESH a_very_long_block_name");
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Err(FrameParseError::TooLong(16)))
+        );
+        assert_eq!(decoder.next_frame(), None);
+
+        // Rest of too long frame is skipped.
+        decoder.extend(b"_that_goes_on_and_on\r");
+        assert_eq!(decoder.next_frame(), None);
+        decoder.extend(b"\nLIST\r\nGET a_long_block_name\r\nLIST\r\n");
+        assert_eq!(decoder.next_frame(), Some(Ok(Frame::List)));
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Err(FrameParseError::TooLong(16)))
+        );
+        assert_eq!(decoder.next_frame(), Some(Ok(Frame::List)));
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn frame_decode_errors() {
+        assert_eq!(
+            Frame::decode(b"REFRESH\xf0\x90\x28\xbc block_id".as_slice()),
+            Err(FrameParseError::InvalidUtf8)
+        );
+        assert_eq!(Frame::decode(b" ".as_slice()), Err(FrameParseError::Empty));
+        assert_eq!(
+            Frame::decode(b"reload block".as_slice()),
+            Err(FrameParseError::UnknownCommand(String::from("RELOAD")))
+        );
+        assert_eq!(
+            Frame::decode(b"refresh block1 block2".as_slice()),
+            Err(FrameParseError::InvalidArguments(String::from("REFRESH")))
+        );
+        assert_eq!(
+            Frame::decode(b"BUTTON 1024 block".as_slice()),
+            Err(FrameParseError::InvalidArgument(String::from("1024")))
+        );
+    }
+
+    #[test]
+    fn response_encode_decode() {
+        let responses = vec![
+            Response::Ok,
+            Response::Error(ResponseError::UnknownBlock(String::from("battery"))),
+            Response::Error(ResponseError::BlockExists(String::from("battery"))),
+            Response::Error(ResponseError::Parse(String::from("unknown command RELOAD"))),
+            Response::Error(ResponseError::UnsupportedVersion(2)),
+            Response::Error(ResponseError::InvalidConfig(String::from(
+                "Each block id should be unique",
+            ))),
+        ];
+
+        for response in responses {
+            let data = response.encode();
+            let data = &data[..data.len() - 2];
+            assert_eq!(Response::decode(data), Ok(response));
+        }
+
+        assert_eq!(Response::Ok.encode(), b"OK\r\n".to_vec());
+        assert_eq!(
+            Response::Error(ResponseError::UnknownBlock(String::from("batery"))).encode(),
+            b"ERR unknown-block batery\r\n".to_vec()
+        );
+        assert!(Response::decode(b"ERR".as_slice()).is_err());
+        assert!(Response::decode(b"YES".as_slice()).is_err());
+    }
+
+    #[test]
+    fn response_encode_decode_queries() {
+        let responses = vec![
+            Response::Value(Some(String::from("12:00 | 85%"))),
+            Response::Value(None),
+            Response::List(vec![
+                BlockInfo {
+                    name: String::from("date"),
+                    command: String::from("date +%H:%M"),
+                    interval: Some(60),
+                },
+                BlockInfo {
+                    name: String::from("volume"),
+                    command: String::from("pamixer --get-volume"),
+                    interval: None,
+                },
+            ]),
+            Response::List(Vec::new()),
+            Response::Ok,
+        ];
+
+        let data: Vec<u8> = responses.iter().flat_map(Response::encode).collect();
+        assert_eq!(Response::decode_all(&data), Ok(responses));
+
+        assert_eq!(
+            Response::Value(Some(String::from("a\nb"))).encode(),
+            b"VALUE a b\r\n".to_vec()
+        );
+        assert_eq!(Response::Value(None).encode(), b"NONE\r\n".to_vec());
+        assert!(Response::decode_all(b"LIST 2\r\nBLOCK date - date\r\n".as_slice()).is_err());
+    }
+
     #[test]
     fn frame_decode_button() {
         let frame = Frame::from(b"button 1 block1".as_slice());
@@ -418,7 +1446,13 @@ mod tests {
             BlockRunMode::Button(2),
         ));
 
+        let refresh_all = Frame::RefreshAll;
+
         assert_eq!(empty.encode(), Vec::<u8>::new());
+        assert_eq!(
+            refresh_all.encode(),
+            Vec::from("REFRESH_ALL\r\n".as_bytes())
+        );
         assert_eq!(normal.encode(), Vec::from("REFRESH date\r\n".as_bytes()));
         assert_eq!(
             button1.encode(),
@@ -5,8 +5,11 @@
 //! Notifier (created by implementing [`Notifier`] trait).
 //! Server is used to listen (through network, pipe, bus or any
 //! other IPC method) for requests on refresh blocks and then passes
-//! them out through a channel. Notifier is on the other hand used
-//! to sent those requests (from another processes).
+//! them out (as [`StatusBarRequest`]s) through a channel. Notifier is
+//! on the other hand used to sent those requests (from another processes).
+//! Server answers every request with a [`Response`](frame::Response),
+//! which notifier reports back. Notifiers can also [subscribe](Notifier::subscribe)
+//! to status bar's [`Event`](frame::Event)s, which server streams over kept open connection.
 //!
 //! Sent messages are streams of bytes. Translation between them
 //! and (in this case) [`BlockRefreshMessage`] and vice versa is
@@ -24,15 +27,26 @@ pub mod uds;
 
 use std::error::Error;
 use std::fmt;
+use std::io;
+use std::iter;
+use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 #[cfg(feature = "config-file")]
 use serde::Deserialize;
-use tokio::io::{AsyncRead, AsyncReadExt};
-use tokio::sync::mpsc;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time;
 
-use crate::statusbar::BlockRefreshMessage;
-use frame::{Frame, Frames};
+use crate::block::Block;
+use crate::config::Config;
+use crate::statusbar::{
+    BlockRefreshMessage, StatusBarMessage, StatusBarRequest, StatusBarResponse,
+};
+use frame::{Event, Frame, FrameDecoder, Frames, Response, ResponseError, PROTOCOL_VERSION};
 
 pub use opaque::{OpaqueNotifier, OpaqueServer};
 
@@ -48,23 +62,38 @@ pub trait Server {
 
 /// This trait defines public API for notifiers.
 ///
-/// Notifiers store messages in queue by calling
-/// [push_message](Notifier::push_message) and then send them all
-/// at once by calling [send_messages](Notifier::send_messages).
+/// Notifiers store frames in queue by calling
+/// [push_frame](Notifier::push_frame) (or [push_message](Notifier::push_message))
+/// and then send them all at once by calling [send_messages](Notifier::send_messages).
 #[async_trait]
 pub trait Notifier {
     /// Notifier error type.
     type Error: Error;
 
+    /// Add frame for sending.
+    fn push_frame(&mut self, frame: Frame);
+
     /// Add message for sending.
-    fn push_message(&mut self, message: BlockRefreshMessage);
+    fn push_message(&mut self, message: BlockRefreshMessage) {
+        self.push_frame(Frame::from(message));
+    }
 
     /// Send all stored messages.
     ///
     /// This method consumes notifier, because it is no longer needed.
     /// All messages should be batched together to avoid opening
-    /// connections multiple times.
-    async fn send_messages(self) -> Result<(), Self::Error>;
+    /// connections multiple times. Returns server's responses to all
+    /// pushed frames (in order). If server responded with an error
+    /// to any of them, then this error is returned instead.
+    async fn send_messages(self) -> Result<Vec<Response>, Self::Error>;
+
+    /// Send all stored messages and subscribe to status bar's [Event]s.
+    ///
+    /// Like [send_messages](Notifier::send_messages), but connection is kept open
+    /// and [Event]s streamed by server are sent through returned channel. If **blocks**
+    /// is `true` then changes of single blocks are received as well. Channel is
+    /// closed when server ends connection.
+    async fn subscribe(self, blocks: bool) -> Result<mpsc::Receiver<Event>, Self::Error>;
 }
 
 /// Type of server and notifier.
@@ -103,44 +132,278 @@ impl fmt::Display for ServerType {
     }
 }
 
+/// Response to a frame that is either ready or awaited from the status bar.
+enum PendingResponse {
+    Ready(Response),
+    Waiting(oneshot::Receiver<StatusBarResponse>),
+}
+
 /// Universal (for `Server`s method to handle streams).
-async fn handle_server_stream<S: AsyncRead + Unpin>(
+///
+/// Data is read from **stream** until it ends, or nothing is received for
+/// [idle_timeout](crate::config::ConfigIpc::idle_timeout). Frames are decoded
+/// incrementally (so they can be split across reads) and every one of them
+/// is answered with a [Response]. Requests from each read are first all
+/// passed to the status bar and then their responses are written back in order.
+///
+/// After subscribing, connection only streams status bar's events.
+async fn handle_server_stream<S: AsyncRead + AsyncWrite + Unpin>(
     mut stream: S,
-    message_sender: mpsc::Sender<BlockRefreshMessage>,
+    message_sender: mpsc::Sender<StatusBarRequest>,
     cancelation_sender: mpsc::Sender<()>,
+    config: Arc<Config>,
 ) {
+    let mut decoder = FrameDecoder::new(config.ipc.max_frame_size);
+    let idle_timeout = config.ipc.idle_timeout.map(Duration::from_secs);
     let mut buffer = [0u8; 1024];
-    let nbytes = match stream.read(&mut buffer).await {
-        Ok(n) => {
-            if n == 0 {
-                // Don't analyse empty stream
+    let mut subscription = None;
+
+    while subscription.is_none() {
+        let read = stream.read(&mut buffer);
+        let nbytes = match idle_timeout {
+            Some(idle_timeout) => match time::timeout(idle_timeout, read).await {
+                Ok(read) => read,
+                // Nothing was received for too long, end connection.
+                Err(_) => return,
+            },
+            None => read.await,
+        };
+        let nbytes = match nbytes {
+            Ok(n) => n,
+            // There is nothing we could do, end connection.
+            Err(_) => return,
+        };
+
+        let finished = nbytes == 0;
+        if finished {
+            decoder.finish();
+        } else {
+            decoder.extend(&buffer[..nbytes]);
+        }
+
+        let mut pending = Vec::new();
+        while let Some(frame) = decoder.next_frame() {
+            let msg = match frame {
+                Ok(Frame::Message(msg)) => StatusBarMessage::Refresh(msg),
+                Ok(Frame::RefreshAll) => StatusBarMessage::RefreshAll,
+                Ok(Frame::List) => StatusBarMessage::List,
+                Ok(Frame::Get(name)) => StatusBarMessage::Get(name),
+                Ok(Frame::Status) => StatusBarMessage::Status,
+                Ok(Frame::Subscribe { blocks }) => StatusBarMessage::Subscribe { blocks },
+                Ok(Frame::Insert {
+                    index,
+                    name,
+                    interval,
+                    command,
+                    args,
+                }) => StatusBarMessage::Insert {
+                    index,
+                    name,
+                    // Frame::decode checks that interval is greater than 0.
+                    block: Box::new(Block::new(command, args, interval, Arc::clone(&config))),
+                },
+                Ok(Frame::Remove(name)) => StatusBarMessage::Remove(name),
+                Ok(Frame::Move { name, index }) => StatusBarMessage::Move { name, index },
+                Ok(Frame::Change { name, update }) => StatusBarMessage::Update { name, update },
+                Ok(Frame::Set { name, text }) => StatusBarMessage::Set { name, text },
+                Ok(Frame::Version(version)) => {
+                    let response = if version == PROTOCOL_VERSION {
+                        Response::Ok
+                    } else {
+                        Response::Error(ResponseError::UnsupportedVersion(version))
+                    };
+                    pending.push(PendingResponse::Ready(response));
+                    continue;
+                }
+                // Frame::decode never returns Frame::Error.
+                Ok(Frame::Error) => continue,
+                Err(err) => {
+                    let response = Response::Error(ResponseError::Parse(err.to_string()));
+                    pending.push(PendingResponse::Ready(response));
+                    continue;
+                }
+            };
+
+            let (request, response_receiver) = StatusBarRequest::with_reply(msg);
+            // Receiving channel was closed, so there is no point in sending this
+            // frame, any of this frames and accept new connections, since whoever
+            // is listening to us has stopped doing it. Send signal to self to stop running.
+            if message_sender.send(request).await.is_err() {
+                // If receiving channel is closed that means that another task
+                // has already sent termination message and it was enforced.
+                // So it doesn't matter that we failed.
+                let _ = cancelation_sender.send(()).await;
+                // Don't try to send next messages. End this task.
                 return;
             }
-            n
+            pending.push(PendingResponse::Waiting(response_receiver));
         }
-        // There is nothing we could do, end connection.
-        Err(_) => return,
-    };
-    let frames = Frames::from(&buffer[..nbytes]);
-    for frame in frames {
-        match frame {
-            Frame::Message(msg) => {
-                // Receiving channel was closed, so there is no point in sending this
-                // frame, any of this frames and accept new connections, since whoever
-                // is listening to us has stopped doing it. Send signal to self to stop running.
-                if message_sender.send(msg).await.is_err() {
-                    // If receiving channel is closed that means that another task
-                    // has already sent termination message and it was enforced.
-                    // So it doesn't matter that we failed.
-                    let _ = cancelation_sender.send(()).await;
-                    // Don't try to send next messages. End this task.
-                    break;
-                }
+
+        for response in pending {
+            let response = match response {
+                PendingResponse::Ready(response) => response,
+                PendingResponse::Waiting(receiver) => match receiver.await {
+                    Ok(StatusBarResponse::Subscribed(s)) => {
+                        subscription = Some(s);
+                        Response::Ok
+                    }
+                    Ok(response) => Response::from(response),
+                    // Status bar stopped running before responding.
+                    Err(_) => return,
+                },
+            };
+
+            if stream.write_all(&response.encode()).await.is_err() {
+                // Notifier doesn't wait for responses.
+                return;
+            }
+        }
+
+        if finished {
+            break;
+        }
+    }
+
+    // Keep connection open and stream events until either
+    // status bar stops running or notifier disconnects.
+    if let Some(mut subscription) = subscription {
+        while let Some(event) = subscription.recv().await {
+            if stream
+                .write_all(&Event::from(event).encode())
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+}
+
+/// Universal (for `Notifier`s) method to send frames and receive responses.
+///
+/// **frames** are preceded by a [Version](Frame::Version) frame. After
+/// sending them writing half of the stream is shut down and server's
+/// [Response]s are read (one for every sent frame, including version).
+async fn handle_notifier_stream<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    frames: Vec<Frame>,
+) -> io::Result<Vec<Response>> {
+    let frames: Frames = iter::once(Frame::Version(PROTOCOL_VERSION))
+        .chain(frames.into_iter().filter(|f| *f != Frame::Error))
+        .collect();
+    let expected_responses = frames.clone().into_iter().count();
+
+    stream.write_all(frames.encode().as_slice()).await?;
+    stream.shutdown().await?;
+
+    let mut buffer = Vec::new();
+    stream.read_to_end(&mut buffer).await?;
+
+    let responses = Response::decode_all(&buffer)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    if responses.len() != expected_responses {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "server did not respond to all requests",
+        ));
+    }
+
+    Ok(responses)
+}
+
+/// Universal (for `Notifier`s) method to subscribe to server's events.
+///
+/// **frames** are sent like in [handle_notifier_stream], but followed by a
+/// [Subscribe](Frame::Subscribe) frame. After reading responses to all of them
+/// (including version and subscription) a new task is spawned, that sends every
+/// received [Event] through returned channel. Channel is closed when server
+/// ends connection.
+async fn handle_subscriber_stream<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+    mut stream: S,
+    frames: Vec<Frame>,
+    blocks: bool,
+) -> io::Result<(Vec<Response>, mpsc::Receiver<Event>)> {
+    let frames: Frames = iter::once(Frame::Version(PROTOCOL_VERSION))
+        .chain(frames.into_iter().filter(|f| *f != Frame::Error))
+        .chain(iter::once(Frame::Subscribe { blocks }))
+        .collect();
+    let expected_responses = frames.clone().into_iter().count();
+
+    stream.write_all(frames.encode().as_slice()).await?;
+    stream.flush().await?;
+
+    let mut stream = BufReader::new(stream);
+    let mut responses = Vec::with_capacity(expected_responses);
+    while responses.len() < expected_responses {
+        let mut data = read_line(&mut stream).await?;
+        // List responses span multiple lines.
+        let count = data
+            .strip_prefix(b"LIST ")
+            .and_then(|c| std::str::from_utf8(c).ok())
+            .and_then(|c| c.parse::<usize>().ok())
+            .unwrap_or_default();
+        data.extend_from_slice(b"\r\n");
+        for _ in 0..count {
+            data.append(&mut read_line(&mut stream).await?);
+            data.extend_from_slice(b"\r\n");
+        }
+
+        let response = Response::decode_all(&data)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        responses.extend(response);
+    }
+
+    let (sender, receiver) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Ok(data) = read_line(&mut stream).await {
+            let event = match Event::decode(&data) {
+                Ok(event) => event,
+                // Server sent something unexpected. End connection.
+                Err(_) => break,
+            };
+            if sender.send(event).await.is_err() {
+                // Nobody listens to events anymore.
+                break;
             }
-            // We do not currently report back weather
-            // parsing or execution were successful or not,
-            // so for now we silently ignore any errors.
-            Frame::Error => continue,
         }
+    });
+
+    Ok((responses, receiver))
+}
+
+/// Reads a single line (without ending `b"\r\n"`) from **reader**.
+///
+/// Returns `UnexpectedEof` error if stream has ended.
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line).await? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "server ended connection",
+        ));
     }
+
+    if line.ends_with(b"\n") {
+        line.pop();
+    }
+    if line.ends_with(b"\r") {
+        line.pop();
+    }
+    Ok(line)
+}
+
+/// Checks server's responses (read by [handle_notifier_stream]) for errors.
+///
+/// Returns first error, or responses to frames given to `handle_notifier_stream`
+/// (without response to version frame).
+fn check_responses(responses: Vec<Response>) -> Result<Vec<Response>, ResponseError> {
+    if let Some(err) = responses.iter().find_map(|r| match r {
+        Response::Error(err) => Some(err.clone()),
+        _ => None,
+    }) {
+        return Err(err);
+    }
+
+    Ok(responses.into_iter().skip(1).collect())
 }
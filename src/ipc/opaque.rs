@@ -66,9 +66,10 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use tokio::sync::{broadcast, mpsc};
 
+use super::frame::{Event, Frame, Response};
 use super::{Notifier, Server, ServerType};
 use crate::config::Config;
-use crate::statusbar::BlockRefreshMessage;
+use crate::statusbar::StatusBarRequest;
 
 #[cfg(feature = "tcp")]
 use super::tcp;
@@ -120,7 +121,7 @@ impl OpaqueServer {
     /// a receiver for process termination by a signal.
     #[allow(unused_variables)] // In some features combination some input parameters won't be used
     pub fn new(
-        sender: mpsc::Sender<BlockRefreshMessage>,
+        sender: mpsc::Sender<StatusBarRequest>,
         termination_signal_receiver: broadcast::Receiver<()>,
         config: Arc<Config>,
     ) -> Self {
@@ -208,16 +209,16 @@ impl OpaqueNotifier {
 impl Notifier for OpaqueNotifier {
     type Error = OpaqueNotifierError;
 
-    fn push_message(&mut self, message: BlockRefreshMessage) {
+    fn push_frame(&mut self, frame: Frame) {
         match self {
             #[cfg(feature = "tcp")]
-            Self::Tcp(notifier) => notifier.push_message(message),
+            Self::Tcp(notifier) => notifier.push_frame(frame),
             #[cfg(feature = "uds")]
-            Self::UnixDomainSocket(notifier) => notifier.push_message(message),
+            Self::UnixDomainSocket(notifier) => notifier.push_frame(frame),
         }
     }
 
-    async fn send_messages(self) -> Result<(), Self::Error> {
+    async fn send_messages(self) -> Result<Vec<Response>, Self::Error> {
         match self {
             #[cfg(feature = "tcp")]
             Self::Tcp(notifier) => notifier.send_messages().await.map_err(Self::Error::from),
@@ -227,6 +228,17 @@ impl Notifier for OpaqueNotifier {
             }
         }
     }
+
+    async fn subscribe(self, blocks: bool) -> Result<mpsc::Receiver<Event>, Self::Error> {
+        match self {
+            #[cfg(feature = "tcp")]
+            Self::Tcp(notifier) => notifier.subscribe(blocks).await.map_err(Self::Error::from),
+            #[cfg(feature = "uds")]
+            Self::UnixDomainSocket(notifier) => {
+                notifier.subscribe(blocks).await.map_err(Self::Error::from)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -236,7 +248,8 @@ mod tests {
     use crate::{
         block::BlockRunMode,
         config,
-        ipc::frame::{Frame, Frames},
+        ipc::frame::{Frame, Frames, Response, PROTOCOL_VERSION},
+        statusbar::{BlockRefreshMessage, StatusBarMessage},
     };
     use chrono::{DateTime, Utc};
     use std::fs;
@@ -257,7 +270,11 @@ mod tests {
                 BlockRefreshMessage::new("block3".into(), BlockRunMode::Button(3)),
                 BlockRefreshMessage::new("block4".into(), BlockRunMode::Button(4)),
             ];
-            let expected_messages = messages.clone();
+            let expected_messages: Vec<StatusBarMessage> = messages
+                .iter()
+                .cloned()
+                .map(StatusBarMessage::from)
+                .collect();
 
             let mut server =
                 OpaqueServer::new(sender, termination_signal_receiver, Arc::clone(&$config));
@@ -274,10 +291,10 @@ mod tests {
                 stream.write_all(data.as_slice()).await.unwrap();
             });
 
-            assert_eq!(receiver.recv().await.unwrap(), expected_messages[0]);
-            assert_eq!(receiver.recv().await.unwrap(), expected_messages[1]);
-            assert_eq!(receiver.recv().await.unwrap(), expected_messages[2]);
-            assert_eq!(receiver.recv().await.unwrap(), expected_messages[3]);
+            assert_eq!(receiver.recv().await.unwrap().message, expected_messages[0]);
+            assert_eq!(receiver.recv().await.unwrap().message, expected_messages[1]);
+            assert_eq!(receiver.recv().await.unwrap().message, expected_messages[2]);
+            assert_eq!(receiver.recv().await.unwrap().message, expected_messages[3]);
         };
     }
 
@@ -289,10 +306,12 @@ mod tests {
                 BlockRefreshMessage::new("block3".into(), BlockRunMode::Button(3)),
                 BlockRefreshMessage::new("block4".into(), BlockRunMode::Button(4)),
             ];
-            let expected_messages: Frames = messages.clone().into_iter().map(Frame::from).collect();
+            let expected_messages: Frames = std::iter::once(Frame::Version(PROTOCOL_VERSION))
+                .chain(messages.clone().into_iter().map(Frame::from))
+                .collect();
 
             let mut notifier = OpaqueNotifier::new(Arc::clone(&$config));
-            tokio::spawn(async move {
+            let notifier_handle = tokio::spawn(async move {
                 for message in messages {
                     notifier.push_message(message);
                 }
@@ -303,8 +322,14 @@ mod tests {
             let (mut stream, _) = $listener.accept().await.unwrap();
             stream.read_to_end(&mut buff).await.unwrap();
             let frames = Frames::from(buff.as_slice());
+            stream
+                .write_all(&Response::Ok.encode().repeat(5))
+                .await
+                .unwrap();
+            drop(stream);
 
             assert_eq!(frames, expected_messages);
+            notifier_handle.await.unwrap();
         };
     }
 
@@ -398,7 +423,7 @@ mod tests {
         }
         .arc();
 
-        opaque_notifier!(config, UnixListener::bind(&config.ipc.uds.addr()).unwrap());
+        opaque_notifier!(config, UnixListener::bind(config.ipc.uds.addr()).unwrap());
 
         fs::remove_file(&config.ipc.uds.addr).unwrap();
     }
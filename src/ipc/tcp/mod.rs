@@ -8,17 +8,86 @@ pub mod server;
 pub use notifier::TcpNotifier;
 pub use server::TcpServer;
 
-use super::{frame, handle_server_stream, Notifier, Server};
+use super::{
+    check_responses, frame, handle_notifier_stream, handle_server_stream, handle_subscriber_stream,
+    Notifier, Server,
+};
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::block::BlockRunMode;
     use crate::config::{self, Config};
+    use crate::ipc::frame::Event;
     use crate::ipc::ServerType;
-    use crate::statusbar::BlockRefreshMessage;
+    use crate::statusbar::{
+        BlockRefreshMessage, StatusBarEvent, StatusBarMessage, StatusBarResponse,
+        StatusBarSubscription,
+    };
     use std::sync::Arc;
-    use tokio::sync::mpsc;
+    use tokio::sync::{broadcast, mpsc};
+    use tokio::time;
+
+    #[tokio::test]
+    async fn server_and_subscriber() {
+        let config = Config {
+            ipc: config::ConfigIpc {
+                server_type: ServerType::Tcp,
+                tcp: config::ConfigIpcTcp { port: 44008 },
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+
+        let (sender, mut receiver) = mpsc::channel(8);
+        let mut server = TcpServer::new(sender, Arc::clone(&config));
+        tokio::spawn(async move {
+            server.run().await.unwrap();
+        });
+
+        // Wait for server to bind.
+        time::sleep(time::Duration::from_millis(100)).await;
+        let notifier = TcpNotifier::new(Arc::clone(&config));
+        let notifier_handle = tokio::spawn(async move { notifier.subscribe(true).await.unwrap() });
+
+        let request = receiver.recv().await.unwrap();
+        assert_eq!(
+            request.message,
+            StatusBarMessage::Subscribe { blocks: true }
+        );
+        let (events, _) = broadcast::channel(8);
+        let subscription = StatusBarSubscription::new("a".into(), true, events.subscribe());
+        request.respond(StatusBarResponse::Subscribed(subscription));
+
+        let mut subscriber = notifier_handle.await.unwrap();
+        assert_eq!(subscriber.recv().await, Some(Event::Status("a".into())));
+
+        events
+            .send(StatusBarEvent::Block {
+                name: "date".into(),
+                result: "12:00".into(),
+            })
+            .unwrap();
+        events
+            .send(StatusBarEvent::Status("a | 12:00".into()))
+            .unwrap();
+        assert_eq!(
+            subscriber.recv().await,
+            Some(Event::Block {
+                name: "date".into(),
+                result: "12:00".into()
+            })
+        );
+        assert_eq!(
+            subscriber.recv().await,
+            Some(Event::Status("a | 12:00".into()))
+        );
+
+        // Status bar stopped running, so server ends connection.
+        drop(events);
+        assert_eq!(subscriber.recv().await, None);
+    }
 
     #[tokio::test]
     async fn server_and_notifier() {
@@ -39,7 +108,11 @@ mod tests {
             BlockRefreshMessage::new("block3".into(), BlockRunMode::Button(3)),
             BlockRefreshMessage::new("block4".into(), BlockRunMode::Button(4)),
         ];
-        let expected_messages = messages.clone();
+        let expected_messages: Vec<StatusBarMessage> = messages
+            .iter()
+            .cloned()
+            .map(StatusBarMessage::from)
+            .collect();
 
         let mut server = TcpServer::new(sender, Arc::clone(&config));
         tokio::spawn(async move {
@@ -47,16 +120,18 @@ mod tests {
         });
 
         let mut notifier = TcpNotifier::new(Arc::clone(&config));
-        tokio::spawn(async move {
+        let notifier_handle = tokio::spawn(async move {
             for message in messages {
                 notifier.push_message(message);
             }
             notifier.send_messages().await.unwrap();
         });
 
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[0]);
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[1]);
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[2]);
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[3]);
+        for expected_message in expected_messages {
+            let request = receiver.recv().await.unwrap();
+            assert_eq!(request.message, expected_message);
+            request.respond(StatusBarResponse::Ok);
+        }
+        notifier_handle.await.unwrap();
     }
 }
@@ -7,21 +7,23 @@ use std::net::Ipv4Addr;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
+use tokio::sync::mpsc;
 
 use super::{
-    frame::{Frame, Frames},
-    Notifier,
+    check_responses,
+    frame::{Event, Frame, Response, ResponseError},
+    handle_notifier_stream, handle_subscriber_stream, Notifier,
 };
 use crate::config::Config;
-use crate::statusbar::BlockRefreshMessage;
 
-/// [TcpNotifier]'s error. Currently it's a wrapper around [std::io::Error].
+/// [TcpNotifier]'s error.
 #[derive(Debug)]
 pub enum TcpNotifierError {
     /// IO error.
     IO(io::Error),
+    /// Server responded with an error.
+    Response(ResponseError),
 }
 
 impl From<io::Error> for TcpNotifierError {
@@ -42,6 +44,7 @@ impl fmt::Display for TcpNotifierError {
 
                 msg
             }
+            Self::Response(err) => format!("asyncdwmblocks responded with error: {}", err),
         };
 
         write!(f, "{}", msg)
@@ -63,14 +66,14 @@ impl TcpNotifierError {
 
 /// A TCP notifier.
 ///
-/// This notifier collects messages ([`BlockRefreshMessage`]) and then
+/// This notifier collects messages ([`Frame`]s) and then
 /// connects to TCP socket on *localhost* and port defined in
 /// [config](crate::config::ConfigIpcTcp::port)
 /// and sends encoded messages to a listening server.
 #[derive(Debug, PartialEq, Clone)]
 pub struct TcpNotifier {
     config: Arc<Config>,
-    buff: Vec<BlockRefreshMessage>,
+    buff: Vec<Frame>,
 }
 
 impl TcpNotifier {
@@ -87,20 +90,23 @@ impl TcpNotifier {
 impl Notifier for TcpNotifier {
     type Error = TcpNotifierError;
 
-    fn push_message(&mut self, message: BlockRefreshMessage) {
-        self.buff.push(message)
+    fn push_frame(&mut self, frame: Frame) {
+        self.buff.push(frame)
     }
 
-    async fn send_messages(self) -> Result<(), Self::Error> {
-        let mut stream =
-            TcpStream::connect((Ipv4Addr::LOCALHOST, self.config.ipc.tcp.port)).await?;
+    async fn send_messages(self) -> Result<Vec<Response>, Self::Error> {
+        let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, self.config.ipc.tcp.port)).await?;
 
-        let frames: Frames = self.buff.into_iter().map(Frame::from).collect();
-        let data = frames.encode();
+        let responses = handle_notifier_stream(stream, self.buff).await?;
+        check_responses(responses).map_err(Self::Error::Response)
+    }
 
-        stream.write_all(data.as_slice()).await?;
+    async fn subscribe(self, blocks: bool) -> Result<mpsc::Receiver<Event>, Self::Error> {
+        let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, self.config.ipc.tcp.port)).await?;
 
-        Ok(())
+        let (responses, events) = handle_subscriber_stream(stream, self.buff, blocks).await?;
+        check_responses(responses).map_err(Self::Error::Response)?;
+        Ok(events)
     }
 }
 
@@ -110,7 +116,8 @@ mod tests {
     use crate::block::BlockRunMode;
     use crate::config;
     use crate::ipc::ServerType;
-    use tokio::io::AsyncReadExt;
+    use crate::statusbar::BlockRefreshMessage;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::net::TcpListener;
 
     #[tokio::test]
@@ -126,7 +133,7 @@ mod tests {
         .arc();
 
         let config_notifier = Arc::clone(&config);
-        tokio::spawn(async move {
+        let notifier_handle = tokio::spawn(async move {
             let mut notifier = TcpNotifier::new(config_notifier);
             notifier.push_message(BlockRefreshMessage::new(
                 String::from("cpu"),
@@ -140,7 +147,8 @@ mod tests {
                 String::from("battery"),
                 BlockRunMode::Button(1),
             ));
-            notifier.send_messages().await.unwrap();
+            notifier.push_frame(Frame::RefreshAll);
+            notifier.send_messages().await
         });
 
         let mut buff = Vec::new();
@@ -149,11 +157,22 @@ mod tests {
             .unwrap();
         let (mut stream, _) = listener.accept().await.unwrap();
         stream.read_to_end(&mut buff).await.unwrap();
+        stream
+            .write_all(b"OK\r\nOK\r\nERR unknown-block memory\r\nOK\r\nOK\r\n")
+            .await
+            .unwrap();
+        drop(stream);
 
         assert_eq!(
             buff.as_slice(),
-            b"REFRESH cpu\r\nBUTTON 3 memory\r\nBUTTON 1 battery\r\n"
+            b"VERSION 1\r\nREFRESH cpu\r\nBUTTON 3 memory\r\nBUTTON 1 battery\r\nREFRESH_ALL\r\n"
         );
+        match notifier_handle.await.unwrap() {
+            Err(TcpNotifierError::Response(err)) => {
+                assert_eq!(err, ResponseError::UnknownBlock(String::from("memory")))
+            }
+            r => panic!("Expected error response, got {:?}", r),
+        }
     }
 
     #[tokio::test]
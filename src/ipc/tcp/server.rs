@@ -12,7 +12,7 @@ use tokio::sync::mpsc::{self, Sender};
 
 use super::{handle_server_stream, Server};
 use crate::config::Config;
-use crate::statusbar::BlockRefreshMessage;
+use crate::statusbar::StatusBarRequest;
 
 /// [TcpServer]'s error. Currently it's a wrapper around [std::io::Error].
 #[derive(Debug)]
@@ -67,7 +67,7 @@ impl TcpServerError {
 #[derive(Debug, Clone)]
 pub struct TcpServer {
     config: Arc<Config>,
-    sender: Sender<BlockRefreshMessage>,
+    sender: Sender<StatusBarRequest>,
 }
 
 impl TcpServer {
@@ -75,7 +75,7 @@ impl TcpServer {
     ///
     /// **sender** is a sender half of the channel used to
     /// communicate that some request was made.
-    pub fn new(sender: mpsc::Sender<BlockRefreshMessage>, config: Arc<Config>) -> Self {
+    pub fn new(sender: mpsc::Sender<StatusBarRequest>, config: Arc<Config>) -> Self {
         Self { sender, config }
     }
 }
@@ -99,8 +99,9 @@ impl Server for TcpServer {
 
             let cancelation_sender = cancelation_sender.clone();
             let message_sender = self.sender.clone();
+            let config = Arc::clone(&self.config);
             tokio::spawn(async move {
-                handle_server_stream(stream, message_sender, cancelation_sender).await;
+                handle_server_stream(stream, message_sender, cancelation_sender, config).await;
             });
         }
 
@@ -114,7 +115,9 @@ mod tests {
     use crate::block::BlockRunMode;
     use crate::config;
     use crate::ipc::ServerType;
-    use tokio::io::AsyncWriteExt;
+    use crate::statusbar::BlockRefreshMessage;
+    use crate::statusbar::StatusBarResponse;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::net::TcpStream;
     use tokio::sync::mpsc::channel;
     use tokio::time;
@@ -149,15 +152,140 @@ mod tests {
         });
 
         assert_eq!(
-            receiver.recv().await.unwrap(),
-            BlockRefreshMessage::new(String::from("date"), BlockRunMode::Normal)
+            receiver.recv().await.unwrap().message,
+            BlockRefreshMessage::new(String::from("date"), BlockRunMode::Normal).into()
         );
         assert_eq!(
-            receiver.recv().await.unwrap(),
-            BlockRefreshMessage::new(String::from("weather"), BlockRunMode::Button(3))
+            receiver.recv().await.unwrap().message,
+            BlockRefreshMessage::new(String::from("weather"), BlockRunMode::Button(3)).into()
         );
     }
 
+    #[tokio::test]
+    async fn tcp_server_responses() {
+        let (sender, mut receiver) = channel::<StatusBarRequest>(8);
+        let config = Config {
+            ipc: config::ConfigIpc {
+                server_type: ServerType::Tcp,
+                tcp: config::ConfigIpcTcp { port: 44007 },
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+
+        let mut server = TcpServer::new(sender, Arc::clone(&config));
+        tokio::spawn(async move {
+            let _ = server.run().await;
+        });
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                request.respond(StatusBarResponse::UnknownBlock(String::from("date")));
+            }
+        });
+        // Wait for server to bind.
+        time::sleep(time::Duration::from_millis(100)).await;
+
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, config.ipc.tcp.port))
+            .await
+            .unwrap();
+        stream
+            .write_all(b"VERSION 1\r\nREFRESH date\r\nRELOAD date\r\nVERSION 7\r\n")
+            .await
+            .unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut buff = Vec::new();
+        stream.read_to_end(&mut buff).await.unwrap();
+        assert_eq!(
+            String::from_utf8(buff).unwrap(),
+            "OK\r\nERR unknown-block date\r\nERR parse unknown command RELOAD\r\nERR unsupported-version 7\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn tcp_server_partial_frames() {
+        let (sender, mut receiver) = channel::<StatusBarRequest>(8);
+        let config = Config {
+            ipc: config::ConfigIpc {
+                server_type: ServerType::Tcp,
+                tcp: config::ConfigIpcTcp { port: 44009 },
+                max_frame_size: 16,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+
+        let mut server = TcpServer::new(sender, Arc::clone(&config));
+        tokio::spawn(async move {
+            let _ = server.run().await;
+        });
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                request.respond(StatusBarResponse::Ok);
+            }
+        });
+        // Wait for server to bind.
+        time::sleep(time::Duration::from_millis(100)).await;
+
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, config.ipc.tcp.port))
+            .await
+            .unwrap();
+
+        // Frames are answered as they arrive, on the same connection.
+        let mut buff = [0u8; 64];
+        stream.write_all(b"REFRESH da").await.unwrap();
+        time::sleep(time::Duration::from_millis(50)).await;
+        stream.write_all(b"te\r\n").await.unwrap();
+        let n = stream.read(&mut buff).await.unwrap();
+        assert_eq!(&buff[..n], b"OK\r\n");
+
+        stream
+            .write_all(b"REFRESH a_very_long_block_name\r\nREFRESH_ALL")
+            .await
+            .unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut buff = Vec::new();
+        stream.read_to_end(&mut buff).await.unwrap();
+        assert_eq!(
+            String::from_utf8(buff).unwrap(),
+            "ERR parse frame exceeds maximum size of 16 bytes\r\nOK\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn tcp_server_idle_timeout() {
+        let (sender, _receiver) = channel::<StatusBarRequest>(8);
+        let config = Config {
+            ipc: config::ConfigIpc {
+                server_type: ServerType::Tcp,
+                tcp: config::ConfigIpcTcp { port: 44012 },
+                idle_timeout: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+
+        let mut server = TcpServer::new(sender, Arc::clone(&config));
+        tokio::spawn(async move {
+            let _ = server.run().await;
+        });
+        // Wait for server to bind.
+        time::sleep(time::Duration::from_millis(100)).await;
+
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, config.ipc.tcp.port))
+            .await
+            .unwrap();
+
+        // Server closes connection without waiting for notifier to finish.
+        let mut buff = Vec::new();
+        let read = time::timeout(time::Duration::from_secs(3), stream.read_to_end(&mut buff));
+        assert_eq!(read.await.unwrap().unwrap(), 0);
+    }
+
     #[tokio::test]
     async fn tcp_server_binding_error() {
         let config = Config {
@@ -8,7 +8,10 @@ pub mod server;
 pub use notifier::UdsNotifier;
 pub use server::UdsServer;
 
-use super::{frame, handle_server_stream, Notifier, Server};
+use super::{
+    check_responses, frame, handle_notifier_stream, handle_server_stream, handle_subscriber_stream,
+    Notifier, Server,
+};
 
 #[cfg(test)]
 mod tests {
@@ -16,7 +19,7 @@ mod tests {
     use crate::block::BlockRunMode;
     use crate::config::{self, Config};
     use crate::ipc::ServerType;
-    use crate::statusbar::BlockRefreshMessage;
+    use crate::statusbar::{BlockRefreshMessage, StatusBarMessage, StatusBarResponse};
     use chrono::{DateTime, Utc};
     use std::fs;
     use std::io::ErrorKind;
@@ -55,7 +58,11 @@ mod tests {
             BlockRefreshMessage::new("block3".into(), BlockRunMode::Button(3)),
             BlockRefreshMessage::new("block4".into(), BlockRunMode::Button(4)),
         ];
-        let expected_messages = messages.clone();
+        let expected_messages: Vec<StatusBarMessage> = messages
+            .iter()
+            .cloned()
+            .map(StatusBarMessage::from)
+            .collect();
 
         let mut server = UdsServer::new(sender, termination_signal_receiver, Arc::clone(&config));
         tokio::spawn(async move {
@@ -63,17 +70,19 @@ mod tests {
         });
 
         let mut notifier = UdsNotifier::new(Arc::clone(&config));
-        tokio::spawn(async move {
+        let notifier_handle = tokio::spawn(async move {
             for message in messages {
                 notifier.push_message(message);
             }
             notifier.send_messages().await.unwrap();
         });
 
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[0]);
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[1]);
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[2]);
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[3]);
+        for expected_message in expected_messages {
+            let request = receiver.recv().await.unwrap();
+            assert_eq!(request.message, expected_message);
+            request.respond(StatusBarResponse::Ok);
+        }
+        notifier_handle.await.unwrap();
     }
 
     #[cfg(target_os = "linux")]
@@ -108,7 +117,11 @@ mod tests {
             BlockRefreshMessage::new("block3".into(), BlockRunMode::Button(3)),
             BlockRefreshMessage::new("block4".into(), BlockRunMode::Button(4)),
         ];
-        let expected_messages = messages.clone();
+        let expected_messages: Vec<StatusBarMessage> = messages
+            .iter()
+            .cloned()
+            .map(StatusBarMessage::from)
+            .collect();
 
         let mut server = UdsServer::new(sender, termination_signal_receiver, Arc::clone(&config));
         tokio::spawn(async move {
@@ -121,16 +134,18 @@ mod tests {
         });
 
         let mut notifier = UdsNotifier::new(Arc::clone(&config));
-        tokio::spawn(async move {
+        let notifier_handle = tokio::spawn(async move {
             for message in messages {
                 notifier.push_message(message);
             }
             notifier.send_messages().await.unwrap();
         });
 
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[0]);
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[1]);
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[2]);
-        assert_eq!(receiver.recv().await.unwrap(), expected_messages[3]);
+        for expected_message in expected_messages {
+            let request = receiver.recv().await.unwrap();
+            assert_eq!(request.message, expected_message);
+            request.respond(StatusBarResponse::Ok);
+        }
+        notifier_handle.await.unwrap();
     }
 }
@@ -6,21 +6,23 @@ use std::io;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use tokio::io::AsyncWriteExt;
 use tokio::net::UnixStream;
+use tokio::sync::mpsc;
 
 use super::{
-    frame::{Frame, Frames},
-    Notifier,
+    check_responses,
+    frame::{Event, Frame, Response, ResponseError},
+    handle_notifier_stream, handle_subscriber_stream, Notifier,
 };
 use crate::config::Config;
-use crate::statusbar::BlockRefreshMessage;
 
-/// [UdsNotifier]'s error. Currently it's a wrapper around [std::io::Error].
+/// [UdsNotifier]'s error.
 #[derive(Debug)]
 pub enum UdsNotifierError {
     /// IO error.
     IO(io::Error),
+    /// Server responded with an error.
+    Response(ResponseError),
 }
 
 impl From<io::Error> for UdsNotifierError {
@@ -50,6 +52,7 @@ impl fmt::Display for UdsNotifierError {
 
                 msg
             }
+            Self::Response(err) => format!("asyncdwmblocks responded with error: {}", err),
         };
 
         write!(f, "{}", msg)
@@ -73,7 +76,7 @@ impl UdsNotifierError {
 #[derive(Debug, PartialEq, Clone)]
 pub struct UdsNotifier {
     config: Arc<Config>,
-    buff: Vec<BlockRefreshMessage>,
+    buff: Vec<Frame>,
 }
 
 impl UdsNotifier {
@@ -90,19 +93,23 @@ impl UdsNotifier {
 impl Notifier for UdsNotifier {
     type Error = UdsNotifierError;
 
-    fn push_message(&mut self, message: BlockRefreshMessage) {
-        self.buff.push(message)
+    fn push_frame(&mut self, frame: Frame) {
+        self.buff.push(frame)
     }
 
-    async fn send_messages(self) -> Result<(), Self::Error> {
-        let mut stream = UnixStream::connect(&self.config.ipc.uds.addr()).await?;
+    async fn send_messages(self) -> Result<Vec<Response>, Self::Error> {
+        let stream = UnixStream::connect(self.config.ipc.uds.addr()).await?;
 
-        let frames: Frames = self.buff.into_iter().map(Frame::from).collect();
-        let data = frames.encode();
+        let responses = handle_notifier_stream(stream, self.buff).await?;
+        check_responses(responses).map_err(Self::Error::Response)
+    }
 
-        stream.write_all(data.as_slice()).await?;
+    async fn subscribe(self, blocks: bool) -> Result<mpsc::Receiver<Event>, Self::Error> {
+        let stream = UnixStream::connect(self.config.ipc.uds.addr()).await?;
 
-        Ok(())
+        let (responses, events) = handle_subscriber_stream(stream, self.buff, blocks).await?;
+        check_responses(responses).map_err(Self::Error::Response)?;
+        Ok(events)
     }
 }
 
@@ -118,7 +125,7 @@ mod tests {
     use std::io::ErrorKind;
     use std::path::PathBuf;
     use std::time::SystemTime;
-    use tokio::io::AsyncReadExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::net::UnixListener;
 
     #[tokio::test]
@@ -144,7 +151,7 @@ mod tests {
         .arc();
 
         let mut notifier = UdsNotifier::new(Arc::clone(&config));
-        tokio::spawn(async move {
+        let notifier_handle = tokio::spawn(async move {
             notifier.push_message(BlockRefreshMessage::new(
                 String::from("cpu"),
                 BlockRunMode::Normal,
@@ -157,19 +164,22 @@ mod tests {
                 String::from("battery"),
                 BlockRunMode::Button(1),
             ));
-            notifier.send_messages().await.unwrap();
+            notifier.send_messages().await
         });
 
         let mut buff = Vec::new();
-        let listener = UnixListener::bind(&config.ipc.uds.addr()).unwrap();
+        let listener = UnixListener::bind(config.ipc.uds.addr()).unwrap();
         let (mut stream, _) = listener.accept().await.unwrap();
         stream.read_to_end(&mut buff).await.unwrap();
+        stream.write_all(&b"OK\r\n".repeat(4)).await.unwrap();
+        drop(stream);
+        notifier_handle.await.unwrap().unwrap();
 
         fs::remove_file(&config.ipc.uds.addr).unwrap();
 
         assert_eq!(
             buff.as_slice(),
-            b"REFRESH cpu\r\nBUTTON 3 memory\r\nBUTTON 1 battery\r\n"
+            b"VERSION 1\r\nREFRESH cpu\r\nBUTTON 3 memory\r\nBUTTON 1 battery\r\n"
         );
     }
 
@@ -234,7 +244,7 @@ mod tests {
         .arc();
 
         let mut notifier = UdsNotifier::new(Arc::clone(&config));
-        tokio::spawn(async move {
+        let notifier_handle = tokio::spawn(async move {
             notifier.push_message(BlockRefreshMessage::new(
                 String::from("cpu"),
                 BlockRunMode::Normal,
@@ -247,17 +257,20 @@ mod tests {
                 String::from("battery"),
                 BlockRunMode::Button(1),
             ));
-            notifier.send_messages().await.unwrap();
+            notifier.send_messages().await
         });
 
         let mut buff = Vec::new();
-        let listener = UnixListener::bind(&config.ipc.uds.addr()).unwrap();
+        let listener = UnixListener::bind(config.ipc.uds.addr()).unwrap();
         let (mut stream, _) = listener.accept().await.unwrap();
         stream.read_to_end(&mut buff).await.unwrap();
+        stream.write_all(&b"OK\r\n".repeat(4)).await.unwrap();
+        drop(stream);
+        notifier_handle.await.unwrap().unwrap();
 
         assert_eq!(
             buff.as_slice(),
-            b"REFRESH cpu\r\nBUTTON 3 memory\r\nBUTTON 1 battery\r\n"
+            b"VERSION 1\r\nREFRESH cpu\r\nBUTTON 3 memory\r\nBUTTON 1 battery\r\n"
         );
 
         // Check that file does not exists. Socket is created in abstract namespace.
@@ -14,7 +14,7 @@ use tokio::sync::{
 
 use super::{handle_server_stream, Server};
 use crate::config::Config;
-use crate::statusbar::BlockRefreshMessage;
+use crate::statusbar::StatusBarRequest;
 
 /// [UdsServer]'s error. Currently it's a wrapper around [std::io::Error].
 #[derive(Debug)]
@@ -84,7 +84,7 @@ impl UdsServerError {
 #[derive(Debug)]
 pub struct UdsServer {
     config: Arc<Config>,
-    sender: mpsc::Sender<BlockRefreshMessage>,
+    sender: mpsc::Sender<StatusBarRequest>,
     termination_signal_receiver: broadcast::Receiver<()>,
     binded: bool,
 }
@@ -99,7 +99,7 @@ impl UdsServer {
     /// notified when a OS signal was sent to this process
     /// (done by the caller).
     pub fn new(
-        sender: mpsc::Sender<BlockRefreshMessage>,
+        sender: mpsc::Sender<StatusBarRequest>,
         termination_signal_receiver: broadcast::Receiver<()>,
         config: Arc<Config>,
     ) -> Self {
@@ -117,7 +117,7 @@ impl Server for UdsServer {
     type Error = UdsServerError;
 
     async fn run(&mut self) -> Result<(), Self::Error> {
-        let listener = match UnixListener::bind(&self.config.ipc.uds.addr()) {
+        let listener = match UnixListener::bind(self.config.ipc.uds.addr()) {
             Ok(listener) => listener,
             Err(e) => match e.kind() {
                 io::ErrorKind::AddrInUse
@@ -161,8 +161,9 @@ impl Server for UdsServer {
 
             let cancelation_sender = cancelation_sender.clone();
             let message_sender = self.sender.clone();
+            let config = Arc::clone(&self.config);
             tokio::spawn(async move {
-                handle_server_stream(stream, message_sender, cancelation_sender).await;
+                handle_server_stream(stream, message_sender, cancelation_sender, config).await;
             });
         }
 
@@ -199,6 +200,7 @@ mod tests {
     use crate::block::BlockRunMode;
     use crate::config;
     use crate::ipc::ServerType;
+    use crate::statusbar::BlockRefreshMessage;
     use chrono::{DateTime, Utc};
     use std::fs;
     use std::io::ErrorKind;
@@ -249,12 +251,12 @@ mod tests {
         });
 
         assert_eq!(
-            receiver.recv().await.unwrap(),
-            BlockRefreshMessage::new(String::from("date"), BlockRunMode::Normal)
+            receiver.recv().await.unwrap().message,
+            BlockRefreshMessage::new(String::from("date"), BlockRunMode::Normal).into()
         );
         assert_eq!(
-            receiver.recv().await.unwrap(),
-            BlockRefreshMessage::new(String::from("weather"), BlockRunMode::Button(3))
+            receiver.recv().await.unwrap().message,
+            BlockRefreshMessage::new(String::from("weather"), BlockRunMode::Button(3)).into()
         );
     }
 
@@ -429,12 +431,12 @@ mod tests {
         });
 
         assert_eq!(
-            receiver.recv().await.unwrap(),
-            BlockRefreshMessage::new(String::from("date"), BlockRunMode::Normal)
+            receiver.recv().await.unwrap().message,
+            BlockRefreshMessage::new(String::from("date"), BlockRunMode::Normal).into()
         );
         assert_eq!(
-            receiver.recv().await.unwrap(),
-            BlockRefreshMessage::new(String::from("weather"), BlockRunMode::Button(3))
+            receiver.recv().await.unwrap().message,
+            BlockRefreshMessage::new(String::from("weather"), BlockRunMode::Button(3)).into()
         );
     }
 }
@@ -0,0 +1,148 @@
+//! This module implements markup of lemonbar (also understood by polybar).
+//!
+//! When [protocol](crate::config::ConfigStatusBar::protocol) is set to
+//! [lemonbar](crate::statusbar::Protocol::Lemonbar), every block is [rendered](render)
+//! inside of clickable areas (`%{A<button>:<command>:}...%{A}`) of all mouse buttons.
+//! Clicking a block runs `asyncdwmblocks-notify -b <button> '<name>'`, which refreshes it
+//! with [BlockRunMode::Button](crate::block::BlockRunMode::Button) (lemonbar's output
+//! must be piped to `sh`). Block's [color](crate::config::ConfigStatusBarBlock::color)
+//! is set as it's foreground color (`%{F<color>}...%{F-}`).
+//!
+//! Status bar is usually written to the [standard output](crate::output::Stdout).
+//!
+//! # Example
+//! ```yaml
+//! statusbar:
+//!   protocol: lemonbar
+//!   blocks:
+//!     - name: battery
+//!       command: my_battery_script.sh
+//!       interval: 60
+//!       color: "#ffff00"
+//!
+//! outputs:
+//!   - type: stdout
+//! ```
+//! ```sh
+//! asyncdwmblocks | lemonbar | sh
+//! ```
+
+use crate::block::Block;
+
+/// Command run when block is clicked.
+pub const NOTIFY_COMMAND: &str = "asyncdwmblocks-notify";
+
+/// Mouse buttons (left, middle, right, scroll up and scroll down)
+/// for which clickable areas are created.
+pub const BUTTONS: [u8; 5] = [1, 2, 3, 4, 5];
+
+/// Escapes `%`, which starts lemonbar's formatting blocks.
+fn escape_text(text: &str) -> String {
+    text.replace('%', "%%")
+}
+
+/// Escapes `:`, which ends command of a clickable area.
+fn escape_command(command: &str) -> String {
+    command.replace(':', "\\:")
+}
+
+/// Quotes **argument** with single quotes (escaping single quotes inside of it),
+/// so that shell passes it to command as it is.
+fn quote_argument(argument: &str) -> String {
+    format!("'{}'", argument.replace('\'', "'\\''"))
+}
+
+/// Renders block with a given name in clickable areas and with it's color.
+/// Returns `None` if block has no [output](Block::output).
+fn render_block(name: &str, block: &Block) -> Option<String> {
+    let output = block.output()?;
+
+    let mut rendered = String::new();
+    for button in BUTTONS {
+        let command = format!("{} -b {} {}", NOTIFY_COMMAND, button, quote_argument(name));
+        rendered.push_str(&format!("%{{A{}:{}:}}", button, escape_command(&command)));
+    }
+    match block.color() {
+        Some(color) => rendered.push_str(&format!(
+            "%{{F{}}}{}%{{F-}}",
+            escape_text(color),
+            escape_text(&output)
+        )),
+        None => rendered.push_str(&escape_text(&output)),
+    }
+    for _ in BUTTONS {
+        rendered.push_str("%{A}");
+    }
+
+    Some(rendered)
+}
+
+/// Renders **blocks** (with their names) joined with **delimiter**.
+/// Blocks without [output](Block::output) are skipped.
+pub fn render<'a, I>(blocks: I, delimiter: &str) -> String
+where
+    I: Iterator<Item = (&'a str, &'a Block)>,
+{
+    blocks
+        .filter_map(|(name, block)| render_block(name, block))
+        .collect::<Vec<_>>()
+        .join(&escape_text(delimiter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn lemonbar_render() {
+        let config = Config::default().arc();
+        let mut battery = Block::new("battery".into(), vec![], None, config.clone())
+            .with_color(Some(String::from("#ff0000")));
+        battery.set_result(Some(String::from("50%")));
+        let mut date = Block::new("date".into(), vec![], None, config.clone());
+        date.set_result(Some(String::from("12:00")));
+        let never_run = Block::new("date".into(), vec![], None, config);
+        let blocks = [
+            ("battery", &battery),
+            ("unknown", &never_run),
+            ("a:b", &date),
+        ];
+
+        let areas = |name: &str| {
+            BUTTONS
+                .iter()
+                .map(|b| format!("%{{A{}:asyncdwmblocks-notify -b {} '{}':}}", b, b, name))
+                .collect::<String>()
+        };
+        let expected = format!(
+            "{}%{{F#ff0000}}50%%%{{F-}}%{{A}}%{{A}}%{{A}}%{{A}}%{{A}} | {}12:00%{{A}}%{{A}}%{{A}}%{{A}}%{{A}}",
+            areas("battery"),
+            areas("a\\:b")
+        );
+        assert_eq!(render(blocks.into_iter(), " | "), expected);
+        assert_eq!(render(std::iter::empty(), " | "), "");
+    }
+
+    #[test]
+    fn lemonbar_quote_name() {
+        assert_eq!(quote_argument("date"), "'date'");
+        assert_eq!(quote_argument("a b;rm -rf ~"), "'a b;rm -rf ~'");
+        assert_eq!(quote_argument("$(reboot)"), "'$(reboot)'");
+        assert_eq!(quote_argument("it's"), "'it'\\''s'");
+        for name in ["a b;rm -rf ~", "$(reboot)", "it's", "`x` \\ \"y\""] {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf %s {}", quote_argument(name)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), name);
+        }
+
+        let config = Config::default().arc();
+        let mut block = Block::new("echo".into(), vec![], None, config);
+        block.set_result(Some(String::from("x")));
+        let rendered = render(std::iter::once(("'; reboot; '", &block)), "");
+        assert!(rendered.starts_with("%{A1:asyncdwmblocks-notify -b 1 ''\\''; reboot; '\\''':}"));
+    }
+}
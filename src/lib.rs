@@ -2,15 +2,16 @@
 //!
 //! Internal:
 //! - `ipc`: Builds library with support of IPC (inter process communication).
-//! This is automatically enabled when needed and should not be manually selected.
+//!   This is automatically enabled when needed and should not be manually selected.
 //!
 //! User selectable:
 //! - `tcp`: Enables IPC through TCP sockets
 //! - `uds`: Enables IPC through Unix domain sockets
 //! - `config-file`: Enables loading configuration from file. If not present, then
-//! configuration will be created from source code
+//!   configuration will be created from source code
+//! - `i3bar`: Enables rendering status bar in i3bar (and swaybar) protocol
 //!
-//! By default following features are enabled: `uds`, `tcp`, `config-file`.
+//! By default following features are enabled: `uds`, `tcp`, `config-file`, `i3bar`.
 
 #![warn(
     missing_docs,
@@ -23,9 +24,16 @@
 #![allow(clippy::needless_update)]
 
 pub mod block;
+pub mod builtin;
 pub mod config;
+#[cfg(feature = "i3bar")]
+pub mod i3bar;
 #[cfg(feature = "ipc")]
 pub mod ipc;
+pub mod lemonbar;
+pub mod output;
+#[cfg(target_os = "linux")]
+pub mod signals;
 pub mod statusbar;
 pub mod utils;
 pub mod x11;
@@ -0,0 +1,319 @@
+//! This module defines [Sink]s, to which rendered status bars are written.
+//!
+//! Every [Sink] is created from a [ConfigOutput] listed in
+//! [Config::outputs](crate::config::Config::outputs), so status bar can be
+//! written to many outputs at once. Following sinks are available:
+//!  - [X11Connection]: sets root window's name (read by dwm)
+//!  - [Stdout]: prints one line per status bar (useful for lemonbar, dzen2 or tmux)
+//!  - [File]: overwrites file with status bar or appends status bars (one per line)
+//!  - [Fifo]: writes one line per status bar to a named pipe
+//!
+//! # Example
+//! ```yaml
+//! outputs:
+//!   - type: x11
+//!   - type: file
+//!     path: /tmp/asyncdwmblocks.log
+//!     append: true
+//! ```
+
+use std::error::Error;
+use std::ffi::{CString, OsString};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use async_trait::async_trait;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{Config, ConfigOutput};
+#[cfg(feature = "i3bar")]
+use crate::i3bar::I3bar;
+#[cfg(feature = "i3bar")]
+use crate::statusbar::Protocol;
+use crate::x11::{X11Connection, X11ConnectionError};
+
+/// Error returned when [Sink] can't be created.
+#[derive(Debug)]
+pub enum OutputError {
+    /// Connecting to X server failed.
+    X11(X11ConnectionError),
+    /// Named pipe couldn't be created.
+    Fifo(PathBuf, io::Error),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Self::X11(err) => format!("{}", err),
+            Self::Fifo(path, err) => {
+                format!("Couldn't create named pipe {}: {}", path.display(), err)
+            }
+        };
+
+        write!(f, "{}", msg)
+    }
+}
+
+impl Error for OutputError {}
+
+impl From<X11ConnectionError> for OutputError {
+    fn from(err: X11ConnectionError) -> Self {
+        Self::X11(err)
+    }
+}
+
+/// Output to which rendered status bars are written.
+#[async_trait]
+pub trait Sink: Send {
+    /// Writes status bar.
+    async fn write(&mut self, status: &str) -> io::Result<()>;
+
+    /// Returns description of this sink (used when reporting errors).
+    fn describe(&self) -> String;
+}
+
+#[async_trait]
+impl Sink for X11Connection {
+    async fn write(&mut self, status: &str) -> io::Result<()> {
+        if status.contains('\0') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "status bar contains a null byte",
+            ));
+        }
+        self.set_root_name(status);
+
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        String::from("X11 root window")
+    }
+}
+
+/// [Sink] that prints every status bar in a separate line to the standard output.
+#[derive(Debug)]
+pub struct Stdout {
+    stdout: tokio::io::Stdout,
+}
+
+impl Stdout {
+    /// Creates new `Stdout`.
+    pub fn new() -> Self {
+        Self {
+            stdout: tokio::io::stdout(),
+        }
+    }
+}
+
+impl Default for Stdout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Sink for Stdout {
+    async fn write(&mut self, status: &str) -> io::Result<()> {
+        self.stdout.write_all(line(status).as_bytes()).await?;
+        self.stdout.flush().await
+    }
+
+    fn describe(&self) -> String {
+        String::from("standard output")
+    }
+}
+
+/// [Sink] that writes status bars to a file. File is either overwritten
+/// with every status bar, or status bars are appended to it (one per line).
+///
+/// File is overwritten by writing status bar to a temporary file in the same
+/// directory and renaming it, so that readers never see it empty or partially written.
+#[derive(Debug, PartialEq, Clone)]
+pub struct File {
+    path: PathBuf,
+    append: bool,
+}
+
+impl File {
+    /// Creates new `File` writing to **path**.
+    pub fn new(path: PathBuf, append: bool) -> Self {
+        Self { path, append }
+    }
+}
+
+#[async_trait]
+impl Sink for File {
+    async fn write(&mut self, status: &str) -> io::Result<()> {
+        if !self.append {
+            let temporary = temporary_path(&self.path);
+            if let Err(err) = fs::write(&temporary, line(status)).await {
+                let _ = fs::remove_file(&temporary).await;
+                return Err(err);
+            }
+            return fs::rename(&temporary, &self.path).await;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line(status).as_bytes()).await?;
+        file.flush().await
+    }
+
+    fn describe(&self) -> String {
+        format!("file {}", self.path.display())
+    }
+}
+
+/// [Sink] that writes every status bar in a separate line to a named pipe.
+///
+/// Pipe is created if it doesn't exist. Status bars are written only while some
+/// process reads from the pipe. When there is no reader, or pipe is full,
+/// then status bar is skipped.
+///
+/// Every line is written at once, so that reader never gets a part of it. Since only
+/// writes of at most `PIPE_BUF` bytes (4096 on Linux) are atomic, longer status bars
+/// are rejected.
+#[derive(Debug)]
+pub struct Fifo {
+    path: PathBuf,
+    pipe: Option<std::fs::File>,
+}
+
+impl Fifo {
+    /// Creates new `Fifo` writing to **path** (creating named pipe if needed).
+    pub fn new(path: PathBuf) -> Result<Self, OutputError> {
+        match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_fifo() => {}
+            Ok(_) => {
+                let err = io::Error::new(io::ErrorKind::AlreadyExists, "file is not a FIFO");
+                return Err(OutputError::Fifo(path, err));
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                if let Err(err) = mkfifo(&path) {
+                    return Err(OutputError::Fifo(path, err));
+                }
+            }
+            Err(err) => return Err(OutputError::Fifo(path, err)),
+        }
+
+        Ok(Self { path, pipe: None })
+    }
+
+    /// Opens pipe without blocking. Returns `None` if there is no reader.
+    fn open(&self) -> io::Result<Option<std::fs::File>> {
+        match OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&self.path)
+        {
+            Ok(pipe) => Ok(Some(pipe)),
+            Err(err) if err.raw_os_error() == Some(libc::ENXIO) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for Fifo {
+    async fn write(&mut self, status: &str) -> io::Result<()> {
+        if self.pipe.is_none() {
+            self.pipe = self.open()?;
+        }
+        let pipe = match self.pipe.as_mut() {
+            Some(pipe) => pipe,
+            None => return Ok(()),
+        };
+
+        let line = line(status);
+        if line.len() > libc::PIPE_BUF {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("status bar is longer than {} bytes", libc::PIPE_BUF - 1),
+            ));
+        }
+        match pipe.write(line.as_bytes()) {
+            Ok(written) if written == line.len() => Ok(()),
+            // Atomic writes are never partial, but if it happened, then reader
+            // gets end of file instead of this line joined with the next one.
+            Ok(_) => {
+                self.pipe = None;
+                Ok(())
+            }
+            // Nothing was written.
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            // Reader has closed pipe. It will be reopened when next reader appears.
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {
+                self.pipe = None;
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("named pipe {}", self.path.display())
+    }
+}
+
+/// Creates named pipe at **path**.
+fn mkfifo(path: &Path) -> io::Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    // SAFETY: path is a valid NUL-terminated C string that outlives the call.
+    match unsafe { libc::mkfifo(path.as_ptr(), 0o644) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Returns path of a temporary file (in the same directory as **path**)
+/// used to overwrite file at **path**.
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", process::id()));
+    path.with_file_name(name)
+}
+
+/// Returns status bar terminated with a new line.
+fn line(status: &str) -> String {
+    let mut line = String::with_capacity(status.len() + 1);
+    line.push_str(status);
+    line.push('\n');
+    line
+}
+
+/// Creates [Sink] from it's configuration.
+pub fn sink(output: &ConfigOutput) -> Result<Box<dyn Sink>, OutputError> {
+    let sink: Box<dyn Sink> = match output {
+        ConfigOutput::X11 => Box::new(X11Connection::new()?),
+        ConfigOutput::Stdout => Box::new(Stdout::new()),
+        ConfigOutput::File { path, append } => Box::new(File::new(path.clone(), *append)),
+        ConfigOutput::Fifo { path } => Box::new(Fifo::new(path.clone())?),
+    };
+
+    Ok(sink)
+}
+
+/// Creates [Sink]s of all [outputs](Config::outputs) listed in **config**.
+///
+/// If status bar is rendered in [i3bar](Protocol::I3bar) protocol, then
+/// only [I3bar] sink is created.
+pub fn sinks(config: &Config) -> Result<Vec<Box<dyn Sink>>, OutputError> {
+    #[cfg(feature = "i3bar")]
+    if config.statusbar.protocol == Protocol::I3bar {
+        return Ok(vec![Box::new(I3bar::new())]);
+    }
+
+    config.outputs.iter().map(sink).collect()
+}
@@ -0,0 +1,470 @@
+//! This module allows to refresh blocks by sending real-time signals.
+//!
+//! It implements the same protocol as dwmblocks: sending `SIGRTMIN+n` to
+//! asyncdwmblocks refreshes every block whose
+//! [signal](crate::config::ConfigStatusBarBlock::signal) is set to `n`.
+//!
+//! It also implements receiving side of dwm's *statuscmd* patch (see
+//! [statuscmd](crate::config::ConfigStatusBar::statuscmd)). When signal was
+//! sent with `sigqueue`, then it's value is a number of a clicked mouse button
+//! and block is refreshed with [BlockRunMode::Button]. Older versions of this
+//! patch send `SIGUSR1` with value `(n << 8) | button` instead. It is handled
+//! only when `statuscmd` is enabled.
+//!
+//! Signal handlers can't do much, so handler only writes received signal into
+//! a pipe. This pipe is read by a separate thread that translates signals into
+//! [BlockRefreshMessage]s. Because of that only one listener can be
+//! [started](listen) per process, but blocks it refreshes can be
+//! [updated](SignalsListener::update) later.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::mem;
+use std::os::unix::io::FromRawFd;
+use std::ptr;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use libc::{c_int, c_void};
+use tokio::sync::mpsc;
+
+use crate::block::BlockRunMode;
+use crate::config::Config;
+use crate::statusbar::{BlockRefreshMessage, StatusBarRequest};
+
+/// `si_code` of signals sent by `sigqueue`.
+const SI_QUEUE: c_int = -1;
+
+/// Size of a record written by signal handler (signal number, `si_code` and value).
+const RECORD_SIZE: usize = 3 * mem::size_of::<c_int>();
+
+/// Write end of a pipe used by signal handler. Negative if listener wasn't started.
+static PIPE_WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// Error returned when starting signal listener failed.
+#[derive(Debug)]
+pub enum SignalsError {
+    /// Block's signal doesn't fit in real-time signals range.
+    OutOfRange {
+        /// Block's name.
+        name: String,
+        /// Block's signal.
+        signal: u8,
+    },
+    /// Listener was already started.
+    AlreadyListening,
+    /// IO error (creating pipe or installing signal handler failed).
+    IO(io::Error),
+}
+
+impl From<io::Error> for SignalsError {
+    fn from(err: io::Error) -> Self {
+        Self::IO(err)
+    }
+}
+
+impl fmt::Display for SignalsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Self::OutOfRange { name, signal } => format!(
+                "Signal {} of block `{}` is out of range (1..={})",
+                signal,
+                name,
+                max_signal()
+            ),
+            Self::AlreadyListening => "Signal listener was already started".to_string(),
+            Self::IO(err) => format!("io error: {}", err),
+        };
+
+        write!(f, "{}", msg)
+    }
+}
+
+impl Error for SignalsError {}
+
+/// Returns maximal value of block's signal.
+fn max_signal() -> c_int {
+    libc::SIGRTMAX() - libc::SIGRTMIN()
+}
+
+/// Blocks refreshed by signals.
+#[derive(Debug, PartialEq, Clone, Default)]
+struct BlockSignals {
+    /// Names of blocks for every signal.
+    blocks: HashMap<u8, Vec<String>>,
+    /// Whether `SIGUSR1` (sent by older versions of statuscmd) is handled.
+    statuscmd: bool,
+}
+
+impl BlockSignals {
+    fn from_config(config: &Config) -> Result<Self, SignalsError> {
+        let mut blocks = HashMap::<u8, Vec<String>>::new();
+        for block in config.statusbar.blocks.iter() {
+            if let Some(signal) = block.signal {
+                if signal == 0 || c_int::from(signal) > max_signal() {
+                    return Err(SignalsError::OutOfRange {
+                        name: block.name.clone(),
+                        signal,
+                    });
+                }
+                blocks.entry(signal).or_default().push(block.name.clone());
+            }
+        }
+
+        Ok(Self {
+            blocks,
+            statuscmd: config.statusbar.statuscmd,
+        })
+    }
+
+    /// Installs signal handlers for all signals. Installing
+    /// handler for the same signal again does nothing.
+    fn install_handlers(&self) -> io::Result<()> {
+        for signal in self.blocks.keys() {
+            install_handler(libc::SIGRTMIN() + c_int::from(*signal))?;
+        }
+        if self.statuscmd {
+            install_handler(libc::SIGUSR1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Handle to a running signal listener (returned by [listen]).
+#[derive(Debug, Clone)]
+pub struct SignalsListener {
+    signals: Arc<Mutex<BlockSignals>>,
+}
+
+impl SignalsListener {
+    /// Starts listening to signals of blocks defined in new `config`
+    /// (for example after reloading it). Signals of blocks that no
+    /// longer exist are ignored. On error previous blocks are kept.
+    pub fn update(&self, config: &Config) -> Result<(), SignalsError> {
+        let signals = BlockSignals::from_config(config)?;
+        signals.install_handlers()?;
+        // Thread reading signals never panics while holding this lock.
+        *self.signals.lock().unwrap() = signals;
+
+        Ok(())
+    }
+}
+
+/// Starts listening to signals of blocks defined in `config`
+/// and sending refresh requests through **sender**.
+///
+/// Signals are listened to by a separate thread, which stops when
+/// receiving half of **sender** is closed. This function can be
+/// called only once per process. Returned [SignalsListener] can be
+/// used to update blocks that are refreshed.
+pub fn listen(
+    config: &Config,
+    sender: mpsc::Sender<StatusBarRequest>,
+) -> Result<SignalsListener, SignalsError> {
+    let signals = BlockSignals::from_config(config)?;
+
+    let mut fds = [0 as c_int; 2];
+    // SAFETY: fds is a valid array of two ints.
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let [read_fd, write_fd] = fds;
+    // SAFETY: read_fd was just created and is owned only by this File.
+    let mut pipe = unsafe { File::from_raw_fd(read_fd) };
+    // Signal handler must never block.
+    // SAFETY: write_fd is a valid file descriptor.
+    unsafe {
+        libc::fcntl(write_fd, libc::F_SETFL, libc::O_NONBLOCK);
+    }
+    if PIPE_WRITE_FD
+        .compare_exchange(-1, write_fd, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        // SAFETY: write_fd was never shared.
+        unsafe {
+            libc::close(write_fd);
+        }
+        return Err(SignalsError::AlreadyListening);
+    }
+
+    signals.install_handlers()?;
+    let listener = SignalsListener {
+        signals: Arc::new(Mutex::new(signals)),
+    };
+
+    let signals = Arc::clone(&listener.signals);
+    thread::spawn(move || {
+        let mut buffer = [0u8; RECORD_SIZE];
+        while pipe.read_exact(&mut buffer).is_ok() {
+            let (signum, code, value) = decode_record(&buffer);
+            let (signal, mode) = match translate(signum, code, value) {
+                Some(translated) => translated,
+                None => continue,
+            };
+
+            let names = {
+                let signals = signals.lock().unwrap();
+                if signum == libc::SIGUSR1 && !signals.statuscmd {
+                    continue;
+                }
+                signals.blocks.get(&signal).cloned().unwrap_or_default()
+            };
+            for name in names {
+                let message = BlockRefreshMessage::new(name, mode.clone());
+                if sender.blocking_send(message.into()).is_err() {
+                    // Statusbar has stopped running.
+                    return;
+                }
+            }
+        }
+    });
+
+    Ok(listener)
+}
+
+/// Translates received signal into block's signal and [BlockRunMode].
+fn translate(signum: c_int, code: c_int, value: c_int) -> Option<(u8, BlockRunMode)> {
+    if signum == libc::SIGUSR1 {
+        // Only sigqueue carries meaningful value.
+        if code != SI_QUEUE {
+            return None;
+        }
+        let signal = u8::try_from(value >> 8).ok()?;
+        let button = (value & 0xff) as u8;
+        Some((signal, BlockRunMode::Button(button)))
+    } else {
+        let signal = u8::try_from(signum - libc::SIGRTMIN()).ok()?;
+        let mode = match u8::try_from(value) {
+            Ok(button) if code == SI_QUEUE && button > 0 => BlockRunMode::Button(button),
+            _ => BlockRunMode::Normal,
+        };
+        Some((signal, mode))
+    }
+}
+
+fn install_handler(signum: c_int) -> io::Result<()> {
+    // SAFETY: action is fully initialised before passing it to sigaction
+    // and handler only calls async-signal-safe functions.
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handler as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(signum, &action, ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+extern "C" fn handler(signum: c_int, info: *mut libc::siginfo_t, _context: *mut c_void) {
+    let fd = PIPE_WRITE_FD.load(Ordering::SeqCst);
+    if fd < 0 || info.is_null() {
+        return;
+    }
+
+    // SAFETY: info is not null and was provided by the kernel.
+    let (code, value) = unsafe { ((*info).si_code, sigval_int((*info).si_value())) };
+    let record = encode_record(signum, code, value);
+
+    // SAFETY: write is async-signal-safe. errno is restored, so that
+    // interrupted code doesn't see it changed.
+    unsafe {
+        let errno = *libc::__errno_location();
+        libc::write(fd, record.as_ptr() as *const c_void, RECORD_SIZE);
+        *libc::__errno_location() = errno;
+    }
+}
+
+/// Reads `sival_int` member of `sigval` union.
+fn sigval_int(value: libc::sigval) -> c_int {
+    // `sival_int` occupies first bytes of the union.
+    let bytes = (value.sival_ptr as usize).to_ne_bytes();
+    let mut int = [0u8; mem::size_of::<c_int>()];
+    int.copy_from_slice(&bytes[..mem::size_of::<c_int>()]);
+    c_int::from_ne_bytes(int)
+}
+
+fn encode_record(signum: c_int, code: c_int, value: c_int) -> [u8; RECORD_SIZE] {
+    let size = mem::size_of::<c_int>();
+    let mut record = [0u8; RECORD_SIZE];
+    record[..size].copy_from_slice(&signum.to_ne_bytes());
+    record[size..2 * size].copy_from_slice(&code.to_ne_bytes());
+    record[2 * size..].copy_from_slice(&value.to_ne_bytes());
+    record
+}
+
+fn decode_record(record: &[u8; RECORD_SIZE]) -> (c_int, c_int, c_int) {
+    let size = mem::size_of::<c_int>();
+    let int = |i: usize| {
+        let mut bytes = [0u8; mem::size_of::<c_int>()];
+        bytes.copy_from_slice(&record[i * size..(i + 1) * size]);
+        c_int::from_ne_bytes(bytes)
+    };
+    (int(0), int(1), int(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{self, ConfigStatusBarBlock};
+    use tokio::time::{timeout, Duration};
+
+    extern "C" {
+        fn sigqueue(pid: libc::pid_t, sig: c_int, value: libc::sigval) -> c_int;
+    }
+
+    fn queue_signal(signum: c_int, value: c_int) {
+        let mut bytes = [0u8; mem::size_of::<usize>()];
+        bytes[..mem::size_of::<c_int>()].copy_from_slice(&value.to_ne_bytes());
+        let value = libc::sigval {
+            sival_ptr: usize::from_ne_bytes(bytes) as *mut c_void,
+        };
+        // SAFETY: sending a signal to ourselves with installed handler.
+        unsafe {
+            assert_eq!(sigqueue(libc::getpid(), signum, value), 0);
+        }
+    }
+
+    #[test]
+    fn translate_signals() {
+        let rtmin = libc::SIGRTMIN();
+
+        assert_eq!(
+            translate(rtmin + 10, 0, 0),
+            Some((10, BlockRunMode::Normal))
+        );
+        assert_eq!(
+            translate(rtmin + 10, SI_QUEUE, 3),
+            Some((10, BlockRunMode::Button(3)))
+        );
+        assert_eq!(
+            translate(libc::SIGUSR1, SI_QUEUE, (4 << 8) | 2),
+            Some((4, BlockRunMode::Button(2)))
+        );
+        assert_eq!(translate(libc::SIGUSR1, 0, (4 << 8) | 2), None);
+    }
+
+    #[test]
+    fn record_encoding() {
+        let record = encode_record(34, -1, 1025);
+        assert_eq!(decode_record(&record), (34, -1, 1025));
+    }
+
+    #[tokio::test]
+    async fn listen_to_signals() {
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                blocks: vec![
+                    ConfigStatusBarBlock {
+                        name: String::from("volume"),
+                        signal: Some(5),
+                        ..Default::default()
+                    },
+                    ConfigStatusBarBlock {
+                        name: String::from("battery"),
+                        signal: Some(6),
+                        ..Default::default()
+                    },
+                ],
+                statuscmd: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let (sender, mut receiver) = mpsc::channel(8);
+        let listener = listen(&config, sender.clone()).unwrap();
+        assert!(matches!(
+            listen(&config, sender),
+            Err(SignalsError::AlreadyListening)
+        ));
+
+        // SAFETY: handler is installed.
+        unsafe {
+            libc::kill(libc::getpid(), libc::SIGRTMIN() + 5);
+        }
+        assert_eq!(
+            timeout(Duration::from_secs(1), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap()
+                .message,
+            BlockRefreshMessage::new(String::from("volume"), BlockRunMode::Normal).into()
+        );
+
+        queue_signal(libc::SIGRTMIN() + 6, 3);
+        assert_eq!(
+            timeout(Duration::from_secs(1), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap()
+                .message,
+            BlockRefreshMessage::new(String::from("battery"), BlockRunMode::Button(3)).into()
+        );
+
+        queue_signal(libc::SIGUSR1, (5 << 8) | 1);
+        assert_eq!(
+            timeout(Duration::from_secs(1), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap()
+                .message,
+            BlockRefreshMessage::new(String::from("volume"), BlockRunMode::Button(1)).into()
+        );
+
+        // Reloaded configuration.
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                blocks: vec![ConfigStatusBarBlock {
+                    name: String::from("memory"),
+                    signal: Some(5),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        listener.update(&config).unwrap();
+
+        // Signal of removed block and SIGUSR1 (statuscmd is disabled) are ignored.
+        queue_signal(libc::SIGRTMIN() + 6, 3);
+        queue_signal(libc::SIGUSR1, (5 << 8) | 1);
+        queue_signal(libc::SIGRTMIN() + 5, 2);
+        assert_eq!(
+            timeout(Duration::from_secs(1), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap()
+                .message,
+            BlockRefreshMessage::new(String::from("memory"), BlockRunMode::Button(2)).into()
+        );
+    }
+
+    #[test]
+    fn signal_out_of_range() {
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                blocks: vec![ConfigStatusBarBlock {
+                    name: String::from("block"),
+                    signal: Some(0),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let (sender, _) = mpsc::channel(8);
+        assert!(matches!(
+            listen(&config, sender),
+            Err(SignalsError::OutOfRange { .. })
+        ));
+    }
+}
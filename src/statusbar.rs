@@ -1,15 +1,45 @@
 //! This module defines [StatusBar] and it's errors.
 
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::sync::Arc;
 
-use futures::future::join_all;
 use indexmap::IndexMap;
-use tokio::sync::mpsc;
-
-use crate::block::{Block, BlockRunMode};
-use crate::config::Config;
+use regex::Regex;
+#[cfg(feature = "config-file")]
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Duration, Instant};
+
+use crate::block::{
+    aligned_scheduler, Block, BlockMode, BlockOutput, BlockRunError, BlockRunMode, BusyPolicy,
+    HideRules, Retry, OUTPUT_PLACEHOLDER,
+};
+use crate::builtin::{BuiltinError, Builtins};
+use crate::config::{BlockType, Config};
+#[cfg(feature = "i3bar")]
+use crate::i3bar;
+use crate::lemonbar;
+use crate::utils::{Template, TemplateError};
+
+/// Protocol in which [StatusBar] is rendered.
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[cfg_attr(feature = "config-file", derive(Deserialize))]
+#[cfg_attr(feature = "config-file", serde(rename_all = "lowercase"))]
+pub enum Protocol {
+    /// Blocks' outputs are joined with a delimiter (as read by dwm).
+    #[default]
+    Plain,
+    /// JSON array of blocks (see [i3bar] module).
+    #[cfg(feature = "i3bar")]
+    I3bar,
+    /// Blocks in clickable areas of lemonbar and polybar
+    /// (see [lemonbar] module).
+    Lemonbar,
+}
 
 /// [Block] held by [StatusBar].
 #[derive(Debug, PartialEq, Clone)]
@@ -37,17 +67,797 @@ impl BlockRefreshMessage {
     }
 }
 
+/// Message passed to running [StatusBar](StatusBar::run).
+#[derive(Debug, PartialEq, Clone)]
+pub enum StatusBarMessage {
+    /// Refresh single block.
+    Refresh(BlockRefreshMessage),
+    /// Refresh all blocks (except persistent ones) in [normal](BlockRunMode::Normal) mode.
+    RefreshAll,
+    /// Ask for a list of blocks.
+    List,
+    /// Ask for a current result of a block with given name.
+    Get(String),
+    /// Ask for a current status bar.
+    Status,
+    /// Subscribe to [StatusBarEvent]s. If `blocks` is `true` then
+    /// subscriber also receives changes of single blocks.
+    Subscribe {
+        /// Whether to receive [StatusBarEvent::Block] events.
+        blocks: bool,
+    },
+    /// Reload configuration. Blocks are added, removed or reconfigured,
+    /// while blocks that did not change keep their results. Configuration
+    /// with different [protocol](crate::config::ConfigStatusBar::protocol)
+    /// is rejected.
+    ///
+    /// This message can't be sent over IPC.
+    Reload(Arc<Config>),
+    /// Insert `block` at position `index` (or at the end, if `index` is out of bounds).
+    ///
+    /// Blocks added (and changes made) at runtime are discarded
+    /// when configuration is [reloaded](StatusBarMessage::Reload).
+    Insert {
+        /// Position of a new block.
+        index: usize,
+        /// Name of a new block.
+        name: String,
+        /// New block.
+        block: Box<Block>,
+    },
+    /// Remove block with given name.
+    Remove(String),
+    /// Move block with given name to position `index`
+    /// (or to the end, if `index` is out of bounds).
+    Move {
+        /// Name of a moved block.
+        name: String,
+        /// New position of a block.
+        index: usize,
+    },
+    /// Set text of a block with given name (without running it's command).
+    ///
+    /// If block has a [ttl](Block::ttl), then after it passes text is cleared
+    /// ([push](BlockMode::Push) blocks) or block is refreshed (other blocks).
+    /// Until then results of block's command are discarded.
+    Set {
+        /// Name of a block.
+        name: String,
+        /// Block's new text.
+        text: String,
+    },
+    /// Change definition of a block with given name. Block is rerun
+    /// immediately, but keeps it's result until new one is computed.
+    Update {
+        /// Name of a changed block.
+        name: String,
+        /// Changes to apply.
+        update: BlockUpdate,
+    },
+}
+
+impl From<BlockRefreshMessage> for StatusBarMessage {
+    fn from(message: BlockRefreshMessage) -> Self {
+        Self::Refresh(message)
+    }
+}
+
+/// Changes of a [Block]'s definition (see [StatusBarMessage::Update]).
+///
+/// Only changes that were set are applied.
+///
+/// # Example
+/// ```
+/// use asyncdwmblocks::statusbar::BlockUpdate;
+///
+/// // Refresh block every 5 seconds with a new command.
+/// let update = BlockUpdate::new()
+///     .with_command("date".to_string(), vec!["+%H:%M:%S".to_string()])
+///     .with_interval(Some(5));
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BlockUpdate {
+    pub(crate) command: Option<(String, Vec<String>)>,
+    pub(crate) interval: Option<Option<u64>>,
+}
+
+impl BlockUpdate {
+    /// Creates new `BlockUpdate` that doesn't change anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Changes block's command and it's arguments.
+    pub fn with_command(mut self, command: String, args: Vec<String>) -> Self {
+        self.command = Some((command, args));
+        self
+    }
+
+    /// Changes block's refresh interval (in seconds).
+    /// If `None` then block won't be refreshed at interval anymore.
+    ///
+    /// # Panics
+    /// If `interval` is `Some`, then it must be greater than 0. Interval with value
+    /// `Some(0)` will panic.
+    pub fn with_interval(mut self, interval: Option<u64>) -> Self {
+        if interval.is_some() {
+            assert!(interval > Some(0), "Interval must be at least 1 second.");
+        }
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Applies changes to **block**.
+    fn apply(self, block: &mut Block) {
+        if let Some((command, args)) = self.command {
+            block.set_command(command, args);
+        }
+        if let Some(interval) = self.interval {
+            block.set_interval(interval);
+        }
+    }
+}
+
+/// Description of a [Block] held by running [StatusBar].
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockInfo {
+    /// Block's name.
+    pub name: String,
+    /// Block's command (together with it's arguments).
+    pub command: String,
+    /// Block's refresh interval (in seconds).
+    pub interval: Option<u64>,
+}
+
+/// Event published by a running [StatusBar] to it's subscribers.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StatusBarEvent {
+    /// New status bar was rendered.
+    Status(String),
+    /// Result of a block has changed. Empty `result`
+    /// means that block has no result.
+    Block {
+        /// Block's name.
+        name: String,
+        /// Block's new result.
+        result: String,
+    },
+}
+
+/// Subscription to [StatusBarEvent]s of a running [StatusBar]
+/// (response to [StatusBarMessage::Subscribe]).
+///
+/// First received event is always a status bar
+/// from the moment of subscribing.
+#[derive(Debug)]
+pub struct StatusBarSubscription {
+    status: Option<String>,
+    blocks: bool,
+    receiver: broadcast::Receiver<StatusBarEvent>,
+}
+
+impl StatusBarSubscription {
+    pub(crate) fn new(
+        status: String,
+        blocks: bool,
+        receiver: broadcast::Receiver<StatusBarEvent>,
+    ) -> Self {
+        Self {
+            status: Some(status),
+            blocks,
+            receiver,
+        }
+    }
+
+    /// Receives next event.
+    ///
+    /// Returns `None` when status bar stopped running. If subscriber
+    /// is too slow, then oldest events it didn't receive are skipped.
+    pub async fn recv(&mut self) -> Option<StatusBarEvent> {
+        if let Some(status) = self.status.take() {
+            return Some(StatusBarEvent::Status(status));
+        }
+
+        loop {
+            match self.receiver.recv().await {
+                Ok(StatusBarEvent::Block { .. }) if !self.blocks => continue,
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// Subscriptions are compared by their initial status and
+/// whether they receive block events.
+impl PartialEq for StatusBarSubscription {
+    fn eq(&self, other: &Self) -> bool {
+        self.status == other.status && self.blocks == other.blocks
+    }
+}
+
+/// Response of a running [StatusBar] to a [StatusBarRequest].
+#[derive(Debug, PartialEq)]
+pub enum StatusBarResponse {
+    /// Request was accepted.
+    Ok,
+    /// There is no block with given name.
+    UnknownBlock(String),
+    /// Block with given name already exists (response to [StatusBarMessage::Insert]).
+    BlockExists(String),
+    /// List of blocks (response to [StatusBarMessage::List]).
+    List(Vec<BlockInfo>),
+    /// Block's current result (response to [StatusBarMessage::Get]).
+    /// `None` means that block has not computed it's result yet.
+    Result(Option<String>),
+    /// Current status bar (response to [StatusBarMessage::Status]).
+    Status(String),
+    /// Subscription to status bar's events (response to [StatusBarMessage::Subscribe]).
+    Subscribed(StatusBarSubscription),
+    /// Configuration could not be reloaded (response to [StatusBarMessage::Reload]).
+    /// Status bar keeps running with it's previous configuration.
+    InvalidConfig(StatusBarCreationError),
+}
+
+/// Request passed to running [StatusBar](StatusBar::run).
+///
+/// It holds a [StatusBarMessage] and (optionally) a channel
+/// through which StatusBar sends back it's [StatusBarResponse].
+#[derive(Debug)]
+pub struct StatusBarRequest {
+    pub(crate) message: StatusBarMessage,
+    reply: Option<oneshot::Sender<StatusBarResponse>>,
+}
+
+impl StatusBarRequest {
+    /// Creates new `StatusBarRequest` that doesn't wait for a response.
+    pub fn new(message: StatusBarMessage) -> Self {
+        Self {
+            message,
+            reply: None,
+        }
+    }
+
+    /// Creates new `StatusBarRequest` together with a receiver of it's response.
+    pub fn with_reply(message: StatusBarMessage) -> (Self, oneshot::Receiver<StatusBarResponse>) {
+        let (sender, receiver) = oneshot::channel();
+        let request = Self {
+            message,
+            reply: Some(sender),
+        };
+        (request, receiver)
+    }
+
+    /// Returns request's message.
+    pub fn message(&self) -> &StatusBarMessage {
+        &self.message
+    }
+
+    /// Sends **response** to whoever made this request (if they are waiting for it).
+    pub fn respond(self, response: StatusBarResponse) {
+        if let Some(reply) = self.reply {
+            // Requester might not wait for response anymore. Ignore it.
+            let _ = reply.send(response);
+        }
+    }
+}
+
+impl From<StatusBarMessage> for StatusBarRequest {
+    fn from(message: StatusBarMessage) -> Self {
+        Self::new(message)
+    }
+}
+
+impl From<BlockRefreshMessage> for StatusBarRequest {
+    fn from(message: BlockRefreshMessage) -> Self {
+        Self::new(StatusBarMessage::Refresh(message))
+    }
+}
+
+/// Error returned by [StatusBarHandle].
+#[derive(Debug, PartialEq, Clone)]
+pub enum StatusBarHandleError {
+    /// Status bar is not running.
+    Stopped,
+    /// There is no block with given name.
+    UnknownBlock(String),
+    /// Block with given name already exists.
+    BlockExists(String),
+}
+
+impl fmt::Display for StatusBarHandleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Self::Stopped => String::from("status bar is not running"),
+            Self::UnknownBlock(name) => format!("unknown block `{}`", name),
+            Self::BlockExists(name) => format!("block `{}` already exists", name),
+        };
+
+        write!(f, "{}", msg)
+    }
+}
+
+impl Error for StatusBarHandleError {}
+
+/// Handle used to control a running [StatusBar](StatusBar::run).
+///
+/// It sends [StatusBarRequest]s and waits for their responses. With it blocks
+/// can be added, removed, reordered and changed while status bar is running.
+/// It can be cloned and shared between tasks.
+///
+/// # Example
+/// ```no_run
+/// use std::sync::Arc;
+/// use tokio::sync::mpsc;
+/// use asyncdwmblocks::block::Block;
+/// use asyncdwmblocks::statusbar::{StatusBar, StatusBarHandle};
+/// use asyncdwmblocks::config::Config;
+///
+/// # async fn doc() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = Config::default().arc();
+/// let mut status_bar = StatusBar::try_from(Arc::clone(&config))?;
+///
+/// let (result_sender, mut result_receiver) = mpsc::channel(8);
+/// let (handle, requests) = StatusBarHandle::channel(8);
+/// tokio::spawn(async move {
+///     status_bar.run(result_sender, requests).await;
+/// });
+///
+/// // Show recording indicator only while it is relevant.
+/// let indicator = Block::new("echo".into(), vec!["REC".into()], None, config);
+/// handle.insert_block(0, "recording".into(), indicator).await?;
+/// // ...
+/// handle.remove_block("recording").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StatusBarHandle {
+    sender: mpsc::Sender<StatusBarRequest>,
+}
+
+impl StatusBarHandle {
+    /// Creates new `StatusBarHandle` together with a receiver of it's requests,
+    /// that should be passed to [run](StatusBar::run).
+    pub fn channel(buffer: usize) -> (Self, mpsc::Receiver<StatusBarRequest>) {
+        let (sender, receiver) = mpsc::channel(buffer);
+        (Self { sender }, receiver)
+    }
+
+    /// Returns sender of requests (for example to be passed to a [Server](crate::ipc::Server)).
+    pub fn sender(&self) -> mpsc::Sender<StatusBarRequest> {
+        self.sender.clone()
+    }
+
+    /// Sends **message** and waits for status bar's response.
+    pub async fn request(
+        &self,
+        message: StatusBarMessage,
+    ) -> Result<StatusBarResponse, StatusBarHandleError> {
+        let (request, response) = StatusBarRequest::with_reply(message);
+        self.sender
+            .send(request)
+            .await
+            .map_err(|_| StatusBarHandleError::Stopped)?;
+        response.await.map_err(|_| StatusBarHandleError::Stopped)
+    }
+
+    /// Inserts **block** at position `index` (or at the end, if `index` is out of bounds)
+    /// and starts running it.
+    pub async fn insert_block(
+        &self,
+        index: usize,
+        name: String,
+        block: Block,
+    ) -> Result<(), StatusBarHandleError> {
+        let message = StatusBarMessage::Insert {
+            index,
+            name,
+            block: Box::new(block),
+        };
+        self.change(message).await
+    }
+
+    /// Removes block with given name.
+    pub async fn remove_block(&self, name: &str) -> Result<(), StatusBarHandleError> {
+        let message = StatusBarMessage::Remove(name.to_string());
+        self.change(message).await
+    }
+
+    /// Moves block with given name to position `index` (or to the end,
+    /// if `index` is out of bounds).
+    pub async fn move_block(&self, name: &str, index: usize) -> Result<(), StatusBarHandleError> {
+        let message = StatusBarMessage::Move {
+            name: name.to_string(),
+            index,
+        };
+        self.change(message).await
+    }
+
+    /// Sets text of a block with given name (see [StatusBarMessage::Set]).
+    pub async fn set_text(&self, name: &str, text: String) -> Result<(), StatusBarHandleError> {
+        let message = StatusBarMessage::Set {
+            name: name.to_string(),
+            text,
+        };
+        self.change(message).await
+    }
+
+    /// Changes definition of a block with given name.
+    pub async fn update_block(
+        &self,
+        name: &str,
+        update: BlockUpdate,
+    ) -> Result<(), StatusBarHandleError> {
+        let message = StatusBarMessage::Update {
+            name: name.to_string(),
+            update,
+        };
+        self.change(message).await
+    }
+
+    /// Sends **message** that changes blocks and checks it's response.
+    async fn change(&self, message: StatusBarMessage) -> Result<(), StatusBarHandleError> {
+        match self.request(message).await? {
+            StatusBarResponse::UnknownBlock(name) => Err(StatusBarHandleError::UnknownBlock(name)),
+            StatusBarResponse::BlockExists(name) => Err(StatusBarHandleError::BlockExists(name)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<mpsc::Sender<StatusBarRequest>> for StatusBarHandle {
+    fn from(sender: mpsc::Sender<StatusBarRequest>) -> Self {
+        Self { sender }
+    }
+}
+
+/// Output of a [Block] run in a separate task.
+#[derive(Debug)]
+struct BlockRunResult {
+    name: String,
+    id: u64,
+    output: Result<BlockOutput, BlockRunError>,
+}
+
+/// Task running [Block]'s computation.
+#[derive(Debug)]
+struct BlockTask {
+    id: u64,
+    handle: JoinHandle<()>,
+    queued: Option<BlockRunMode>,
+    persistent: bool,
+}
+
+/// Collection of currently running [Block]s.
+///
+/// Each Block is run in it's own task, which sends it's output
+/// as a [BlockRunResult] through a channel. At most one task per
+/// Block is running at a time. When Block is triggered while it is
+/// still running it's [BusyPolicy] decides what happens.
+///
+/// Tasks of [persistent](BlockMode::Persistent) Blocks never finish
+/// and send result for every line of output. Triggering them restarts them.
+#[derive(Debug)]
+struct BlockTasks {
+    tasks: HashMap<String, BlockTask>,
+    next_id: u64,
+    sender: mpsc::Sender<BlockRunResult>,
+}
+
+impl BlockTasks {
+    fn new(sender: mpsc::Sender<BlockRunResult>) -> Self {
+        Self {
+            tasks: HashMap::new(),
+            next_id: 0,
+            sender,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Stops task of a block with given name (if it is running).
+    fn cancel(&mut self, name: &str) {
+        if let Some(task) = self.tasks.remove(name) {
+            task.handle.abort();
+        }
+    }
+
+    /// Runs `block` in a new task, or applies it's [BusyPolicy]
+    /// if it is already running.
+    fn trigger(&mut self, name: &str, block: &Block, mode: BlockRunMode) {
+        match block.mode() {
+            BlockMode::Oneshot => {}
+            BlockMode::Persistent => return self.spawn_persistent(name, block, mode),
+            // There is no command to run.
+            BlockMode::Push => return,
+        }
+
+        if let Some(task) = self.tasks.get_mut(name) {
+            match block.busy_policy() {
+                BusyPolicy::Skip => return,
+                BusyPolicy::Queue => {
+                    task.queued = Some(mode);
+                    return;
+                }
+                BusyPolicy::Restart => task.handle.abort(),
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let computation = block.computation(mode);
+        let sender = self.sender.clone();
+        let task_name = name.to_string();
+        let handle = tokio::spawn(async move {
+            let output = computation.await;
+            let result = BlockRunResult {
+                name: task_name,
+                id,
+                output,
+            };
+            // If receiver was closed, then StatusBar has stopped running.
+            let _ = sender.send(result).await;
+        });
+
+        self.tasks.insert(
+            name.to_string(),
+            BlockTask {
+                id,
+                handle,
+                queued: None,
+                persistent: false,
+            },
+        );
+    }
+
+    /// (Re)starts persistent `block`.
+    fn spawn_persistent(&mut self, name: &str, block: &Block, mode: BlockRunMode) {
+        if let Some(task) = self.tasks.get(name) {
+            task.handle.abort();
+        }
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let (output_sender, mut output_receiver) = mpsc::channel(1);
+        let computation = block.persistent_computation(mode, output_sender);
+        let sender = self.sender.clone();
+        let task_name = name.to_string();
+        let handle = tokio::spawn(async move {
+            let forward = async move {
+                while let Some(output) = output_receiver.recv().await {
+                    let result = BlockRunResult {
+                        name: task_name.clone(),
+                        id,
+                        output,
+                    };
+                    if sender.send(result).await.is_err() {
+                        break;
+                    }
+                }
+            };
+            tokio::join!(computation, forward);
+        });
+
+        self.tasks.insert(
+            name.to_string(),
+            BlockTask {
+                id,
+                handle,
+                queued: None,
+                persistent: true,
+            },
+        );
+    }
+
+    /// Marks run with `id` of block `name` as finished and returns mode of a
+    /// queued run (if there is one). Returns `None` if this run was restarted
+    /// in meantime (and so it's result should be discarded).
+    ///
+    /// Persistent tasks are never marked as finished.
+    fn finish(&mut self, name: &str, id: u64) -> Option<Option<BlockRunMode>> {
+        match self.tasks.get(name) {
+            Some(task) if task.id == id && task.persistent => Some(None),
+            Some(task) if task.id == id => self.tasks.remove(name).map(|task| task.queued),
+            _ => None,
+        }
+    }
+}
+
+/// Tasks that request refreshing [Block]s at their intervals.
+///
+/// Each scheduler periodically sends name of it's Block through a channel.
+#[derive(Debug)]
+struct Schedulers {
+    handles: HashMap<String, JoinHandle<()>>,
+    sender: Option<mpsc::Sender<String>>,
+}
+
+impl Schedulers {
+    fn new(sender: mpsc::Sender<String>) -> Self {
+        Self {
+            handles: HashMap::new(),
+            sender: Some(sender),
+        }
+    }
+
+    /// Spawns scheduler of `block` (replacing previous one). Nothing is spawned
+    /// if `block` is not refreshed at interval or schedulers were closed.
+    fn spawn(&mut self, name: &str, block: &Block) {
+        self.cancel(name);
+
+        let sender = match &self.sender {
+            Some(sender) => sender.clone(),
+            None => return,
+        };
+        let mut scheduler = match block.get_scheduler() {
+            Some(scheduler) => scheduler,
+            None => return,
+        };
+        let task_name = name.to_string();
+        let alignment = block.alignment();
+
+        let handle = tokio::spawn(async move {
+            loop {
+                scheduler.tick().await;
+                if let Some(alignment) = alignment {
+                    // Realign to the wall clock, which could drift from monotonic clock.
+                    scheduler = aligned_scheduler(scheduler.period(), alignment);
+                }
+
+                if sender.send(task_name.clone()).await.is_err() {
+                    // receiver channel dropped or closed, so we finish as well
+                    break;
+                }
+            }
+        });
+        self.handles.insert(name.to_string(), handle);
+    }
+
+    /// Stops scheduler of a block with given name (if there is one).
+    fn cancel(&mut self, name: &str) {
+        if let Some(handle) = self.handles.remove(name) {
+            handle.abort();
+        }
+    }
+
+    /// Stops spawning new schedulers, so that channel is closed
+    /// once all running schedulers finish.
+    fn close(&mut self) {
+        self.sender = None;
+    }
+}
+
+/// Timers of [Block]s. They are used to expire texts [set](StatusBarMessage::Set)
+/// to Blocks after Block's TTL and to retry Block's failed runs.
+///
+/// Each timer sends name of it's Block through a channel when it fires.
+#[derive(Debug)]
+struct Timers {
+    handles: HashMap<String, JoinHandle<()>>,
+    sender: mpsc::Sender<String>,
+}
+
+impl Timers {
+    fn new(sender: mpsc::Sender<String>) -> Self {
+        Self {
+            handles: HashMap::new(),
+            sender,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Checks if timer of a block with given name hasn't fired yet.
+    fn contains(&self, name: &str) -> bool {
+        self.handles.contains_key(name)
+    }
+
+    /// Starts timer (replacing previous one) that fires after **delay**.
+    fn spawn(&mut self, name: &str, delay: Duration) {
+        self.cancel(name);
+
+        let sender = self.sender.clone();
+        let task_name = name.to_string();
+        let handle = tokio::spawn(async move {
+            time::sleep(delay).await;
+            // If receiver was closed, then StatusBar has stopped running.
+            let _ = sender.send(task_name).await;
+        });
+        self.handles.insert(name.to_string(), handle);
+    }
+
+    /// Stops timer of a block with given name (if there is one).
+    fn cancel(&mut self, name: &str) {
+        if let Some(handle) = self.handles.remove(name) {
+            handle.abort();
+        }
+    }
+
+    /// Marks timer of a block with given name as fired.
+    fn finish(&mut self, name: &str) {
+        self.handles.remove(name);
+    }
+}
+
+/// Stops running task, scheduler and timers (of it's text and
+/// of retrying it) of a block with given name.
+fn stop_block(
+    name: &str,
+    tasks: &mut BlockTasks,
+    schedulers: &mut Schedulers,
+    expirations: &mut Timers,
+    retries: &mut Timers,
+) {
+    tasks.cancel(name);
+    schedulers.cancel(name);
+    expirations.cancel(name);
+    retries.cancel(name);
+}
+
+/// Replaces running task and scheduler of `block` with new ones
+/// (and stops timers of it's text and of retrying it).
+fn restart_block(
+    name: &str,
+    block: &Block,
+    tasks: &mut BlockTasks,
+    schedulers: &mut Schedulers,
+    expirations: &mut Timers,
+    retries: &mut Timers,
+) {
+    tasks.cancel(name);
+    expirations.cancel(name);
+    retries.cancel(name);
+    schedulers.spawn(name, block);
+    tasks.trigger(name, block, BlockRunMode::Normal);
+}
+
+/// Names of [Block]s affected by [reloading](StatusBar::reload) configuration.
+#[derive(Debug, PartialEq, Clone, Default)]
+struct ReloadedBlocks {
+    /// Blocks that no longer exist.
+    removed: Vec<String>,
+    /// Blocks that are new or were reconfigured.
+    started: Vec<String>,
+}
+
 /// Error that represents failure to create StatusBar.
 #[derive(Debug, PartialEq, Clone)]
 pub enum StatusBarCreationError {
     /// Multiple blocks had the same name
     BlockIdError(String),
+    /// Builtin block (with this name) couldn't be created
+    BuiltinError(String, BuiltinError),
+    /// Block (with this name) has invalid format
+    FormatError(String, TemplateError),
+    /// Block (with this name) has invalid `hide_when_matches` regular expression
+    HideRegexError(String, regex::Error),
+    /// Block (with this name) has invalid value of a setting (described by message)
+    InvalidSetting(String, String),
+    /// Setting (with this name) was changed, but it can't be changed without restarting
+    RestartRequired(String),
 }
 
 impl fmt::Display for StatusBarCreationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let msg = match self {
             Self::BlockIdError(msg) => format!("Each block id should be unique\n\n{}", msg),
+            Self::BuiltinError(name, e) => format!("Block `{}`: {}", name, e),
+            Self::FormatError(name, e) => format!("Block `{}`: invalid format: {}", name, e),
+            Self::HideRegexError(name, e) => {
+                format!("Block `{}`: invalid hide_when_matches: {}", name, e)
+            }
+            Self::InvalidSetting(name, msg) => format!("Block `{}`: {}", name, msg),
+            Self::RestartRequired(setting) => {
+                format!("Changing `{}` requires restarting asyncdwmblocks", setting)
+            }
         };
 
         write!(f, "{}", msg)
@@ -56,6 +866,22 @@ impl fmt::Display for StatusBarCreationError {
 
 impl Error for StatusBarCreationError {}
 
+/// Returns error if **value** (in seconds) of **setting** of block
+/// with a given name is zero.
+fn check_seconds(
+    name: &str,
+    setting: &str,
+    value: Option<u64>,
+) -> Result<(), StatusBarCreationError> {
+    match value {
+        Some(0) => Err(StatusBarCreationError::InvalidSetting(
+            name.to_string(),
+            format!("{} must be at least 1 second", setting),
+        )),
+        _ => Ok(()),
+    }
+}
+
 /// This struct represents a status bar.
 ///
 /// `StatusBar` is a collection of `Block`s that can refresh them at
@@ -65,11 +891,13 @@ impl Error for StatusBarCreationError {}
 ///
 /// `StatusBar` can be created either manually by calling [new](StatusBar::new)
 /// or [try_from](StatusBar::try_from<Config>) [`Config`] (which is preferred way).
+/// To configure custom builtin blocks use [from_config](StatusBar::from_config).
 #[derive(Debug, PartialEq, Clone)]
 pub struct StatusBar {
     blocks: IndexMap<String, Block>,
     config: Arc<Config>,
     buff_size: Option<usize>,
+    builtins: Arc<Builtins>,
 }
 
 impl StatusBar {
@@ -125,15 +953,120 @@ impl StatusBar {
                 blocks: blocks_map,
                 config,
                 buff_size: None,
+                builtins: Arc::new(Builtins::new()),
             })
         }
     }
 
+    /// Creates new `StatusBar` from [`Config`], whose builtin blocks
+    /// are created from given registry of [`Builtins`].
+    ///
+    /// Registry is kept and used again when configuration is [reloaded](StatusBarMessage::Reload).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use asyncdwmblocks::builtin::Builtins;
+    /// use asyncdwmblocks::statusbar::StatusBar;
+    /// use asyncdwmblocks::config::Config;
+    ///
+    /// # fn doc() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = Config::default().arc();
+    /// let builtins = Builtins::new();
+    /// // Register custom builtins here.
+    /// let statusbar = StatusBar::from_config(config, Arc::new(builtins))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_config(
+        config: Arc<Config>,
+        builtins: Arc<Builtins>,
+    ) -> Result<Self, StatusBarCreationError> {
+        let mut blocks = Vec::with_capacity(config.statusbar.blocks.len());
+        for b in config.statusbar.blocks.iter() {
+            check_seconds(&b.name, "interval", b.interval)?;
+            check_seconds(&b.name, "timeout", b.timeout.or(config.block.timeout))?;
+            check_seconds(&b.name, "ttl", b.ttl)?;
+            if let Some(Err(err)) = b.retry.as_ref().map(Retry::validate) {
+                return Err(StatusBarCreationError::InvalidSetting(
+                    b.name.clone(),
+                    format!("invalid retry: {}", err),
+                ));
+            }
+            let block = match b.kind {
+                BlockType::Command => Block::new(
+                    b.command.clone(),
+                    b.args.clone(),
+                    b.interval,
+                    Arc::clone(&config),
+                ),
+                BlockType::Builtin => {
+                    let source = builtins
+                        .create(b)
+                        .map_err(|e| StatusBarCreationError::BuiltinError(b.name.clone(), e))?;
+                    Block::builtin(
+                        b.command.clone(),
+                        b.args.clone(),
+                        source,
+                        b.interval,
+                        Arc::clone(&config),
+                    )
+                }
+            };
+            let format = b
+                .format
+                .as_deref()
+                .map(|format| Template::parse(format, &[OUTPUT_PLACEHOLDER]))
+                .transpose()
+                .map_err(|e| StatusBarCreationError::FormatError(b.name.clone(), e))?;
+            let hide_rules = HideRules {
+                when_empty: b.hide_when_empty,
+                when_exit_code: b.hide_when_exit_code,
+                when_matches: b
+                    .hide_when_matches
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(|e| StatusBarCreationError::HideRegexError(b.name.clone(), e))?,
+            };
+            blocks.push(StatusBarBlock {
+                name: b.name.clone(),
+                block: block
+                    .with_format(format)
+                    .with_prefix(b.prefix.clone())
+                    .with_suffix(b.suffix.clone())
+                    .with_color(b.color.clone())
+                    .with_hide_rules(hide_rules)
+                    .with_timeout(b.timeout)
+                    .with_busy_policy(b.busy_policy)
+                    .with_retry(b.retry)
+                    .with_error_policy(b.error_policy)
+                    .with_error_marker(b.error_marker.clone())
+                    .with_mode(b.mode)
+                    .with_signal(b.signal)
+                    .with_ttl(b.ttl),
+            });
+        }
+
+        let mut status_bar = Self::new(blocks, config)?;
+        status_bar.builtins = builtins;
+        Ok(status_bar)
+    }
+
     /// Starts executing blocks asynchronously and sending results through a channel.
     ///
+    /// Each block (starting with it's first run) is run in it's own task, so slow blocks
+    /// do not delay other ones. Status bar is sent when output of some block has changed
+    /// (changes made within [coalescing window](crate::config::ConfigStatusBar::coalesce)
+    /// are sent together). If block is triggered while it is still running, then it's
+    /// [BusyPolicy] decides what happens.
+    ///
     /// This function requires two channel pairs to be created. One to send results of
     /// a status bar computation (**sender**) and the other to signal reloading specific
-    /// block (**reload**). This function can possibly run to infinity
+    /// block or all of them (**reload**). Every [StatusBarRequest] is answered
+    /// (if it expects it) as soon as it is accepted. Every sent status bar is also published
+    /// to [subscribers](StatusBarMessage::Subscribe). Blocks can be changed while status bar
+    /// is running with a [StatusBarHandle]. This function can possibly run to infinity
     /// (if there is at least one block with `Some` interval) and so it should be either
     /// spawned as a separate task, or should be placed at the end of method call.
     ///
@@ -170,99 +1103,352 @@ impl StatusBar {
     pub async fn run(
         &mut self,
         sender: mpsc::Sender<String>,
-        mut reload: mpsc::Receiver<BlockRefreshMessage>,
+        mut reload: mpsc::Receiver<StatusBarRequest>,
     ) {
-        self.init().await;
-        if sender.send(self.get_status_bar()).await.is_err() {
-            // Receiving channel was closed, so there is no point
-            // in sending new messages. Quit run.
-            return;
-        }
+        // Nothing was sent yet, so the first status bar is always sent.
+        let mut last_status: Option<String> = None;
 
+        // Schedulers can be spawned as long as blocks can be
+        // changed (by reloading configuration or inserting them).
         let (schedulers_sender, mut schedulers_receiver) = mpsc::channel(8);
-        for (index, block) in self.blocks.values().enumerate() {
-            if let Some(mut scheduler) = block.get_scheduler() {
-                let schedulers_sender = schedulers_sender.clone();
-                tokio::spawn(async move {
-                    loop {
-                        scheduler.tick().await;
-
-                        if schedulers_sender.send(index).await.is_err() {
-                            // receiver channel dropped or closed, so we finish as well
-                            break;
-                        }
-                    }
-                });
-            }
+        let mut schedulers = Schedulers::new(schedulers_sender);
+        for (name, block) in self.blocks.iter() {
+            schedulers.spawn(name, block);
         }
-        // drop unused sender
-        drop(schedulers_sender);
+
+        // Blocks are run in separate tasks, that send their
+        // results back through this channel.
+        let (results_sender, mut results_receiver) = mpsc::channel(8);
+        let mut tasks = BlockTasks::new(results_sender);
+        // Texts set to blocks expire after block's TTL and their names are sent back.
+        let (expirations_sender, mut expirations_receiver) = mpsc::channel(8);
+        let mut expirations = Timers::new(expirations_sender);
+        // Failed runs of blocks are retried after a delay and their names are sent back.
+        let (retries_sender, mut retries_receiver) = mpsc::channel(8);
+        let mut retries = Timers::new(retries_sender);
+        // Rendered status bars and changes of blocks are published to subscribers.
+        let (events, _) = broadcast::channel(32);
+        // The first runs of blocks are run in their tasks as well, so that
+        // status bar is rendered as their results arrive.
+        for (name, block) in self.blocks.iter() {
+            tasks.trigger(name, block, BlockRunMode::Normal);
+        }
+
+        // Status bar is rendered only when output of some block has changed.
+        // Changes made within coalescing window are rendered together.
+        let coalesce = Duration::from_millis(self.config.statusbar.coalesce);
+        // If no block is running, then there is nothing to wait for.
+        let mut render_at: Option<Instant> = tasks.is_empty().then(Instant::now);
 
         let mut reload_finished = false;
         let mut schedulers_finished = false;
         // In this loop we await signals to refresh blocks
         // as well as for custom block reloading using *reload*
-        // and we are sending result through *sender* channel.
+        // and for results of running blocks. We are sending
+        // result through *sender* channel.
         loop {
+            let mut changed = false;
             tokio::select! {
                 r = reload.recv(), if !reload_finished => {
                     match r {
-                        Some(message) => {
-                            let block: &mut Block = match self.get_block_by_name_mut(&message.name) {
-                                Some(block) => block,
-                                None => {
-                                    // For now ignore error and just continue
-                                    continue;
+                        Some(request) => {
+                            let mut render = false;
+                            let response = match request.message() {
+                                StatusBarMessage::Refresh(message) => {
+                                    match self.get_block_by_name(&message.name) {
+                                        Some(block) => {
+                                            tasks.trigger(&message.name, block, message.mode.clone());
+                                            StatusBarResponse::Ok
+                                        }
+                                        None => StatusBarResponse::UnknownBlock(message.name.clone()),
+                                    }
+                                }
+                                StatusBarMessage::RefreshAll => {
+                                    // Persistent blocks are not restarted.
+                                    for (name, block) in self.blocks.iter() {
+                                        if block.mode() == BlockMode::Oneshot {
+                                            tasks.trigger(name, block, BlockRunMode::Normal);
+                                        }
+                                    }
+                                    StatusBarResponse::Ok
+                                }
+                                StatusBarMessage::List => StatusBarResponse::List(self.list_blocks()),
+                                StatusBarMessage::Get(name) => match self.get_block_by_name(name) {
+                                    Some(block) => StatusBarResponse::Result(block.result().cloned()),
+                                    None => StatusBarResponse::UnknownBlock(name.clone()),
+                                },
+                                StatusBarMessage::Status => StatusBarResponse::Status(self.get_status_bar()),
+                                StatusBarMessage::Subscribe { blocks } => {
+                                    StatusBarResponse::Subscribed(StatusBarSubscription::new(
+                                        self.get_status_bar(),
+                                        *blocks,
+                                        events.subscribe(),
+                                    ))
+                                }
+                                StatusBarMessage::Reload(config) => match self.reload(Arc::clone(config)) {
+                                    Ok(ReloadedBlocks { removed, started }) => {
+                                        for name in removed.iter().chain(started.iter()) {
+                                            stop_block(name, &mut tasks, &mut schedulers, &mut expirations, &mut retries);
+                                        }
+                                        for name in started {
+                                            // Block was just created from config, so it exists.
+                                            let block = self.get_block_by_name(&name).unwrap();
+                                            restart_block(&name, block, &mut tasks, &mut schedulers, &mut expirations, &mut retries);
+                                        }
+                                        render = true;
+                                        StatusBarResponse::Ok
+                                    }
+                                    Err(err) => StatusBarResponse::InvalidConfig(err),
+                                },
+                                StatusBarMessage::Insert { index, name, block } => {
+                                    if self.insert_block(*index, name.clone(), block.as_ref().clone()) {
+                                        // Block was just inserted, so it exists.
+                                        let block = self.get_block_by_name(name).unwrap();
+                                        restart_block(name, block, &mut tasks, &mut schedulers, &mut expirations, &mut retries);
+                                        StatusBarResponse::Ok
+                                    } else {
+                                        StatusBarResponse::BlockExists(name.clone())
+                                    }
+                                }
+                                StatusBarMessage::Remove(name) => {
+                                    if self.blocks.shift_remove(name).is_some() {
+                                        stop_block(name, &mut tasks, &mut schedulers, &mut expirations, &mut retries);
+                                        render = true;
+                                        StatusBarResponse::Ok
+                                    } else {
+                                        StatusBarResponse::UnknownBlock(name.clone())
+                                    }
+                                }
+                                StatusBarMessage::Move { name, index } => {
+                                    if self.move_block(name, *index) {
+                                        render = true;
+                                        StatusBarResponse::Ok
+                                    } else {
+                                        StatusBarResponse::UnknownBlock(name.clone())
+                                    }
+                                }
+                                StatusBarMessage::Set { name, text } => {
+                                    match self.get_block_by_name_mut(name) {
+                                        Some(block) => {
+                                            if block.result() != Some(text) {
+                                                block.set_result(Some(text.clone()));
+                                                let _ = events.send(StatusBarEvent::Block {
+                                                    name: name.clone(),
+                                                    result: text.clone(),
+                                                });
+                                            }
+                                            match block.ttl() {
+                                                Some(ttl) => expirations.spawn(name, ttl),
+                                                None => expirations.cancel(name),
+                                            }
+                                            render = true;
+                                            StatusBarResponse::Ok
+                                        }
+                                        None => StatusBarResponse::UnknownBlock(name.clone()),
+                                    }
+                                }
+                                StatusBarMessage::Update { name, update } => {
+                                    match self.get_block_by_name_mut(name) {
+                                        Some(block) => {
+                                            update.clone().apply(block);
+                                            let block = self.get_block_by_name(name).unwrap();
+                                            restart_block(name, block, &mut tasks, &mut schedulers, &mut expirations, &mut retries);
+                                            StatusBarResponse::Ok
+                                        }
+                                        None => StatusBarResponse::UnknownBlock(name.clone()),
+                                    }
                                 }
                             };
-                            // TODO: crash on internal error
-                            // Ignore errors
-                            let _ = block.run(message.mode.clone()).await;
-
-                            if sender.send(self.get_status_bar()).await.is_err() {
-                                // Receiving channel was closed, so there is no point
-                                // in sending new messages. Quit run.
-                                return;
+                            request.respond(response);
+
+                            if render {
+                                // Removed, moved, set blocks and changed delimiter must be visible immediately.
+                                render_at = None;
+                                if !self.publish(&mut last_status, &sender, &events).await {
+                                    return;
+                                }
                             }
                         }
-                        None => reload_finished = true
+                        None => {
+                            reload_finished = true;
+                            // Blocks can't be changed anymore.
+                            schedulers.close();
+                        }
                     }
                 }
                 s = schedulers_receiver.recv(), if !schedulers_finished => {
                     match s {
-                        Some(index) => {
-                            // It is safe to index into self.blocks, because this index was created
-                            // while enumerating it's values.
-                            let block = &mut self.blocks[index];
-                            // Ignore errors
-                            let _ = block.run(BlockRunMode::Normal).await;
-
-                            if sender.send(self.get_status_bar()).await.is_err() {
-                                // Receiving channel was closed, so there is no point
-                                // in sending new messages. Quit run.
-                                return;
+                        Some(name) => {
+                            // Block might have been removed in meantime. There is also
+                            // no point in running it while it shows text that was set to it.
+                            if expirations.contains(&name) {
+                                continue;
+                            }
+                            if let Some(block) = self.get_block_by_name(&name) {
+                                tasks.trigger(&name, block, BlockRunMode::Normal);
                             }
                         }
                         None => schedulers_finished = true
                     }
                 }
+                Some(name) = expirations_receiver.recv(), if !expirations.is_empty() => {
+                    // Timer might have been restarted in meantime.
+                    if !expirations.contains(&name) {
+                        continue;
+                    }
+                    expirations.finish(&name);
+
+                    let block = match self.get_block_by_name_mut(&name) {
+                        Some(block) => block,
+                        None => continue,
+                    };
+                    if block.mode() == BlockMode::Push {
+                        block.set_result(None);
+                        let _ = events.send(StatusBarEvent::Block { name, result: String::new() });
+                        changed = true;
+                    } else {
+                        // Fall back to command's output.
+                        tasks.trigger(&name, block, BlockRunMode::Normal);
+                    }
+                }
+                Some(name) = retries_receiver.recv(), if !retries.is_empty() => {
+                    // Timer might have been restarted in meantime.
+                    if !retries.contains(&name) {
+                        continue;
+                    }
+                    retries.finish(&name);
+
+                    // Block showing text that was set to it is not retried
+                    // (it's command is run again when this text expires).
+                    if expirations.contains(&name) {
+                        continue;
+                    }
+                    if let Some(block) = self.get_block_by_name(&name) {
+                        if block.last_error().is_some() {
+                            tasks.trigger(&name, block, BlockRunMode::Normal);
+                        }
+                    }
+                }
+                Some(result) = results_receiver.recv(), if !tasks.is_empty() => {
+                    let BlockRunResult { name, id, output } = result;
+                    let queued = match tasks.finish(&name, id) {
+                        Some(queued) => queued,
+                        // This run was restarted, it's result is outdated.
+                        None => continue,
+                    };
+                    if expirations.contains(&name) {
+                        // Block shows text that was set to it, until it expires
+                        // (and then it's command is run again).
+                        continue;
+                    }
+
+                    if let Some(block) = self.get_block_by_name_mut(&name) {
+                        let previous = block.result().cloned();
+                        let previous_output = block.output().map(Cow::into_owned);
+                        // TODO: crash on internal error
+                        // Ignore errors
+                        let _ = block.update(output);
+
+                        match block.retry_delay() {
+                            Some(delay) => {
+                                block.start_retry();
+                                retries.spawn(&name, delay);
+                            }
+                            None => retries.cancel(&name),
+                        }
+
+                        if block.result() != previous.as_ref() {
+                            let result = block.result().cloned().unwrap_or_default();
+                            // There might be no subscribers. Ignore it.
+                            let _ = events.send(StatusBarEvent::Block { name: name.clone(), result });
+                        }
+                        changed = block.output().as_deref() != previous_output.as_deref();
+                    }
+                    if let (Some(mode), Some(block)) = (queued, self.get_block_by_name(&name)) {
+                        tasks.trigger(&name, block, mode);
+                    }
+
+                }
+                _ = time::sleep_until(render_at.unwrap_or_else(Instant::now)), if render_at.is_some() => {
+                    render_at = None;
+                    if !self.publish(&mut last_status, &sender, &events).await {
+                        // Receiving channel was closed, so there is no point
+                        // in sending new messages. Quit run.
+                        return;
+                    }
+                }
                 else => break
             };
+
+            if changed && render_at.is_none() {
+                render_at = Some(Instant::now() + coalesce);
+            }
+        }
+    }
+
+    /// Renders status bar and sends it through **sender** and to subscribers
+    /// of **events**, unless it is the same as **last** sent one.
+    ///
+    /// Returns `false` if receiving channel of **sender** was closed.
+    async fn publish(
+        &mut self,
+        last: &mut Option<String>,
+        sender: &mpsc::Sender<String>,
+        events: &broadcast::Sender<StatusBarEvent>,
+    ) -> bool {
+        let status = self.get_status_bar();
+        if last.as_ref() == Some(&status) {
+            return true;
         }
+        *last = Some(status.clone());
+        // There might be no subscribers. Ignore it.
+        let _ = events.send(StatusBarEvent::Status(status.clone()));
+
+        sender.send(status).await.is_ok()
     }
 
-    /// Collects `Block`s results and concatenates them into String.
+    /// Collects `Block`s [outputs](Block::output) (results rendered with their
+    /// format, prefix and suffix) and concatenates them into String.
     ///
     /// If `Block`s result is `None` then this block is skipped.
     /// If non of the blocks executed it's command and empty String
     /// is returned.
+    ///
+    /// If [statuscmd](crate::config::ConfigStatusBar::statuscmd) is enabled,
+    /// then results of blocks with signal are prefixed with this signal's byte.
     fn get_status_bar(&mut self) -> String {
+        match self.config.statusbar.protocol {
+            Protocol::Plain => self.get_plain_status_bar(),
+            #[cfg(feature = "i3bar")]
+            Protocol::I3bar => i3bar::render(
+                self.blocks
+                    .iter()
+                    .map(|(name, block)| (name.as_str(), block)),
+                !self.config.statusbar.delimiter.is_empty(),
+            ),
+            Protocol::Lemonbar => lemonbar::render(
+                self.blocks
+                    .iter()
+                    .map(|(name, block)| (name.as_str(), block)),
+                &self.config.statusbar.delimiter,
+            ),
+        }
+    }
+
+    /// Renders status bar in [plain](Protocol::Plain) protocol.
+    fn get_plain_status_bar(&mut self) -> String {
+        let statuscmd = self.config.statusbar.statuscmd;
         let mut blocks = self
             .blocks
             .iter()
             .map(|(_, block)| block)
-            .map(Block::result)
-            .flatten();
+            .filter_map(|block| {
+                let signal = block
+                    .signal()
+                    .filter(|s| statuscmd && (1..32).contains(s))
+                    .map(char::from);
+                block.output().map(|output| (signal, output))
+            });
 
         let first = blocks.next();
         if first.is_none() {
@@ -274,10 +1460,15 @@ impl StatusBar {
             None => String::new(),
         };
 
-        buffer.push_str(first.unwrap());
-        blocks.for_each(|r| {
+        let push_block = |buffer: &mut String, (signal, output): (Option<char>, Cow<str>)| {
+            buffer.extend(signal);
+            buffer.push_str(&output);
+        };
+
+        push_block(&mut buffer, first.unwrap());
+        blocks.for_each(|b| {
             buffer.push_str(&self.config.statusbar.delimiter);
-            buffer.push_str(r);
+            push_block(&mut buffer, b);
         });
 
         buffer.shrink_to_fit();
@@ -285,16 +1476,76 @@ impl StatusBar {
         buffer
     }
 
-    /// Initialises all `Block`s by awaiting completion of [running](Block::run) them.
-    async fn init(&mut self) {
-        let futures: Vec<_> = self
+    /// Replaces `Block`s with ones defined in **config** (and starts using **config**).
+    ///
+    /// Blocks that are defined the same way as before keep their results.
+    /// Changing [protocol](crate::config::ConfigStatusBar::protocol) is rejected,
+    /// because outputs are set up for it. On error StatusBar is left unchanged.
+    fn reload(&mut self, config: Arc<Config>) -> Result<ReloadedBlocks, StatusBarCreationError> {
+        if config.statusbar.protocol != self.config.statusbar.protocol {
+            return Err(StatusBarCreationError::RestartRequired(String::from(
+                "statusbar.protocol",
+            )));
+        }
+        let mut status_bar = Self::from_config(config, Arc::clone(&self.builtins))?;
+        let mut reloaded = ReloadedBlocks::default();
+
+        for (name, block) in status_bar.blocks.iter_mut() {
+            match self.blocks.get(name) {
+                Some(old) if old.same_definition(block) => block.keep_state_of(old),
+                _ => reloaded.started.push(name.clone()),
+            }
+        }
+        reloaded.removed = self
             .blocks
-            .iter_mut()
-            .map(|(_, block)| block)
-            .map(|b| b.run(BlockRunMode::Normal))
+            .keys()
+            .filter(|name| !status_bar.blocks.contains_key(*name))
+            .cloned()
             .collect();
 
-        let _ = join_all(futures).await;
+        *self = status_bar;
+        Ok(reloaded)
+    }
+
+    /// Inserts `block` at position `index` (clamped to number of `Block`s).
+    ///
+    /// Returns `false` (and doesn't insert it) if there already is a block with this name.
+    fn insert_block(&mut self, index: usize, name: String, block: Block) -> bool {
+        if self.blocks.contains_key(&name) {
+            return false;
+        }
+
+        let index = index.min(self.blocks.len());
+        let mut blocks: Vec<(String, Block)> = self.blocks.drain(..).collect();
+        blocks.insert(index, (name, block));
+        self.blocks = blocks.into_iter().collect();
+        true
+    }
+
+    /// Moves block with given name to position `index` (clamped to number of `Block`s).
+    ///
+    /// Returns `false` if there is no block with this name.
+    fn move_block(&mut self, name: &str, index: usize) -> bool {
+        match self.blocks.shift_remove(name) {
+            Some(block) => self.insert_block(index, name.to_string(), block),
+            None => false,
+        }
+    }
+
+    /// Returns descriptions of all `Block`s.
+    fn list_blocks(&self) -> Vec<BlockInfo> {
+        self.blocks
+            .iter()
+            .map(|(name, block)| BlockInfo {
+                name: name.clone(),
+                command: block.to_string(),
+                interval: block.interval().map(|i| i.as_secs()),
+            })
+            .collect()
+    }
+
+    fn get_block_by_name(&self, name: &str) -> Option<&Block> {
+        self.blocks.get(name)
     }
 
     fn get_block_by_name_mut(&mut self, name: &str) -> Option<&mut Block> {
@@ -305,21 +1556,7 @@ impl StatusBar {
 impl TryFrom<Arc<Config>> for StatusBar {
     type Error = StatusBarCreationError;
     fn try_from(config: Arc<Config>) -> Result<Self, Self::Error> {
-        let blocks = config
-            .statusbar
-            .blocks
-            .iter()
-            .map(|b| StatusBarBlock {
-                name: b.name.clone(),
-                block: Block::new(
-                    b.command.clone(),
-                    b.args.clone(),
-                    b.interval,
-                    Arc::clone(&config),
-                ),
-            })
-            .collect();
-        Self::new(blocks, config)
+        Self::from_config(config, Arc::new(Builtins::new()))
     }
 }
 
@@ -333,6 +1570,30 @@ mod tests {
 
     use pretty_assertions::assert_eq;
 
+    /// Receives status bars until **expected** one is received. Results of blocks
+    /// arrive in any order, so some status bars might be sent before it.
+    async fn recv_until(receiver: &mut mpsc::Receiver<String>, expected: &str) {
+        loop {
+            let result = timeout_at(Instant::now() + Duration::from_secs(3), receiver.recv());
+            match result.await.unwrap() {
+                Some(status) if status == expected => return,
+                Some(_) => {}
+                None => panic!("status bar `{}` was not sent", expected),
+            }
+        }
+    }
+
+    /// Runs **statusbar** until it sends **expected** status bar.
+    async fn run_until(mut statusbar: StatusBar, expected: &str) {
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (_reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            statusbar.run(result_sender, reload_receiver).await;
+        });
+        recv_until(&mut result_receiver, expected).await;
+    }
+
     fn setup_blocks_for_get_status_bar(data: Vec<Option<&str>>, config: Arc<Config>) -> StatusBar {
         let blocks: IndexMap<String, Block> = data
             .iter()
@@ -350,6 +1611,7 @@ mod tests {
             blocks,
             config,
             buff_size: None,
+            builtins: Arc::new(Builtins::new()),
         }
     }
 
@@ -405,8 +1667,248 @@ mod tests {
         );
     }
 
+    #[test]
+    fn statusbar_get_status_bar_statuscmd() {
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                delimiter: " ".into(),
+                statuscmd: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+        let mut statusbar =
+            setup_blocks_for_get_status_bar(vec![Some("A"), Some("B"), Some("C")], config);
+        let signals = [Some(1), None, Some(40)];
+        for ((_, block), signal) in statusbar.blocks.iter_mut().zip(signals) {
+            *block = block.clone().with_signal(signal);
+        }
+        assert_eq!(String::from("\u{1}A B C"), statusbar.get_status_bar());
+    }
+
+    #[test]
+    #[cfg(feature = "i3bar")]
+    fn statusbar_get_status_bar_i3bar() {
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                protocol: Protocol::I3bar,
+                delimiter: String::new(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+        let mut statusbar = setup_blocks_for_get_status_bar(vec![Some("a"), None], config);
+        assert_eq!(
+            statusbar.get_status_bar(),
+            String::from(r#"[{"name":"id_0","full_text":"a","separator":false}]"#)
+        );
+    }
+
+    #[test]
+    fn statusbar_get_status_bar_lemonbar() {
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                protocol: Protocol::Lemonbar,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+        let mut statusbar = setup_blocks_for_get_status_bar(vec![None, Some("100%")], config);
+        let status = statusbar.get_status_bar();
+        assert!(status.starts_with("%{A1:asyncdwmblocks-notify -b 1 'id_1':}"));
+        assert!(status.ends_with("100%%%{A}%{A}%{A}%{A}%{A}"));
+        assert!(!status.contains("id_0"));
+    }
+
+    #[test]
+    fn statusbar_get_status_bar_hidden() {
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                delimiter: " | ".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+        let mut statusbar = setup_blocks_for_get_status_bar(
+            vec![Some(""), Some("vpn: off"), Some("12:00")],
+            config,
+        );
+        let hide_rules = HideRules {
+            when_empty: true,
+            when_matches: Some(Regex::new("^vpn: off$").unwrap()),
+            ..Default::default()
+        };
+        for (_, block) in statusbar.blocks.iter_mut() {
+            *block = block.clone().with_hide_rules(hide_rules.clone());
+        }
+        assert_eq!(String::from("12:00"), statusbar.get_status_bar());
+
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                blocks: vec![config::ConfigStatusBarBlock {
+                    name: "vpn".into(),
+                    hide_when_matches: Some(String::from("(off")),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+        assert!(matches!(
+            StatusBar::try_from(config),
+            Err(StatusBarCreationError::HideRegexError(name, _)) if name == "vpn"
+        ));
+    }
+
+    #[test]
+    fn statusbar_invalid_timeout() {
+        let config = |timeout: Option<u64>, default: Option<u64>| {
+            Config {
+                statusbar: config::ConfigStatusBar {
+                    blocks: vec![config::ConfigStatusBarBlock {
+                        name: "weather".into(),
+                        command: "curl".into(),
+                        timeout,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                block: config::ConfigBlock {
+                    timeout: default,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+            .arc()
+        };
+        let error = StatusBarCreationError::InvalidSetting(
+            String::from("weather"),
+            String::from("timeout must be at least 1 second"),
+        );
+
+        assert_eq!(
+            StatusBar::try_from(config(Some(0), None)).unwrap_err(),
+            error
+        );
+        assert_eq!(
+            StatusBar::try_from(config(None, Some(0))).unwrap_err(),
+            error
+        );
+        assert!(StatusBar::try_from(config(Some(5), Some(0))).is_ok());
+        assert!(StatusBar::try_from(config(None, None)).is_ok());
+    }
+
+    #[test]
+    fn statusbar_invalid_ttl() {
+        let config = |ttl: Option<u64>| {
+            Config {
+                statusbar: config::ConfigStatusBar {
+                    blocks: vec![config::ConfigStatusBarBlock {
+                        name: "notifications".into(),
+                        mode: BlockMode::Push,
+                        ttl,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+            .arc()
+        };
+
+        assert_eq!(
+            StatusBar::try_from(config(Some(0))).unwrap_err(),
+            StatusBarCreationError::InvalidSetting(
+                String::from("notifications"),
+                String::from("ttl must be at least 1 second"),
+            )
+        );
+        assert!(StatusBar::try_from(config(Some(10))).is_ok());
+    }
+
+    #[test]
+    fn statusbar_invalid_retry() {
+        let config = |backoff: f64| {
+            Config {
+                statusbar: config::ConfigStatusBar {
+                    blocks: vec![config::ConfigStatusBarBlock {
+                        name: "weather".into(),
+                        command: "curl".into(),
+                        retry: Some(Retry {
+                            backoff,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+            .arc()
+        };
+
+        assert_eq!(
+            StatusBar::try_from(config(f64::NAN)).unwrap_err(),
+            StatusBarCreationError::InvalidSetting(
+                String::from("weather"),
+                String::from("invalid retry: backoff must be at least 1, got NaN"),
+            )
+        );
+        assert!(StatusBar::try_from(config(1.5)).is_ok());
+    }
+
+    #[test]
+    fn statusbar_get_status_bar_formatted() {
+        let block =
+            |name: &str, format: Option<&str>, prefix: Option<&str>| config::ConfigStatusBarBlock {
+                name: name.into(),
+                format: format.map(String::from),
+                prefix: prefix.map(String::from),
+                suffix: Some(String::from(";")),
+                ..Default::default()
+            };
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                delimiter: " ".into(),
+                blocks: vec![
+                    block("volume", Some("{output}%"), Some("🔊 ")),
+                    block("mail", Some("{{{output}}}"), None),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+        let mut statusbar = StatusBar::try_from(config).unwrap();
+        for (_, block) in statusbar.blocks.iter_mut() {
+            block.set_result(Some(String::from("7")));
+        }
+        assert_eq!(String::from("🔊 7%; {7};"), statusbar.get_status_bar());
+
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                blocks: vec![block("volume", Some("{volume}%"), None)],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+        assert_eq!(
+            StatusBar::try_from(config).unwrap_err(),
+            StatusBarCreationError::FormatError(
+                String::from("volume"),
+                TemplateError::UnknownPlaceholder(String::from("volume"))
+            )
+        );
+    }
+
     #[tokio::test]
-    async fn statusbar_init() {
+    async fn run_first_results() {
         let config = Config {
             statusbar: config::ConfigStatusBar {
                 delimiter: " | ".into(),
@@ -433,7 +1935,7 @@ mod tests {
         let current_date: DateTime<Utc> = DateTime::from(SystemTime::now());
         let current_date = current_date.format("%d/%m/%Y").to_string();
 
-        let mut statusbar = StatusBar::new(
+        let statusbar = StatusBar::new(
             vec![
                 StatusBarBlock {
                     name: "date".into(),
@@ -447,12 +1949,8 @@ mod tests {
             config,
         )
         .unwrap();
-        statusbar.init().await;
 
-        assert_eq!(
-            statusbar.get_status_bar(),
-            format!("{} | asyncdwmblocks v1", current_date)
-        );
+        run_until(statusbar, &format!("{} | asyncdwmblocks v1", current_date)).await;
     }
 
     #[test]
@@ -540,7 +2038,7 @@ mod tests {
     #[tokio::test]
     async fn run_intervals_reload() {
         let config = Config::default().arc();
-        let b = Block::new("date".into(), vec!["+%s".into()], None, Arc::clone(&config));
+        let b = Block::new("date".into(), vec!["+%N".into()], None, Arc::clone(&config));
         let mut status_bar = StatusBar::new(
             vec![StatusBarBlock {
                 name: "epoch".into(),
@@ -568,10 +2066,7 @@ mod tests {
         assert!(timeout.is_err());
 
         reload_sender
-            .send(BlockRefreshMessage::new(
-                "epoch".into(),
-                BlockRunMode::Normal,
-            ))
+            .send(BlockRefreshMessage::new("epoch".into(), BlockRunMode::Normal).into())
             .await
             .unwrap();
         let timeout = timeout_at(
@@ -615,10 +2110,7 @@ mod tests {
             assert!(timeout.is_err());
 
             reload_sender
-                .send(BlockRefreshMessage::new(
-                    "epoch".into(),
-                    BlockRunMode::Normal,
-                ))
+                .send(BlockRefreshMessage::new("epoch".into(), BlockRunMode::Normal).into())
                 .await
                 .unwrap();
             let timeout = timeout_at(
@@ -648,10 +2140,10 @@ mod tests {
         let config = Config::default().arc();
         let blocks: Vec<StatusBarBlock> = (0..NUM)
             .map(|i| StatusBarBlock {
-                name: format!("echo_{}", i),
+                name: format!("date_{}", i),
                 block: Block::new(
-                    "echo".into(),
-                    vec![i.to_string()],
+                    "date".into(),
+                    vec![format!("+{}:%N", i)],
                     Some(1),
                     Arc::clone(&config),
                 ),
@@ -666,18 +2158,27 @@ mod tests {
             status_bar.run(result_sender, reload_receiver).await;
         });
 
-        // initial run
-        let _ = result_receiver.recv().await;
+        // initial runs (their results might be rendered separately)
+        let mut initial = String::new();
+        while initial.split(' ').count() < NUM {
+            initial = result_receiver.recv().await.unwrap();
+        }
 
         sleep(Duration::from_secs(1) + Duration::from_millis(100)).await;
 
-        assert_eq!(
-            NUM,
-            (0..)
-                .map(|_| result_receiver.try_recv())
-                .take_while(|r| r.is_ok())
-                .count()
-        );
+        // Results of blocks might be rendered together, but every block has changed.
+        let last = (0..)
+            .map(|_| result_receiver.try_recv())
+            .take_while(|r| r.is_ok())
+            .last()
+            .unwrap()
+            .unwrap();
+        let changed = initial
+            .split(' ')
+            .zip(last.split(' '))
+            .filter(|(before, after)| before != after)
+            .count();
+        assert_eq!(changed, NUM);
     }
 
     #[tokio::test]
@@ -688,57 +2189,948 @@ mod tests {
                 command: String::from("echo"),
                 args: vec![String::from("I")],
                 interval: None,
+                ..Default::default()
             },
             config::ConfigStatusBarBlock {
                 name: String::from("block2"),
                 command: String::from("echo"),
                 args: vec![String::from("🦀!")],
                 interval: None,
+                ..Default::default()
             },
         ];
         let config = Config {
             statusbar: config::ConfigStatusBar {
                 blocks,
                 delimiter: String::from(" ❤️ "),
+                statuscmd: false,
+                coalesce: 0,
+                protocol: Protocol::Plain,
             },
             ..Default::default()
         }
         .arc();
 
-        let mut statusbar = StatusBar::try_from(config).unwrap();
-        statusbar.init().await;
-
-        assert_eq!(statusbar.get_status_bar(), String::from("I ❤️ 🦀!"));
+        let statusbar = StatusBar::try_from(config).unwrap();
+        run_until(statusbar, "I ❤️ 🦀!").await;
     }
 
-    #[test]
-    fn statusbar_multiple_ids_error() {
+    #[tokio::test]
+    async fn run_slow_block_does_not_block_others() {
         let config = Config::default().arc();
-        let blocks = vec![
-            StatusBarBlock {
-                name: "A".into(),
-                block: Block::new(String::from("1"), vec![], None, Arc::clone(&config)),
-            },
-            StatusBarBlock {
-                name: "B".into(),
-                block: Block::new(String::from("2"), vec![], None, Arc::clone(&config)),
-            },
-            StatusBarBlock {
-                name: "B".into(),
-                block: Block::new(String::from("3"), vec![], None, Arc::clone(&config)),
-            },
-            StatusBarBlock {
-                name: "A".into(),
-                block: Block::new(String::from("4"), vec![], None, Arc::clone(&config)),
-            },
-            StatusBarBlock {
-                name: "C".into(),
-                block: Block::new(String::from("5"), vec![], None, Arc::clone(&config)),
-            },
-        ];
+        let slow = Block::new("sleep".into(), vec!["2".into()], None, Arc::clone(&config));
+        let fast = Block::new("date".into(), vec!["+%N".into()], None, Arc::clone(&config));
+        let mut status_bar = StatusBar::new(
+            vec![
+                StatusBarBlock {
+                    name: "slow".into(),
+                    block: slow,
+                },
+                StatusBarBlock {
+                    name: "fast".into(),
+                    block: fast,
+                },
+            ],
+            config,
+        )
+        .unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+
+        // Initial run of a slow block delays neither rendering nor requests.
+        let result = timeout_at(
+            Instant::now() + Duration::from_secs(1),
+            result_receiver.recv(),
+        )
+        .await;
+        assert!(result.is_ok());
+        let (request, response) = StatusBarRequest::with_reply(StatusBarMessage::Status);
+        reload_sender.send(request).await.unwrap();
+        let response = timeout_at(Instant::now() + Duration::from_millis(100), response).await;
+        assert!(response.is_ok());
+
+        reload_sender
+            .send(BlockRefreshMessage::new("slow".into(), BlockRunMode::Normal).into())
+            .await
+            .unwrap();
+        reload_sender
+            .send(BlockRefreshMessage::new("fast".into(), BlockRunMode::Normal).into())
+            .await
+            .unwrap();
+
+        let result = timeout_at(
+            Instant::now() + Duration::from_millis(100),
+            result_receiver.recv(),
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn run_refresh_all() {
+        let config = Config {
+            statusbar: config::ConfigStatusBar {
+                coalesce: 200,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc();
+        let blocks = ["a", "b", "c"]
+            .iter()
+            .map(|name| StatusBarBlock {
+                name: name.to_string(),
+                block: Block::new(
+                    "date".into(),
+                    vec![format!("+{}%N", name)],
+                    None,
+                    Arc::clone(&config),
+                ),
+            })
+            .collect();
+        let mut status_bar = StatusBar::new(blocks, config).unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+
+        // initial runs (their results might be rendered separately)
+        let mut initial = String::new();
+        while initial.split(' ').count() < 3 {
+            initial = result_receiver.recv().await.unwrap();
+        }
+
+        reload_sender
+            .send(StatusBarMessage::RefreshAll.into())
+            .await
+            .unwrap();
+        drop(reload_sender);
+
+        let mut results = Vec::new();
+        while let Some(result) = result_receiver.recv().await {
+            results.push(result);
+        }
+
+        // Changes of all refreshed blocks are rendered together.
+        assert_eq!(results.len(), 1);
+        let blocks: Vec<char> = results[0]
+            .split(' ')
+            .filter_map(|block| block.chars().next())
+            .collect();
+        assert_eq!(blocks, vec!['a', 'b', 'c']);
+    }
+
+    #[tokio::test]
+    async fn run_skip_unchanged() {
+        let config = config_with_blocks(vec![("a", "a", Some(1)), ("b", "b", None)]);
+        let mut status_bar = StatusBar::try_from(config).unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+        recv_until(&mut result_receiver, "a b").await;
+
+        // Blocks were run, but their outputs didn't change.
+        reload_sender
+            .send(StatusBarMessage::RefreshAll.into())
+            .await
+            .unwrap();
+        let result = timeout_at(
+            Instant::now() + Duration::from_millis(1500),
+            result_receiver.recv(),
+        );
+        assert!(result.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn run_responds_to_requests() {
+        let config = Config::default().arc();
+        let b = Block::new("echo".into(), vec!["a".into()], None, Arc::clone(&config));
+        let mut status_bar = StatusBar::new(
+            vec![StatusBarBlock {
+                name: "block".into(),
+                block: b,
+            }],
+            config,
+        )
+        .unwrap();
+
+        let (result_sender, _result_receiver) = mpsc::channel(8);
+        let (reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+
+        let messages = vec![
+            (
+                BlockRefreshMessage::new("block".into(), BlockRunMode::Normal).into(),
+                StatusBarResponse::Ok,
+            ),
+            (
+                BlockRefreshMessage::new("blok".into(), BlockRunMode::Normal).into(),
+                StatusBarResponse::UnknownBlock("blok".into()),
+            ),
+            (StatusBarMessage::RefreshAll, StatusBarResponse::Ok),
+            (
+                StatusBarMessage::List,
+                StatusBarResponse::List(vec![BlockInfo {
+                    name: "block".into(),
+                    command: "echo a".into(),
+                    interval: None,
+                }]),
+            ),
+            (
+                StatusBarMessage::Get("blok".into()),
+                StatusBarResponse::UnknownBlock("blok".into()),
+            ),
+        ];
+        for (message, expected) in messages {
+            let (request, response) = StatusBarRequest::with_reply(message);
+            reload_sender.send(request).await.unwrap();
+            assert_eq!(response.await.unwrap(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn run_subscribe() {
+        let config = Config::default().arc();
+        let b = Block::new(
+            "sh".into(),
+            vec!["-c".into(), "echo x$BUTTON".into()],
+            None,
+            Arc::clone(&config),
+        );
+        let mut status_bar = StatusBar::new(
+            vec![StatusBarBlock {
+                name: "block".into(),
+                block: b,
+            }],
+            config,
+        )
+        .unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+        assert_eq!(result_receiver.recv().await, Some("x".into()));
+
+        let mut subscriptions = Vec::new();
+        for blocks in [false, true] {
+            let (request, response) =
+                StatusBarRequest::with_reply(StatusBarMessage::Subscribe { blocks });
+            reload_sender.send(request).await.unwrap();
+            match response.await.unwrap() {
+                StatusBarResponse::Subscribed(subscription) => subscriptions.push(subscription),
+                response => panic!("unexpected response {:?}", response),
+            }
+        }
+
+        let message = BlockRefreshMessage::new("block".into(), BlockRunMode::Button(3));
+        reload_sender.send(message.into()).await.unwrap();
+        assert_eq!(result_receiver.recv().await, Some("x3".into()));
+
+        let status = StatusBarEvent::Status;
+        let expected = vec![
+            vec![status("x".into()), status("x3".into())],
+            vec![
+                status("x".into()),
+                StatusBarEvent::Block {
+                    name: "block".into(),
+                    result: "x3".into(),
+                },
+                status("x3".into()),
+            ],
+        ];
+        for (mut subscription, expected) in subscriptions.into_iter().zip(expected) {
+            for event in expected {
+                assert_eq!(subscription.recv().await, Some(event));
+            }
+        }
+    }
+
+    fn config_with_blocks(blocks: Vec<(&str, &str, Option<u64>)>) -> Arc<Config> {
+        Config {
+            statusbar: config::ConfigStatusBar {
+                blocks: blocks
+                    .into_iter()
+                    .map(|(name, output, interval)| config::ConfigStatusBarBlock {
+                        name: name.into(),
+                        command: "echo".into(),
+                        args: vec![output.into()],
+                        interval,
+                        ..Default::default()
+                    })
+                    .collect(),
+                delimiter: " ".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc()
+    }
+
+    #[tokio::test]
+    async fn run_reload() {
+        let config = config_with_blocks(vec![
+            ("kept", "a", None),
+            ("removed", "b", Some(1)),
+            ("changed", "c", None),
+        ]);
+        let mut status_bar = StatusBar::try_from(config).unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+        recv_until(&mut result_receiver, "a b c").await;
+
+        // Blocks with duplicated names are rejected.
+        let config = config_with_blocks(vec![("kept", "a", None), ("kept", "b", None)]);
+        let (request, response) = StatusBarRequest::with_reply(StatusBarMessage::Reload(config));
+        reload_sender.send(request).await.unwrap();
+        assert!(matches!(
+            response.await.unwrap(),
+            StatusBarResponse::InvalidConfig(StatusBarCreationError::BlockIdError(_))
+        ));
+
+        let config = config_with_blocks(vec![
+            ("added", "d", None),
+            ("kept", "a", None),
+            ("changed", "e", None),
+        ]);
+        let (request, response) = StatusBarRequest::with_reply(StatusBarMessage::Reload(config));
+        reload_sender.send(request).await.unwrap();
+        assert_eq!(response.await.unwrap(), StatusBarResponse::Ok);
+
+        // Unchanged block keeps it's result, while new and changed blocks are run.
+        assert_eq!(result_receiver.recv().await, Some("a".into()));
+        // Results of new blocks might be rendered together.
+        recv_until(&mut result_receiver, "d a e").await;
+
+        // Scheduler of removed block was stopped.
+        let result = timeout_at(
+            Instant::now() + Duration::from_millis(1500),
+            result_receiver.recv(),
+        );
+        assert!(result.await.is_err());
+
+        let (request, response) = StatusBarRequest::with_reply(StatusBarMessage::List);
+        reload_sender.send(request).await.unwrap();
+        let names: Vec<String> = match response.await.unwrap() {
+            StatusBarResponse::List(blocks) => blocks.into_iter().map(|b| b.name).collect(),
+            response => panic!("unexpected response {:?}", response),
+        };
+        assert_eq!(names, vec!["added", "kept", "changed"]);
+    }
+
+    #[derive(Debug)]
+    struct Counter(u32);
+
+    #[async_trait::async_trait]
+    impl crate::block::BlockSource for Counter {
+        async fn produce(&mut self, _mode: BlockRunMode) -> Result<String, BlockRunError> {
+            self.0 += 1;
+            Ok(self.0.to_string())
+        }
+    }
+
+    fn config_with_builtin(builtin: &str, interval: Option<u64>) -> Arc<Config> {
+        Config {
+            statusbar: config::ConfigStatusBar {
+                blocks: vec![config::ConfigStatusBarBlock {
+                    name: "counter".into(),
+                    kind: config::BlockType::Builtin,
+                    command: builtin.into(),
+                    interval,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .arc()
+    }
+
+    /// Runs all blocks of **status_bar** (one after another).
+    async fn run_blocks(status_bar: &mut StatusBar) {
+        for (_, block) in status_bar.blocks.iter_mut() {
+            let _ = block.run(BlockRunMode::Normal).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn run_reload_invalid_settings() {
+        let config = config_with_blocks(vec![("a", "a", None)]);
+        let mut status_bar = StatusBar::try_from(config).unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+        assert_eq!(result_receiver.recv().await, Some("a".into()));
+
+        let mut timeout = config_with_blocks(vec![("b", "b", None)]);
+        Arc::make_mut(&mut timeout).statusbar.blocks[0].timeout = Some(0);
+        let interval = config_with_blocks(vec![("b", "b", Some(0))]);
+        for (config, setting) in [(timeout, "timeout"), (interval, "interval")] {
+            let (request, response) =
+                StatusBarRequest::with_reply(StatusBarMessage::Reload(config));
+            reload_sender.send(request).await.unwrap();
+            assert_eq!(
+                response.await.unwrap(),
+                StatusBarResponse::InvalidConfig(StatusBarCreationError::InvalidSetting(
+                    "b".into(),
+                    format!("{} must be at least 1 second", setting)
+                ))
+            );
+        }
+
+        // Status bar keeps running with it's previous configuration.
+        let (request, response) = StatusBarRequest::with_reply(StatusBarMessage::List);
+        reload_sender.send(request).await.unwrap();
+        assert_eq!(
+            response.await.unwrap(),
+            StatusBarResponse::List(vec![BlockInfo {
+                name: "a".into(),
+                command: "echo a".into(),
+                interval: None,
+            }])
+        );
+        let config = config_with_blocks(vec![("a", "a", None), ("c", "c", None)]);
+        let (request, response) = StatusBarRequest::with_reply(StatusBarMessage::Reload(config));
+        reload_sender.send(request).await.unwrap();
+        assert_eq!(response.await.unwrap(), StatusBarResponse::Ok);
+        recv_until(&mut result_receiver, "a c").await;
+    }
+
+    #[tokio::test]
+    async fn run_reload_protocol() {
+        let config = config_with_blocks(vec![("a", "a", None)]);
+        let mut status_bar = StatusBar::try_from(config).unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+        assert_eq!(result_receiver.recv().await, Some("a".into()));
+
+        // Outputs are set up for the protocol, so it can't be changed.
+        let mut config = config_with_blocks(vec![("a", "a", None), ("b", "b", None)]);
+        Arc::make_mut(&mut config).statusbar.protocol = Protocol::Lemonbar;
+        let (request, response) = StatusBarRequest::with_reply(StatusBarMessage::Reload(config));
+        reload_sender.send(request).await.unwrap();
+        assert_eq!(
+            response.await.unwrap(),
+            StatusBarResponse::InvalidConfig(StatusBarCreationError::RestartRequired(
+                "statusbar.protocol".into()
+            ))
+        );
+
+        let config = config_with_blocks(vec![("a", "a", None), ("c", "c", None)]);
+        let (request, response) = StatusBarRequest::with_reply(StatusBarMessage::Reload(config));
+        reload_sender.send(request).await.unwrap();
+        assert_eq!(response.await.unwrap(), StatusBarResponse::Ok);
+        recv_until(&mut result_receiver, "a c").await;
+    }
+
+    #[tokio::test]
+    async fn statusbar_from_config_builtins() {
+        let mut builtins = Builtins::empty();
+        builtins.register("counter", |_| Ok(Box::new(Counter(0))));
+        let builtins = Arc::new(builtins);
+
+        let err =
+            StatusBar::from_config(config_with_builtin("unknown", None), Arc::clone(&builtins))
+                .unwrap_err();
+        assert_eq!(
+            err,
+            StatusBarCreationError::BuiltinError(
+                "counter".into(),
+                BuiltinError::Unknown("unknown".into())
+            )
+        );
+
+        let mut status_bar =
+            StatusBar::from_config(config_with_builtin("counter", None), builtins).unwrap();
+        run_blocks(&mut status_bar).await;
+        assert_eq!(status_bar.get_status_bar(), "1");
+
+        // Builtin keeps it's state when configuration is reloaded without changing it.
+        let reloaded = status_bar
+            .reload(config_with_builtin("counter", None))
+            .unwrap();
+        assert!(reloaded.started.is_empty());
+        run_blocks(&mut status_bar).await;
+        assert_eq!(status_bar.get_status_bar(), "2");
+
+        let reloaded = status_bar
+            .reload(config_with_builtin("counter", Some(1)))
+            .unwrap();
+        assert_eq!(reloaded.started, vec![String::from("counter")]);
+        run_blocks(&mut status_bar).await;
+        assert_eq!(status_bar.get_status_bar(), "1");
+    }
+
+    #[tokio::test]
+    async fn run_handle_changes_blocks() {
+        let config = config_with_blocks(vec![("a", "a", None), ("b", "b", None)]);
+        let mut status_bar = StatusBar::try_from(Arc::clone(&config)).unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (handle, requests) = StatusBarHandle::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, requests).await;
+        });
+        recv_until(&mut result_receiver, "a b").await;
+
+        let block = Block::builtin(
+            "counter".into(),
+            vec![],
+            Box::new(Counter(0)),
+            Some(1),
+            Arc::clone(&config),
+        );
+        handle
+            .insert_block(1, "c".into(), block.clone())
+            .await
+            .unwrap();
+        assert_eq!(result_receiver.recv().await, Some("a 1 b".into()));
+        assert_eq!(
+            handle.insert_block(0, "c".into(), block).await,
+            Err(StatusBarHandleError::BlockExists("c".into()))
+        );
+
+        // Inserted block is refreshed at it's interval.
+        let result = timeout_at(
+            Instant::now() + Duration::from_millis(1500),
+            result_receiver.recv(),
+        );
+        assert_eq!(result.await.unwrap(), Some("a 2 b".into()));
+
+        handle.move_block("a", 10).await.unwrap();
+        assert_eq!(result_receiver.recv().await, Some("2 b a".into()));
+
+        let update = BlockUpdate::new()
+            .with_command("echo".into(), vec!["d".into()])
+            .with_interval(None);
+        handle.update_block("c", update).await.unwrap();
+        assert_eq!(result_receiver.recv().await, Some("d b a".into()));
+
+        handle.remove_block("b").await.unwrap();
+        assert_eq!(result_receiver.recv().await, Some("d a".into()));
+
+        // Changed block is no longer refreshed at interval.
+        let result = timeout_at(
+            Instant::now() + Duration::from_millis(1500),
+            result_receiver.recv(),
+        );
+        assert!(result.await.is_err());
+
+        assert_eq!(
+            handle.remove_block("b").await,
+            Err(StatusBarHandleError::UnknownBlock("b".into()))
+        );
+        assert_eq!(
+            handle.move_block("b", 0).await,
+            Err(StatusBarHandleError::UnknownBlock("b".into()))
+        );
+        assert_eq!(
+            handle.update_block("b", BlockUpdate::new()).await,
+            Err(StatusBarHandleError::UnknownBlock("b".into()))
+        );
+        let names: Vec<String> = match handle.request(StatusBarMessage::List).await.unwrap() {
+            StatusBarResponse::List(blocks) => blocks.into_iter().map(|b| b.name).collect(),
+            response => panic!("unexpected response {:?}", response),
+        };
+        assert_eq!(names, vec!["c", "a"]);
+    }
+
+    #[tokio::test]
+    async fn run_set_text() {
+        let config = Config::default().arc();
+        let push = Block::new(String::new(), vec![], None, Arc::clone(&config))
+            .with_mode(BlockMode::Push)
+            .with_ttl(Some(1));
+        let command = Block::new("echo".into(), vec!["a".into()], None, Arc::clone(&config))
+            .with_ttl(Some(1));
+        let mut status_bar = StatusBar::new(
+            vec![
+                StatusBarBlock {
+                    name: "push".into(),
+                    block: push,
+                },
+                StatusBarBlock {
+                    name: "command".into(),
+                    block: command,
+                },
+            ],
+            config,
+        )
+        .unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (handle, requests) = StatusBarHandle::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, requests).await;
+        });
+        assert_eq!(result_receiver.recv().await, Some("a".into()));
+
+        handle.set_text("push", "3 new".into()).await.unwrap();
+        assert_eq!(result_receiver.recv().await, Some("3 new a".into()));
+        handle.set_text("command", "b".into()).await.unwrap();
+        assert_eq!(result_receiver.recv().await, Some("3 new b".into()));
+        assert_eq!(
+            handle.set_text("missing", "c".into()).await,
+            Err(StatusBarHandleError::UnknownBlock("missing".into()))
+        );
+
+        // Block's command output is discarded while it shows text.
+        handle.request(StatusBarMessage::RefreshAll).await.unwrap();
+        let result = timeout_at(
+            Instant::now() + Duration::from_millis(200),
+            result_receiver.recv(),
+        );
+        assert!(result.await.is_err());
+
+        // When text expires push block is cleared and other block falls back
+        // to it's command's output (both changes might be rendered together).
+        recv_until(&mut result_receiver, "a").await;
+    }
+
+    async fn count_results_with_busy_policy(policy: BusyPolicy) -> (usize, String) {
+        let config = Config::default().arc();
+        let b = Block::new(
+            "sh".into(),
+            vec!["-c".into(), "sleep 0.2; echo $BUTTON".into()],
+            None,
+            Arc::clone(&config),
+        )
+        .with_busy_policy(Some(policy));
+        let mut status_bar = StatusBar::new(
+            vec![StatusBarBlock {
+                name: "block".into(),
+                block: b,
+            }],
+            config,
+        )
+        .unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+
+        // initial run
+        let _ = result_receiver.recv().await;
+
+        for button in 1..=3 {
+            reload_sender
+                .send(BlockRefreshMessage::new("block".into(), BlockRunMode::Button(button)).into())
+                .await
+                .unwrap();
+        }
+        drop(reload_sender);
+
+        let mut results = Vec::new();
+        while let Some(result) = result_receiver.recv().await {
+            results.push(result);
+        }
+
+        (results.len(), results.pop().unwrap())
+    }
+
+    #[tokio::test]
+    async fn run_busy_policy_skip() {
+        let (count, last) = count_results_with_busy_policy(BusyPolicy::Skip).await;
+        assert_eq!(count, 1);
+        assert_eq!(last, String::from("1"));
+    }
+
+    #[tokio::test]
+    async fn run_busy_policy_queue() {
+        let (count, last) = count_results_with_busy_policy(BusyPolicy::Queue).await;
+        assert_eq!(count, 2);
+        assert_eq!(last, String::from("3"));
+    }
+
+    #[tokio::test]
+    async fn run_busy_policy_restart() {
+        let (count, last) = count_results_with_busy_policy(BusyPolicy::Restart).await;
+        assert_eq!(count, 1);
+        assert_eq!(last, String::from("3"));
+    }
+
+    #[tokio::test]
+    async fn run_persistent_block() {
+        let config = Config::default().arc();
+        let b = Block::new(
+            "sh".into(),
+            vec!["-c".into(), "echo 1; sleep 0.1; echo 2; sleep 5".into()],
+            Some(1),
+            Arc::clone(&config),
+        )
+        .with_mode(BlockMode::Persistent);
+        let mut status_bar = StatusBar::new(
+            vec![StatusBarBlock {
+                name: "persistent".into(),
+                block: b,
+            }],
+            config,
+        )
+        .unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (_reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+
+        // every line of output is sent
+        assert_eq!(result_receiver.recv().await.unwrap(), String::from("1"));
+        assert_eq!(result_receiver.recv().await.unwrap(), String::from("2"));
+    }
+
+    #[tokio::test]
+    async fn run_persistent_block_remove() {
+        let config = Config::default().arc();
+        let b = Block::new(
+            "sh".into(),
+            vec!["-c".into(), "sleep 5 & echo $!; wait".into()],
+            None,
+            Arc::clone(&config),
+        )
+        .with_mode(BlockMode::Persistent);
+        let mut status_bar = StatusBar::new(
+            vec![StatusBarBlock {
+                name: "persistent".into(),
+                block: b,
+            }],
+            config,
+        )
+        .unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (handle, requests) = StatusBarHandle::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, requests).await;
+        });
+
+        // command prints pid of it's child
+        let pid = result_receiver.recv().await.unwrap();
+        assert!(crate::block::process_running(&pid));
+
+        handle.remove_block("persistent").await.unwrap();
+        assert!(
+            crate::block::process_exited(&pid).await,
+            "Child of removed persistent command is running."
+        );
+    }
+
+    #[tokio::test]
+    async fn run_persistent_block_restart() {
+        let config = Config::default().arc();
+        let b = Block::new(
+            "sh".into(),
+            vec!["-c".into(), "echo ${BUTTON:-0}; sleep 0.2; echo x".into()],
+            None,
+            Arc::clone(&config),
+        )
+        .with_mode(BlockMode::Persistent);
+        let mut status_bar = StatusBar::new(
+            vec![StatusBarBlock {
+                name: "persistent".into(),
+                block: b,
+            }],
+            config,
+        )
+        .unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (reload_sender, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+
+        assert_eq!(result_receiver.recv().await.unwrap(), String::from("0"));
+        assert_eq!(result_receiver.recv().await.unwrap(), String::from("x"));
+
+        // command exited, so it will be restarted after a delay
+        let result = timeout_at(
+            Instant::now() + Duration::from_secs(1) + Duration::from_millis(200),
+            result_receiver.recv(),
+        )
+        .await;
+        assert_eq!(result.unwrap().unwrap(), String::from("0"));
+
+        // refreshing restarts command immediately
+        reload_sender
+            .send(BlockRefreshMessage::new("persistent".into(), BlockRunMode::Button(2)).into())
+            .await
+            .unwrap();
+        let result = timeout_at(
+            Instant::now() + Duration::from_millis(200),
+            result_receiver.recv(),
+        )
+        .await;
+        assert_eq!(result.unwrap().unwrap(), String::from("2"));
+    }
+
+    #[test]
+    fn statusbar_multiple_ids_error() {
+        let config = Config::default().arc();
+        let blocks = vec![
+            StatusBarBlock {
+                name: "A".into(),
+                block: Block::new(String::from("1"), vec![], None, Arc::clone(&config)),
+            },
+            StatusBarBlock {
+                name: "B".into(),
+                block: Block::new(String::from("2"), vec![], None, Arc::clone(&config)),
+            },
+            StatusBarBlock {
+                name: "B".into(),
+                block: Block::new(String::from("3"), vec![], None, Arc::clone(&config)),
+            },
+            StatusBarBlock {
+                name: "A".into(),
+                block: Block::new(String::from("4"), vec![], None, Arc::clone(&config)),
+            },
+            StatusBarBlock {
+                name: "C".into(),
+                block: Block::new(String::from("5"), vec![], None, Arc::clone(&config)),
+            },
+        ];
 
         let statusbar = StatusBar::new(blocks, config);
 
         assert!(statusbar.is_err());
     }
+
+    #[tokio::test]
+    async fn run_retry() {
+        let config = Config::default().arc();
+        let marker =
+            std::env::temp_dir().join(format!("asyncdwmblocks-retry-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        // Fails on the first run and succeeds on the next ones.
+        let script = format!(
+            "test -e {0} && echo ok || {{ touch {0}; exit 1; }}",
+            marker.display()
+        );
+        let retry = Retry {
+            initial_delay: 1,
+            jitter: 0.0,
+            ..Default::default()
+        };
+        let b = Block::new(
+            "sh".into(),
+            vec!["-c".into(), script],
+            None,
+            Arc::clone(&config),
+        )
+        .with_retry(Some(retry));
+        let mut status_bar = StatusBar::new(
+            vec![StatusBarBlock {
+                name: "retried".into(),
+                block: b,
+            }],
+            config,
+        )
+        .unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (_, reload_receiver) = mpsc::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, reload_receiver).await;
+        });
+        assert_eq!(result_receiver.recv().await, Some(String::new()));
+
+        // Block has no interval, so only retry runs it again.
+        let result = timeout_at(
+            Instant::now() + Duration::from_secs(1) + Duration::from_millis(500),
+            result_receiver.recv(),
+        )
+        .await;
+        let _ = std::fs::remove_file(&marker);
+        assert_eq!(result, Ok(Some(String::from("ok"))));
+    }
+
+    #[tokio::test]
+    async fn run_retry_cancelled_by_update() {
+        let config = Config::default().arc();
+        let marker = std::env::temp_dir().join(format!(
+            "asyncdwmblocks-retry-update-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&marker);
+        let retry = Retry {
+            initial_delay: 1,
+            jitter: 0.0,
+            ..Default::default()
+        };
+        let b = Block::new(
+            "sh".into(),
+            vec!["-c".into(), "exit 1".into()],
+            None,
+            Arc::clone(&config),
+        )
+        .with_retry(Some(retry))
+        .with_busy_policy(Some(BusyPolicy::Queue));
+        let mut status_bar = StatusBar::new(
+            vec![StatusBarBlock {
+                name: "retried".into(),
+                block: b,
+            }],
+            config,
+        )
+        .unwrap();
+
+        let (result_sender, mut result_receiver) = mpsc::channel(8);
+        let (handle, requests) = StatusBarHandle::channel(8);
+
+        tokio::spawn(async move {
+            status_bar.run(result_sender, requests).await;
+        });
+        assert_eq!(result_receiver.recv().await, Some(String::new()));
+
+        // New command is still running when retry of the old one was due.
+        let script = format!("echo run >> {}; sleep 1.5; echo ok", marker.display());
+        let update = BlockUpdate::new().with_command("sh".into(), vec!["-c".into(), script]);
+        handle.update_block("retried", update).await.unwrap();
+        recv_until(&mut result_receiver, "ok").await;
+        sleep(Duration::from_millis(500)).await;
+
+        let runs = std::fs::read_to_string(&marker).unwrap_or_default();
+        let _ = std::fs::remove_file(&marker);
+        assert_eq!(runs, "run\n");
+    }
 }
@@ -5,6 +5,10 @@
 //! For more information consult their documentation.
 //! You may start looking at pub exports.
 
+pub mod random;
 pub mod split;
+pub mod template;
 
+pub use random::{random, SplitMix64};
 pub use split::SplitAtRN;
+pub use template::{Template, TemplateError};
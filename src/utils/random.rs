@@ -0,0 +1,112 @@
+//! This module defines [SplitMix64], a small pseudo-random number generator,
+//! and [random] function drawing numbers from a generator shared by whole process.
+//!
+//! Numbers are not cryptographically secure. They are meant only
+//! for things like spreading delays (jitter).
+
+use std::process;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Pseudo-random number generator [SplitMix64](https://prng.di.unimi.it/splitmix64.c).
+///
+/// # Example
+/// ```
+/// use asyncdwmblocks::utils::SplitMix64;
+///
+/// let mut generator = SplitMix64::new(0);
+/// assert_eq!(generator.next_u64(), 0xe220a8397b1dcdaf);
+/// assert!((0.0..1.0).contains(&generator.next_f64()));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Creates new `SplitMix64` with given **seed**.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates new `SplitMix64` seeded with current time and process id.
+    pub fn from_time() -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        Self::new(time ^ (process::id() as u64).rotate_left(32))
+    }
+
+    /// Returns next pseudo-random number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns next pseudo-random number from range `[0, 1)` (uniformly distributed).
+    pub fn next_f64(&mut self) -> f64 {
+        // 53 highest bits fill mantissa of f64.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Returns pseudo-random number from range `[0, 1)`, drawn from [SplitMix64]
+/// shared by whole process (seeded with current time on first use).
+pub fn random() -> f64 {
+    static GENERATOR: Mutex<Option<SplitMix64>> = Mutex::new(None);
+
+    let mut generator = match GENERATOR.lock() {
+        Ok(generator) => generator,
+        // Generator's state is always valid.
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    generator
+        .get_or_insert_with(SplitMix64::from_time)
+        .next_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splitmix64_reference_values() {
+        let mut generator = SplitMix64::new(0);
+        assert_eq!(generator.next_u64(), 0xe220a8397b1dcdaf);
+        assert_eq!(generator.next_u64(), 0x6e789e6aa1b965f4);
+        assert_eq!(generator.next_u64(), 0x06c45d188009454f);
+    }
+
+    #[test]
+    fn splitmix64_distribution() {
+        const SAMPLES: usize = 100_000;
+
+        let mut generator = SplitMix64::new(42);
+        let mut buckets = [0usize; 10];
+        let mut sum = 0.0;
+        for _ in 0..SAMPLES {
+            let number = generator.next_f64();
+            assert!((0.0..1.0).contains(&number));
+            buckets[(number * 10.0) as usize] += 1;
+            sum += number;
+        }
+
+        let mean = sum / SAMPLES as f64;
+        assert!((mean - 0.5).abs() < 0.01, "mean: {}", mean);
+        for bucket in buckets {
+            let expected = SAMPLES / 10;
+            assert!(bucket.abs_diff(expected) < expected / 20, "{:?}", buckets);
+        }
+    }
+
+    #[test]
+    fn random_range() {
+        let numbers: Vec<f64> = (0..100).map(|_| random()).collect();
+        assert!(numbers.iter().all(|number| (0.0..1.0).contains(number)));
+        assert!(numbers.windows(2).any(|pair| pair[0] != pair[1]));
+    }
+}
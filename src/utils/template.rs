@@ -0,0 +1,159 @@
+//! This module defines [Template]. A format string with
+//! named `{placeholders}` that are replaced by values.
+
+use std::error::Error;
+use std::fmt;
+
+/// Error returned when [Template] can't be parsed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TemplateError {
+    /// Placeholder was opened with `{`, but never closed.
+    Unclosed,
+    /// `}` was found outside of a placeholder.
+    Unopened,
+    /// Placeholder's name is not allowed.
+    UnknownPlaceholder(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Self::Unclosed => String::from("unclosed `{` in template"),
+            Self::Unopened => String::from("unexpected `}` in template"),
+            Self::UnknownPlaceholder(name) => format!("unknown placeholder `{{{}}}`", name),
+        };
+
+        write!(f, "{}", msg)
+    }
+}
+
+impl Error for TemplateError {}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Part {
+    Text(String),
+    Placeholder(String),
+}
+
+/// Format string with named placeholders.
+///
+/// Placeholders are written as `{name}`. To insert literal braces
+/// double them (`{{` and `}}`).
+///
+/// # Example
+/// ```
+/// use asyncdwmblocks::utils::Template;
+///
+/// let template = Template::parse("{{{used}/{total}}}", &["used", "total"]).unwrap();
+/// let rendered = template.render(|name| match name {
+///     "used" => String::from("3"),
+///     _ => String::from("8"),
+/// });
+///
+/// assert_eq!(rendered, "{3/8}");
+/// assert!(Template::parse("{free}", &["used", "total"]).is_err());
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses `template`, which can contain only placeholders from `allowed`.
+    pub fn parse(template: &str, allowed: &[&str]) -> Result<Self, TemplateError> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err(TemplateError::Unopened),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(TemplateError::Unclosed),
+                        }
+                    }
+                    if !allowed.contains(&name.as_str()) {
+                        return Err(TemplateError::UnknownPlaceholder(name));
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Placeholder(name));
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Checks if template contains placeholder `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::Placeholder(p) if p == name))
+    }
+
+    /// Renders template, replacing every placeholder with
+    /// a value returned by `value` for it's name.
+    pub fn render<F>(&self, mut value: F) -> String
+    where
+        F: FnMut(&str) -> String,
+    {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => rendered.push_str(text),
+                Part::Placeholder(name) => rendered.push_str(&value(name)),
+            }
+        }
+
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_parse_errors() {
+        assert_eq!(Template::parse("{a", &["a"]), Err(TemplateError::Unclosed));
+        assert_eq!(Template::parse("a}", &["a"]), Err(TemplateError::Unopened));
+        assert_eq!(
+            Template::parse("{b}", &["a"]),
+            Err(TemplateError::UnknownPlaceholder(String::from("b")))
+        );
+    }
+
+    #[test]
+    fn template_render() {
+        let template = Template::parse("{a}: {b}{a} {{}}", &["a", "b"]).unwrap();
+        assert!(template.contains("b"));
+        assert!(!template.contains("c"));
+        assert_eq!(template.render(|name| name.to_uppercase()), "A: BA {}");
+        // Escaped placeholder is not replaced (and it's name is not checked).
+        let template = Template::parse("{{c}} {{{a}}}", &["a"]).unwrap();
+        assert_eq!(template.render(|_| String::from("x")), "{c} {x}");
+        assert_eq!(
+            Template::parse("", &[]).unwrap().render(|_| String::new()),
+            ""
+        );
+    }
+}
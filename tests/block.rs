@@ -21,6 +21,7 @@ async fn run_mode_button_changed_env_variable() {
     let config = Config {
         block: config::ConfigBlock {
             clicked_env_variable: String::from("BTN"),
+            ..Default::default()
         },
         ..Config::default()
     }
@@ -0,0 +1,136 @@
+use asyncdwmblocks::block::{BlockRunError, BlockRunMode};
+use asyncdwmblocks::builtin::{BuiltinError, Builtins};
+use asyncdwmblocks::config::{BlockType, ConfigStatusBarBlock};
+
+const ROOT: &str = "root=./tests/assets/sysroot";
+
+fn block(builtin: &str, args: &[&str]) -> ConfigStatusBarBlock {
+    ConfigStatusBarBlock {
+        name: builtin.to_string(),
+        kind: BlockType::Builtin,
+        command: builtin.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+async fn produce(builtin: &str, args: &[&str]) -> Result<String, BlockRunError> {
+    let mut source = Builtins::new().create(&block(builtin, args)).unwrap();
+    source.produce(BlockRunMode::Normal).await
+}
+
+#[tokio::test]
+async fn builtin_cpu() {
+    let mut source = Builtins::new()
+        .create(&block("cpu", &[ROOT, "format=CPU {usage}%"]))
+        .unwrap();
+
+    // First run shows usage since boot.
+    let usage = source.produce(BlockRunMode::Normal).await.unwrap();
+    assert_eq!(usage, "CPU 40%");
+    // Nothing changed since the previous run.
+    let usage = source.produce(BlockRunMode::Normal).await.unwrap();
+    assert_eq!(usage, "CPU 0%");
+}
+
+#[tokio::test]
+async fn builtin_memory() {
+    assert_eq!(produce("memory", &[ROOT]).await.unwrap(), "2.0G/8.0G");
+    assert_eq!(
+        produce("memory", &[ROOT, "format={percent}% ({available} free)"])
+            .await
+            .unwrap(),
+        "25% (6.0G free)"
+    );
+}
+
+#[tokio::test]
+async fn builtin_load() {
+    assert_eq!(produce("load", &[ROOT]).await.unwrap(), "0.52");
+    assert_eq!(
+        produce("load", &[ROOT, "format={load1} {load5} {load15}"])
+            .await
+            .unwrap(),
+        "0.52 0.34 0.28"
+    );
+}
+
+#[tokio::test]
+async fn builtin_battery() {
+    // AC is skipped, because it is not a battery.
+    assert_eq!(
+        produce("battery", &[ROOT, "format={status} {capacity}%"])
+            .await
+            .unwrap(),
+        "Discharging 87%"
+    );
+    assert!(produce("battery", &[ROOT, "device=BAT1"]).await.is_err());
+}
+
+#[tokio::test]
+async fn builtin_backlight() {
+    assert_eq!(produce("backlight", &[ROOT]).await.unwrap(), "25%");
+}
+
+#[tokio::test]
+async fn builtin_temperature() {
+    assert_eq!(produce("temperature", &[ROOT]).await.unwrap(), "45°C");
+    assert_eq!(
+        produce(
+            "temperature",
+            &[ROOT, "sensor=hwmon1/temp1", "format={temp}"]
+        )
+        .await
+        .unwrap(),
+        "61"
+    );
+}
+
+#[tokio::test]
+async fn builtin_network() {
+    let mut source = Builtins::new()
+        .create(&block(
+            "network",
+            &[ROOT, "format={interface} {state} {down} {up}"],
+        ))
+        .unwrap();
+
+    // Interface of the default route is used. Nothing was transferred since the first run.
+    let output = source.produce(BlockRunMode::Normal).await.unwrap();
+    assert_eq!(output, "wlan0 up 0B 0B");
+    let output = source.produce(BlockRunMode::Normal).await.unwrap();
+    assert_eq!(output, "wlan0 up 0B 0B");
+
+    assert_eq!(
+        produce("network", &[ROOT, "interface=eth0", "format={state}"])
+            .await
+            .unwrap(),
+        "down"
+    );
+    assert!(produce("network", &[ROOT, "interface=eth1"]).await.is_err());
+}
+
+#[test]
+fn builtin_invalid_arguments() {
+    let builtins = Builtins::new();
+
+    for args in [
+        vec!["format={used}"],
+        vec!["format={usage"],
+        vec!["device=BAT0"],
+        vec!["usage"],
+    ] {
+        assert!(matches!(
+            builtins.create(&block("cpu", &args)),
+            Err(BuiltinError::InvalidConfig { .. })
+        ));
+    }
+    assert!(matches!(
+        builtins.create(&block("temperature", &["sensor=thermal_zone0/temp"])),
+        Err(BuiltinError::InvalidConfig { .. })
+    ));
+    assert!(matches!(
+        builtins.create(&block("network", &["unit=bauds"])),
+        Err(BuiltinError::InvalidConfig { .. })
+    ));
+}
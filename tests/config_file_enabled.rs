@@ -7,9 +7,11 @@ use std::path::PathBuf;
 use pretty_assertions::assert_eq;
 use rusty_fork::rusty_fork_test;
 
+use asyncdwmblocks::block::{BlockMode, BusyPolicy, ErrorPolicy, Retry};
 use asyncdwmblocks::config::{self, Config};
 #[cfg(feature = "ipc")]
 use asyncdwmblocks::ipc::ServerType;
+use asyncdwmblocks::statusbar::Protocol;
 
 #[tokio::test]
 async fn load_configuration_no_ipc() {
@@ -18,6 +20,8 @@ async fn load_configuration_no_ipc() {
         .unwrap();
 
     assert_eq!(config.statusbar.delimiter, String::from(" | "));
+    assert!(config.statusbar.statuscmd);
+    assert_eq!(config.statusbar.coalesce, 50);
     assert_eq!(
         config.statusbar.blocks,
         vec![
@@ -25,24 +29,95 @@ async fn load_configuration_no_ipc() {
                 name: String::from("volume"),
                 command: String::from("my_volume_script.sh"),
                 args: vec![],
-                interval: None
+                interval: None,
+                mode: BlockMode::Persistent,
+                ..Default::default()
             },
             config::ConfigStatusBarBlock {
                 name: String::from("battery"),
                 command: String::from("my_battery_script.sh"),
                 args: vec![],
-                interval: Some(60)
+                interval: Some(60),
+                timeout: Some(5),
+                signal: Some(10),
+                error_policy: Some(ErrorPolicy::Marker),
+                error_marker: Some(String::from("⚠ battery")),
+                retry: Some(Retry {
+                    max_attempts: 3,
+                    initial_delay: 2,
+                    ..Default::default()
+                }),
+                format: Some(String::from("{output}%")),
+                prefix: Some(String::from("BAT ")),
+                suffix: Some(String::from(" |")),
+                ..Default::default()
             },
             config::ConfigStatusBarBlock {
                 name: String::from("date"),
                 command: String::from("my_datetime_script.sh"),
                 args: vec![String::from("--my-arg 5"), String::from("today")],
-                interval: Some(1)
+                interval: Some(1),
+                busy_policy: Some(BusyPolicy::Restart),
+                ttl: Some(10),
+                hide_when_empty: true,
+                ..Default::default()
+            },
+            config::ConfigStatusBarBlock {
+                name: String::from("mail"),
+                command: String::new(),
+                mode: BlockMode::Push,
+                ttl: Some(600),
+                hide_when_exit_code: Some(1),
+                hide_when_matches: Some(String::from("^0$")),
+                ..Default::default()
             },
         ]
     );
 
     assert_eq!(config.block.clicked_env_variable, String::from("BTN"));
+    assert_eq!(config.block.timeout, Some(30));
+    assert_eq!(config.block.timeout_placeholder, Some(String::from("...")));
+    assert_eq!(config.block.busy_policy, BusyPolicy::Skip);
+    assert_eq!(config.block.error_policy, ErrorPolicy::Keep);
+    assert_eq!(config.block.error_marker, String::from("!"));
+
+    assert_eq!(
+        config.outputs,
+        vec![
+            config::ConfigOutput::Stdout,
+            config::ConfigOutput::File {
+                path: PathBuf::from("/tmp/asyncdwmblocks.log"),
+                append: true,
+            },
+            config::ConfigOutput::Fifo {
+                path: PathBuf::from("/tmp/asyncdwmblocks.fifo"),
+            },
+        ]
+    );
+}
+
+#[cfg(feature = "i3bar")]
+#[tokio::test]
+async fn load_configuration_i3bar() {
+    let config = Config::load_from_file("./tests/assets/config_i3bar.yaml")
+        .await
+        .unwrap();
+
+    assert_eq!(config.statusbar.protocol, Protocol::I3bar);
+    assert_eq!(
+        config.statusbar.blocks[0].color,
+        Some(String::from("#ffff00"))
+    );
+}
+
+#[tokio::test]
+async fn load_configuration_lemonbar() {
+    let config = Config::load_from_file("./tests/assets/config_lemonbar.yaml")
+        .await
+        .unwrap();
+
+    assert_eq!(config.statusbar.protocol, Protocol::Lemonbar);
+    assert_eq!(config.outputs, vec![config::ConfigOutput::Stdout]);
 }
 
 #[cfg(feature = "tcp")]
@@ -54,6 +129,8 @@ async fn load_configuration_tcp() {
 
     assert_eq!(config.ipc.server_type, ServerType::Tcp);
     assert_eq!(config.ipc.tcp.port, 44005);
+    assert_eq!(config.ipc.max_frame_size, 4096);
+    assert_eq!(config.ipc.idle_timeout, Some(10));
 }
 
 #[cfg(feature = "uds")]
@@ -0,0 +1,122 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+
+use asyncdwmblocks::config::ConfigOutput;
+use asyncdwmblocks::output::{self, Fifo, OutputError, Sink};
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("asyncdwmblocks-{}-{}", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[tokio::test]
+async fn output_file() {
+    let path = temp_path("file");
+    let mut sink = output::sink(&ConfigOutput::File {
+        path: path.clone(),
+        append: false,
+    })
+    .unwrap();
+    sink.write("a").await.unwrap();
+    // File is replaced, so reader of the previous one still sees it whole.
+    let mut previous = fs::File::open(&path).unwrap();
+    sink.write("b").await.unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "b\n");
+    let mut content = String::new();
+    previous.read_to_string(&mut content).unwrap();
+    assert_eq!(content, "a\n");
+    // Temporary file was renamed.
+    let temporary = format!(".{}.", path.file_name().unwrap().to_string_lossy());
+    assert!(!fs::read_dir(env::temp_dir()).unwrap().any(|entry| entry
+        .unwrap()
+        .file_name()
+        .to_string_lossy()
+        .starts_with(&temporary)));
+
+    let mut sink = output::sink(&ConfigOutput::File {
+        path: path.clone(),
+        append: true,
+    })
+    .unwrap();
+    sink.write("c").await.unwrap();
+    sink.write("d").await.unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "b\nc\nd\n");
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn output_fifo() {
+    let path = temp_path("fifo");
+    let mut sink = Fifo::new(path.clone()).unwrap();
+
+    // There is no reader, so status bar is skipped.
+    sink.write("skipped").await.unwrap();
+
+    let reader = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(&path)
+        .unwrap();
+    sink.write("a").await.unwrap();
+    sink.write("b").await.unwrap();
+    let lines: Vec<String> = BufReader::new(reader)
+        .lines()
+        .take(2)
+        .map(|line| line.unwrap())
+        .collect();
+    assert_eq!(lines, vec!["a", "b"]);
+
+    // Reader has gone.
+    sink.write("c").await.unwrap();
+
+    // Existing pipe is reused, but regular file is rejected.
+    assert!(Fifo::new(path.clone()).is_ok());
+    fs::remove_file(&path).unwrap();
+    fs::write(&path, "").unwrap();
+    assert!(matches!(
+        Fifo::new(path.clone()),
+        Err(OutputError::Fifo(..))
+    ));
+    fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn output_fifo_full() {
+    let path = temp_path("fifo-full");
+    let mut sink = Fifo::new(path.clone()).unwrap();
+    let mut reader = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(&path)
+        .unwrap();
+
+    // Pipe fills up, so later status bars are skipped, but never cut.
+    let status = "x".repeat(1000);
+    for _ in 0..100 {
+        sink.write(&status).await.unwrap();
+    }
+    let mut data = Vec::new();
+    let err = reader.read_to_end(&mut data).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    let data = String::from_utf8(data).unwrap();
+    let lines: Vec<&str> = data.lines().collect();
+    assert!(!lines.is_empty() && lines.len() < 100);
+    assert!(data.ends_with('\n'));
+    assert!(lines.iter().all(|line| *line == status));
+
+    // Status bars that can't be written at once are rejected.
+    let status = "x".repeat(libc::PIPE_BUF);
+    assert!(sink.write(&status).await.is_err());
+    sink.write("y").await.unwrap();
+    let mut data = String::new();
+    let _ = reader.read_to_string(&mut data);
+    assert_eq!(data, "y\n");
+
+    drop(reader);
+    fs::remove_file(&path).unwrap();
+}